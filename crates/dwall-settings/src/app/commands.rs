@@ -11,18 +11,37 @@ use std::{
 };
 
 use dwall::{
-    ColorScheme, DWALL_CONFIG_DIR, DWALL_LOG_DIR, DisplayMonitor,
-    config::{ImageFormat, Network},
-    domain::geography::check_location_permission,
+    ColorScheme, DWALL_CONFIG_DIR, DWALL_LOG_DIR, DisplayMonitor, Position,
+    config::{ConfigIssue, ImageFormat, Network, PositionSource},
+    domain::{
+        geography::check_location_permission,
+        visual::{
+            CUSTOMIZED_THEME_METADATA_FILENAME, CustomizedThemeMetadata, ImageCheck,
+            IntegrityReport, PhotoSolarOptions, ThemeManifest, ThemePackOptions, ValidationReport,
+            pack_theme,
+            theme_import::{
+                export_gnome_background, export_kde_wallpaper, import_ddw_theme,
+                import_gnome_background, import_heic_theme, import_kde_wallpaper,
+                import_photo_theme, write_heic_solar_config,
+            },
+            verify_theme,
+        },
+    },
     read_config_file as dwall_read_config, write_config_file as dwall_write_config,
 };
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager, ResourceId, Runtime, Url, Webview, WebviewWindow};
 use tauri_plugin_updater::UpdaterExt;
 
+use time::{Date, Offset};
+
 use crate::{
-    domain::{monitor::get_monitors, settings::Config, theme::validate_solar_theme},
-    error::DwallSettingsResult,
+    domain::{
+        monitor::get_monitors,
+        settings::Config,
+        theme::{theme_image_format, theme_validation_report, validate_solar_theme},
+    },
+    error::{DwallSettingsError, DwallSettingsResult},
     infrastructure::{
         filesystem::{find_files_in_dir, list_subdirectories, move_directory},
         network::download::ThemeDownloader,
@@ -59,6 +78,11 @@ pub async fn write_config_file(config: dwall::Config) -> DwallSettingsResult<()>
     dwall_write_config(&config).map_err(Into::into)
 }
 
+#[tauri::command]
+pub async fn validate_config_cmd(config: dwall::Config) -> DwallSettingsResult<Vec<ConfigIssue>> {
+    Ok(config.issues())
+}
+
 #[tauri::command]
 pub async fn open_dir(dir_path: Cow<'_, Path>) -> DwallSettingsResult<()> {
     open::that(dir_path.as_os_str()).map_err(|e| e.into())
@@ -102,10 +126,69 @@ pub async fn validate_theme_cmd(
     is_customized: bool,
     image_format: ImageFormat,
 ) -> DwallSettingsResult<()> {
+    let config = dwall_read_config()?;
+    let image_format = theme_image_format(
+        themes_directory,
+        theme_id,
+        is_customized,
+        image_format,
+        &config,
+    );
     validate_solar_theme(themes_directory, theme_id, is_customized, &image_format)
         .map_err(Into::into)
 }
 
+#[tauri::command]
+pub async fn get_theme_validation_report_cmd(
+    themes_directory: &Path,
+    theme_id: &str,
+    is_customized: bool,
+    image_format: ImageFormat,
+    image_check: ImageCheck,
+) -> DwallSettingsResult<ValidationReport> {
+    let config = dwall_read_config()?;
+    let image_format = theme_image_format(
+        themes_directory,
+        theme_id,
+        is_customized,
+        image_format,
+        &config,
+    );
+    Ok(theme_validation_report(
+        themes_directory,
+        theme_id,
+        is_customized,
+        &image_format,
+        image_check,
+    ))
+}
+
+/// Zips a customized theme with a manifest of its files, returning the manifest
+#[tauri::command]
+pub async fn pack_customized_theme_cmd(
+    customized_themes_directory: &Path,
+    theme_id: &str,
+    zip_path: &Path,
+) -> DwallSettingsResult<ThemeManifest> {
+    let theme_directory = customized_themes_directory.join(theme_id);
+    let options = ThemePackOptions::from_metadata(&theme_directory)?.ok_or_else(|| {
+        DwallSettingsError::Other(format!(
+            "Theme '{theme_id}' has no {CUSTOMIZED_THEME_METADATA_FILENAME}"
+        ))
+    })?;
+
+    pack_theme(&theme_directory, &options, zip_path).map_err(Into::into)
+}
+
+/// Checks an installed theme against the manifest it was packed with
+#[tauri::command]
+pub async fn verify_theme_cmd(
+    themes_directory: &Path,
+    theme_id: &str,
+) -> DwallSettingsResult<IntegrityReport> {
+    verify_theme(&themes_directory.join(theme_id)).map_err(Into::into)
+}
+
 #[tauri::command]
 pub async fn get_applied_theme_id_cmd(monitor_id: &str) -> DwallSettingsResult<Option<String>> {
     get_applied_theme_id(monitor_id)
@@ -260,14 +343,6 @@ pub struct CustomizedTheme {
     metadata: CustomizedThemeMetadata,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct CustomizedThemeMetadata {
-    image_format: ImageFormat,
-    theme_name: String,
-    author: String,
-    version: u16,
-}
-
 #[tauri::command]
 pub async fn get_customized_themes_cmd(
     customized_themes_directory: PathBuf,
@@ -284,19 +359,25 @@ pub async fn get_customized_themes_cmd(
         .into_iter()
         .filter(|p| p.components().next_back() != Some(Component::Normal(OsStr::new("backup"))))
     {
-        let metadata_file = subdir.join("metadata.toml");
+        let metadata_file = subdir.join(CUSTOMIZED_THEME_METADATA_FILENAME);
         let metadata_content = fs::read_to_string(&metadata_file)
             .inspect_err(|e| error!(error = ?e, "Failed to read metadata.toml"))?;
         let metadata: CustomizedThemeMetadata = toml::from_str(&metadata_content)
             .inspect_err(|e| error!(error = ?e, "Failed to parse metadata"))?;
 
-        let images = find_files_in_dir(&subdir.join("images"), metadata.image_format.as_str())
+        let images = find_files_in_dir(&subdir.join("images"), metadata.image_format().as_str())
             .await
             .inspect_err(|e| error!(error = ?e, "Failed to find images in directory"))?;
 
-        let thumbnails = find_files_in_dir(&subdir.join("thumbnails"), "avif")
-            .await
-            .inspect_err(|e| error!(error = ?e, "Failed to find avif files in directory"))?;
+        // Imported themes come without thumbnails; preview their full images instead
+        let thumbnails_directory = subdir.join("thumbnails");
+        let thumbnails = if thumbnails_directory.is_dir() {
+            find_files_in_dir(&thumbnails_directory, "avif")
+                .await
+                .inspect_err(|e| error!(error = ?e, "Failed to find avif files in directory"))?
+        } else {
+            images.clone()
+        };
 
         if images.len() != thumbnails.len() {
             error!(
@@ -308,12 +389,7 @@ pub async fn get_customized_themes_cmd(
         }
 
         themes.push(CustomizedTheme {
-            id: format!(
-                "{}-{}-v{}",
-                metadata.theme_name.replace(" ", "-"),
-                metadata.author,
-                metadata.version
-            ),
+            id: metadata.theme_id(),
             directory: subdir,
             thumbnails,
             metadata,
@@ -324,3 +400,161 @@ pub async fn get_customized_themes_cmd(
 
     Ok(themes)
 }
+
+/// Imports a WinDynamicDesktop `.ddw` package as a customized theme, returning its ID
+#[tauri::command]
+pub async fn import_ddw_theme_cmd(
+    ddw_path: PathBuf,
+    customized_themes_directory: PathBuf,
+) -> DwallSettingsResult<String> {
+    import_ddw_theme(&ddw_path, &customized_themes_directory).map_err(Into::into)
+}
+
+/// Writes the sun positions of a macOS dynamic HEIC wallpaper as a `solar.json`
+#[tauri::command]
+pub async fn write_heic_solar_config_cmd(
+    heic_path: PathBuf,
+    solar_config_path: PathBuf,
+) -> DwallSettingsResult<()> {
+    write_heic_solar_config(&heic_path, &solar_config_path).map_err(Into::into)
+}
+
+/// Imports a macOS dynamic HEIC wallpaper and its extracted frames as a customized theme,
+/// returning its ID
+#[tauri::command]
+pub async fn import_heic_theme_cmd(
+    heic_path: PathBuf,
+    frames_directory: PathBuf,
+    customized_themes_directory: PathBuf,
+) -> DwallSettingsResult<String> {
+    import_heic_theme(&heic_path, &frames_directory, &customized_themes_directory)
+        .map_err(Into::into)
+}
+
+/// Imports a GNOME background XML that repeats daily as a time-of-day customized theme,
+/// returning its ID
+#[tauri::command]
+pub async fn import_gnome_background_cmd(
+    xml_path: PathBuf,
+    customized_themes_directory: PathBuf,
+) -> DwallSettingsResult<String> {
+    import_gnome_background(&xml_path, &customized_themes_directory).map_err(Into::into)
+}
+
+/// Theme, place and date to export as a GNOME background
+#[derive(Debug, Deserialize)]
+pub struct GnomeExportOptions {
+    themes_directory: PathBuf,
+    theme_id: String,
+    is_customized: bool,
+    image_format: ImageFormat,
+    latitude: f64,
+    longitude: f64,
+    date: String,
+    xml_path: PathBuf,
+}
+
+/// Exports a solar theme as a GNOME background XML for one place and local date
+#[tauri::command]
+pub async fn export_gnome_background_cmd(options: GnomeExportOptions) -> DwallSettingsResult<()> {
+    let theme_directory = options.themes_directory.join(&options.theme_id);
+    let images_directory = if options.is_customized {
+        theme_directory.join("images")
+    } else {
+        theme_directory.join(options.image_format.as_str())
+    };
+    let date: Date = options
+        .date
+        .parse()
+        .map_err(|e: time::Error| DwallSettingsError::Other(e.to_string()))?;
+
+    export_gnome_background(
+        &theme_directory,
+        &images_directory,
+        &options.image_format,
+        Position::new(options.latitude, options.longitude, 0.0)?,
+        date,
+        Offset::local_offset(),
+        &options.xml_path,
+    )
+    .map_err(Into::into)
+}
+
+/// Imports a KDE dynamic wallpaper package, unpacked or zipped, as a customized theme,
+/// returning its ID
+#[tauri::command]
+pub async fn import_kde_wallpaper_cmd(
+    package_path: PathBuf,
+    customized_themes_directory: PathBuf,
+) -> DwallSettingsResult<String> {
+    import_kde_wallpaper(&package_path, &customized_themes_directory).map_err(Into::into)
+}
+
+/// Exports a solar or time-of-day theme as an unpacked KDE dynamic wallpaper package
+#[tauri::command]
+pub async fn export_kde_wallpaper_cmd(
+    themes_directory: PathBuf,
+    theme_id: &str,
+    is_customized: bool,
+    image_format: ImageFormat,
+    package_directory: PathBuf,
+) -> DwallSettingsResult<()> {
+    let theme_directory = themes_directory.join(theme_id);
+    let images_directory = if is_customized {
+        theme_directory.join("images")
+    } else {
+        theme_directory.join(image_format.as_str())
+    };
+
+    export_kde_wallpaper(
+        &theme_directory,
+        &images_directory,
+        &image_format,
+        theme_id,
+        &package_directory,
+    )
+    .map_err(Into::into)
+}
+
+/// Builds a solar customized theme from timelapse photos, returning its ID
+///
+/// Photos without GPS position are placed at the given coordinates, or at the
+/// configured manual position when none are given.
+#[tauri::command]
+pub async fn import_photo_theme_cmd(
+    photos_directory: PathBuf,
+    theme_name: &str,
+    author: &str,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    customized_themes_directory: PathBuf,
+) -> DwallSettingsResult<String> {
+    let config = dwall_read_config()?;
+
+    let location = match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Some(Position::new(latitude, longitude, 0.0)?),
+        _ => match config.position_source() {
+            PositionSource::Manual {
+                latitude,
+                longitude,
+                altitude,
+            } => Some(Position::new(*latitude, *longitude, *altitude)?),
+            PositionSource::Automatic { .. } => None,
+        },
+    };
+
+    let mut options = PhotoSolarOptions::new(Offset::local_offset());
+    if let Some(location) = location {
+        options = options.with_location(location);
+    }
+
+    import_photo_theme(
+        &photos_directory,
+        theme_name,
+        author,
+        &options,
+        &config,
+        &customized_themes_directory,
+    )
+    .map_err(Into::into)
+}
@@ -5,8 +5,8 @@
 use std::io::Read;
 use std::path::Path;
 
-use dwall::ThemeValidator;
-use dwall::config::ImageFormat;
+use dwall::config::{Config, ImageFormat};
+use dwall::domain::visual::{ImageCheck, ThemeValidator, ValidationReport, select_image_format};
 use serde::Deserialize;
 use serde_json::Value;
 
@@ -15,6 +15,26 @@ struct DaemonLogEntry {
     fields: Value,
 }
 
+/// Format the theme's images are checked in: `requested` if the theme has it,
+/// otherwise the first of the configured fallbacks it has
+pub fn theme_image_format(
+    themes_directory: &Path,
+    theme_id: &str,
+    is_customized: bool,
+    requested: ImageFormat,
+    config: &Config,
+) -> ImageFormat {
+    let mut preference = vec![requested.clone()];
+    preference.extend(config.image_format_fallbacks().iter().cloned());
+    select_image_format(
+        &themes_directory.join(theme_id),
+        is_customized,
+        &preference,
+        config.convert_image_formats(),
+    )
+    .unwrap_or(requested)
+}
+
 /// Validates a theme against the solar theme specification
 pub fn validate_solar_theme(
     themes_directory: &Path,
@@ -25,6 +45,23 @@ pub fn validate_solar_theme(
     ThemeValidator::validate(themes_directory, theme_id, is_customized, image_format)
 }
 
+/// Checks a theme and lists every issue found, errors and warnings alike
+pub fn theme_validation_report(
+    themes_directory: &Path,
+    theme_id: &str,
+    is_customized: bool,
+    image_format: &ImageFormat,
+    image_check: ImageCheck,
+) -> ValidationReport {
+    ThemeValidator::report(
+        themes_directory,
+        theme_id,
+        is_customized,
+        image_format,
+        image_check,
+    )
+}
+
 /// Attempts to read the most recent error from the daemon log file
 ///
 /// Returns the error message if found, or None if no error was found or the log file couldn't be read
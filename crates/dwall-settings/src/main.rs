@@ -60,7 +60,11 @@ async fn main() -> DwallSettingsResult<()> {
             commands::show_window,
             commands::read_config_file,
             commands::write_config_file,
+            commands::validate_config_cmd,
             commands::validate_theme_cmd,
+            commands::get_theme_validation_report_cmd,
+            commands::pack_customized_theme_cmd,
+            commands::verify_theme_cmd,
             commands::apply_theme_cmd,
             commands::get_applied_theme_id_cmd,
             commands::check_auto_start,
@@ -81,6 +85,14 @@ async fn main() -> DwallSettingsResult<()> {
             commands::open_privacy_location_settings,
             commands::check_for_updates_cmd,
             commands::get_customized_themes_cmd,
+            commands::import_ddw_theme_cmd,
+            commands::write_heic_solar_config_cmd,
+            commands::import_heic_theme_cmd,
+            commands::import_gnome_background_cmd,
+            commands::export_gnome_background_cmd,
+            commands::import_kde_wallpaper_cmd,
+            commands::export_kde_wallpaper_cmd,
+            commands::import_photo_theme_cmd,
         ]);
 
     if cfg!(debug_assertions) {
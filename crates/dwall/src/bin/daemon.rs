@@ -1,6 +1,12 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use dwall::core::daemon::DaemonApplication;
+use dwall::{
+    core::{
+        cli::{Command, USAGE},
+        daemon::DaemonApplication,
+    },
+    read_config_file,
+};
 use logging::Logger;
 
 fn main() -> dwall::DwallResult<()> {
@@ -18,6 +24,49 @@ fn main() -> dwall::DwallResult<()> {
             .init()?;
     }
 
-    let mut app = DaemonApplication::new();
-    app.run()
+    let command = Command::parse(std::env::args().skip(1))?;
+    #[cfg(not(debug_assertions))]
+    if !matches!(command, Command::Daemon) {
+        attach_parent_console();
+    }
+
+    match command {
+        Command::Daemon => {
+            let mut app = DaemonApplication::new();
+            app.run()
+        }
+        Command::Help => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        Command::SolarPath(command) => command.run(&read_config_file()?),
+        Command::PhotoTheme(command) => command.run(&read_config_file()?),
+        Command::ValidateTheme(command) => {
+            if !command.run(&read_config_file()?)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::PackTheme(command) => command.run(&read_config_file()?),
+        Command::VerifyTheme(command) => {
+            if !command.run(&read_config_file()?)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::ConfigSchema(command) => command.run(),
+    }
+}
+
+/// Connects stdout and stderr to the console the command was started from
+///
+/// Release builds use the windows subsystem so the daemon opens no console window,
+/// which also leaves the CLI subcommands without one to print to.
+#[cfg(not(debug_assertions))]
+fn attach_parent_console() {
+    use windows::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+
+    // Fails when there is no parent console, such as when started from Explorer;
+    // output is then discarded unless it was redirected
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
 }
@@ -1,38 +1,109 @@
 use std::{
     collections::HashMap,
+    fmt,
+    net::IpAddr,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
 };
 
+use schemars::{JsonSchema, Schema, schema_for};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    Position,
+    domain::{
+        time::{AtmosphericConditions, SolarAlgorithm, SolarModel},
+        visual::{Severity, wallpaper::SelectionPolicy},
+    },
     error::{ConfigError, DwallResult},
     lazy::DWALL_CONFIG_DIR,
 };
 
+/// Version of the `config.toml` layout written by this release
+///
+/// Bump it together with a new step in the migration chain whenever a release
+/// renames, removes or reinterprets a setting.
+pub const CONFIG_VERSION: u32 = 1;
+
 // Configuration constants
-const DEFAULT_INTERVAL_SECONDS: u16 = 15;
+const DEFAULT_INTERVAL_SECONDS: u16 = 600;
 const MIN_INTERVAL_SECONDS: u16 = 1;
 const MAX_INTERVAL_SECONDS: u16 = 3600;
 const DEFAULT_AUTO_DETECT_COLOR_SCHEME: bool = true;
 const DEFAULT_LOCK_SCREEN_WALLPAPER_ENABLED: bool = true;
 const DEFAULT_TITLE_BAR_COLOR_FOLLOWS_WINDOWS_THEME: bool = false;
-
-#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone)]
+const DEFAULT_TEMPERATURE_CELSIUS: f64 = 10.0;
+const MIN_PRESSURE_HPA: f64 = 300.0;
+const MAX_PRESSURE_HPA: f64 = 1100.0;
+const MIN_TEMPERATURE_CELSIUS: f64 = -60.0;
+const MAX_TEMPERATURE_CELSIUS: f64 = 60.0;
+const MAX_HORIZON_HEIGHT_METERS: f64 = 9000.0;
+pub(crate) const MIN_DELTA_T_SECONDS: f64 = -100.0;
+pub(crate) const MAX_DELTA_T_SECONDS: f64 = 300.0;
+const DEFAULT_IMAGE_SWITCH_MARGIN_DEGREES: f64 = 1.0;
+const MAX_IMAGE_SWITCH_MARGIN_DEGREES: f64 = 30.0;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum ImageFormat {
     #[default]
     Jpeg,
     Png,
+    Webp,
+    Avif,
+    Jxl,
 }
 
 impl ImageFormat {
+    /// Every supported format
+    pub const ALL: [ImageFormat; 5] = [
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::Webp,
+        ImageFormat::Avif,
+        ImageFormat::Jxl,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             ImageFormat::Jpeg => "jpg",
             ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Avif => "avif",
+            ImageFormat::Jxl => "jxl",
+        }
+    }
+
+    /// Format of files ending in `extension`, ignoring case
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "png" => Some(ImageFormat::Png),
+            "webp" => Some(ImageFormat::Webp),
+            "avif" => Some(ImageFormat::Avif),
+            "jxl" => Some(ImageFormat::Jxl),
+            _ => None,
         }
     }
+
+    /// The matching format of the image library; it does not know JPEG XL
+    pub(crate) fn codec(&self) -> Option<image::ImageFormat> {
+        match self {
+            ImageFormat::Jpeg => Some(image::ImageFormat::Jpeg),
+            ImageFormat::Png => Some(image::ImageFormat::Png),
+            ImageFormat::Webp => Some(image::ImageFormat::WebP),
+            ImageFormat::Avif => Some(image::ImageFormat::Avif),
+            ImageFormat::Jxl => None,
+        }
+    }
+
+    /// Whether this build can decode images of the format
+    ///
+    /// Only JPEG, PNG and WebP decoders are built in; AVIF and JPEG XL images can be
+    /// shown as they are, but not converted, blended or fully validated.
+    pub(crate) fn is_decodable(&self) -> bool {
+        self.codec().is_some_and(|codec| codec.reading_enabled())
+    }
 }
 
 // impl From<&ImageFormat> for &str {
@@ -44,7 +115,7 @@ impl ImageFormat {
 //     }
 // }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone)]
 #[serde(rename_all = "UPPERCASE", tag = "type")]
 pub enum PositionSource {
     Automatic {
@@ -55,7 +126,9 @@ pub enum PositionSource {
     },
 
     Manual {
+        #[schemars(range(min = -90.0, max = 90.0))]
         latitude: f64,
+        #[schemars(range(min = -180.0, max = 180.0))]
         longitude: f64,
         #[serde(default = "default_altitude")]
         altitude: f64,
@@ -85,20 +158,46 @@ fn default_update_on_each_calculation() -> bool {
     false
 }
 
-impl PositionSource {
-    pub fn validate(&self) -> bool {
-        match *self {
-            PositionSource::Automatic { .. } => true,
-            PositionSource::Manual {
-                latitude,
-                longitude,
-                ..
-            } => (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude),
+/// Weather at the observer, used to correct atmospheric refraction
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone)]
+pub struct Atmosphere {
+    /// Surface air pressure in hPa
+    ///
+    /// Estimated from the observer's altitude with the standard atmosphere when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = MIN_PRESSURE_HPA, max = MAX_PRESSURE_HPA))]
+    pressure: Option<f64>,
+
+    /// Surface air temperature in °C
+    #[serde(default = "default_temperature")]
+    #[schemars(range(min = MIN_TEMPERATURE_CELSIUS, max = MAX_TEMPERATURE_CELSIUS))]
+    temperature: f64,
+}
+
+fn default_temperature() -> f64 {
+    DEFAULT_TEMPERATURE_CELSIUS
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            pressure: None,
+            temperature: default_temperature(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+impl Atmosphere {
+    /// Resolves the conditions at the given observer position
+    pub fn conditions_at(&self, position: &Position) -> AtmosphericConditions {
+        let pressure = self
+            .pressure
+            .unwrap_or_else(|| AtmosphericConditions::standard_pressure_at(position.altitude()));
+        AtmosphericConditions::new(pressure, self.temperature)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum MonitorSpecificWallpapers {
     All(String),
@@ -125,15 +224,123 @@ impl MonitorSpecificWallpapers {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum Network {
     GitHubMirrorTemplate(String),
     Socks5 { host: String, port: u16 },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+impl Network {
+    fn check(&self, issues: &mut Vec<ConfigIssue>) {
+        match self {
+            // An empty template turns the mirror off
+            Network::GitHubMirrorTemplate(template) if template.is_empty() => {}
+            Network::GitHubMirrorTemplate(template) => check_mirror_template(issues, template),
+            Network::Socks5 { host, port } => {
+                if host.trim().is_empty() {
+                    issues.push(ConfigIssue::error(&["network", "host"], "host is empty"));
+                } else if host.contains("://") {
+                    issues.push(ConfigIssue::error(
+                        &["network", "host"],
+                        format!("'{host}' must be a host name or IP address, not a URL"),
+                    ));
+                } else if !is_valid_host(host) {
+                    issues.push(ConfigIssue::error(
+                        &["network", "host"],
+                        format!("'{host}' is not a valid host name or IP address"),
+                    ));
+                }
+
+                if *port == 0 {
+                    issues.push(ConfigIssue::error(
+                        &["network", "port"],
+                        "port must be between 1 and 65535",
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Placeholders replaced in a GitHub mirror template
+const MIRROR_TEMPLATE_PLACEHOLDERS: [&str; 4] = ["<owner>", "<repo>", "<version>", "<asset>"];
+
+fn check_mirror_template(issues: &mut Vec<ConfigIssue>, template: &str) {
+    const FIELD: &[&str] = &["network"];
+
+    if !template.starts_with("https://") && !template.starts_with("http://") {
+        issues.push(ConfigIssue::error(
+            FIELD,
+            format!("mirror template '{template}' must start with https:// or http://"),
+        ));
+    }
+
+    let mut rest = template;
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else {
+            issues.push(ConfigIssue::error(
+                FIELD,
+                format!(
+                    "mirror template has an unclosed placeholder '{}'",
+                    &rest[start..]
+                ),
+            ));
+            break;
+        };
+
+        let placeholder = &rest[start..=start + length];
+        if !MIRROR_TEMPLATE_PLACEHOLDERS.contains(&placeholder) {
+            issues.push(ConfigIssue::error(
+                FIELD,
+                format!(
+                    "mirror template has an unknown placeholder '{placeholder}', expected {}",
+                    MIRROR_TEMPLATE_PLACEHOLDERS.join(", ")
+                ),
+            ));
+        }
+        rest = &rest[start + length + 1..];
+    }
+
+    if !template.contains("<asset>") {
+        issues.push(ConfigIssue::warning(
+            FIELD,
+            "mirror template has no <asset> placeholder, so every download gets the same URL",
+        ));
+    }
+}
+
+/// Whether `host` is an IP address or a DNS host name
+fn is_valid_host(host: &str) -> bool {
+    let unbracketed = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if unbracketed.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+
+    let name = host.strip_suffix('.').unwrap_or(host);
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Settings of the daemon and the settings app, stored in `config.toml`
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+#[schemars(title = "Dwall configuration")]
 pub struct Config {
+    /// Layout version of the file
+    ///
+    /// Files of older versions are migrated before they are deserialized.
+    #[serde(default = "default_config_version")]
+    version: u32,
+
     /// Network configuration for specifying a GitHub mirror template or SOCKS5 proxy.
     ///
     /// Only used in dwall-settings.
@@ -151,18 +358,57 @@ pub struct Config {
     #[serde(default = "default_image_format")]
     image_format: ImageFormat,
 
-    #[serde(alias = "coordinate_source", default = "default_position_source")]
+    /// Formats tried in order for themes without images in `image_format`
+    #[serde(default = "default_image_format_fallbacks")]
+    image_format_fallbacks: Vec<ImageFormat>,
+
+    /// Convert a theme's images into the cache when it has none in a preferred format
+    #[serde(default)]
+    convert_image_formats: bool,
+
+    #[serde(default = "default_position_source")]
     position_source: PositionSource,
 
-    #[serde(
-        alias = "auto_detect_color_mode",
-        default = "default_auto_detect_color_scheme"
-    )]
+    /// Air pressure and temperature used for refraction near the horizon
+    #[serde(default)]
+    atmosphere: Atmosphere,
+
+    /// Height in meters above the terrain that forms the horizon, such as on a hillside
+    /// or a high floor
+    ///
+    /// The lower visible horizon moves sunrise earlier and sunset later. This is not
+    /// the altitude above sea level, which only estimates the air pressure.
+    #[serde(default)]
+    #[schemars(range(min = 0.0, max = MAX_HORIZON_HEIGHT_METERS))]
+    horizon_height: f64,
+
+    /// Algorithm used to compute the sun position
+    #[serde(default)]
+    solar_algorithm: SolarAlgorithm,
+
+    #[serde(default = "default_auto_detect_color_scheme")]
     auto_detect_color_scheme: bool,
 
     #[serde(default = "default_lock_screen_wallpaper_enabled")]
     lock_screen_wallpaper_enabled: bool,
 
+    /// Cross-fade between the theme images on either side of the sun instead of
+    /// snapping to one
+    ///
+    /// Blended files are rendered into the cache directory and reused. Themes shown
+    /// in AVIF or JPEG XL cannot be decoded and switch between single images instead.
+    #[serde(default)]
+    blend_wallpapers: bool,
+
+    /// How much closer to the sun, in degrees, another image must be before it
+    /// replaces the current one
+    ///
+    /// Stops the wallpaper from flapping between two images at a near tie. Not used
+    /// with `blend_wallpapers`, where the cross-fade already changes gradually.
+    #[serde(default = "default_image_switch_margin")]
+    #[schemars(range(min = 0.0, max = MAX_IMAGE_SWITCH_MARGIN_DEGREES))]
+    image_switch_margin: f64,
+
     #[serde(default = "default_themes_directory")]
     themes_directory: PathBuf,
 
@@ -173,19 +419,140 @@ pub struct Config {
     #[serde(default = "default_monitor_specific_wallpapers")]
     monitor_specific_wallpapers: MonitorSpecificWallpapers,
 
-    /// Time interval for detecting solar altitude angle and azimuth angle
-    /// Measured in seconds, range: `[MIN_INTERVAL_SECONDS, MAX_INTERVAL_SECONDS]`
-    #[serde(
-        default = "default_interval",
-        deserialize_with = "deserialize_interval"
-    )]
+    /// Maximum time the daemon sleeps between two solar theme cycles
+    ///
+    /// The daemon normally wakes exactly when the next wallpaper or color scheme
+    /// change is predicted; this caps the sleep so that position updates and
+    /// external changes are still picked up.
+    /// Measured in seconds, from 1 to 3600
+    #[serde(default = "default_interval")]
+    #[schemars(range(min = MIN_INTERVAL_SECONDS, max = MAX_INTERVAL_SECONDS))]
     interval: u16,
 }
 
+/// A problem with one setting, found by [`Config::issues`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    severity: Severity,
+    /// Keys leading to the setting from the top of the file, such as
+    /// `["position_source", "latitude"]`; map keys such as monitor IDs are kept whole
+    field: Vec<String>,
+    message: String,
+}
+
+impl ConfigIssue {
+    fn error(field: &[&str], message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, field, message)
+    }
+
+    fn warning(field: &[&str], message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, field, message)
+    }
+
+    fn new(severity: Severity, field: &[&str], message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            field: field.iter().map(|key| key.to_string()).collect(),
+            message: message.into(),
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn field(&self) -> &[String] {
+        &self.field
+    }
+
+    /// Field keys joined with dots, for messages
+    pub fn field_path(&self) -> String {
+        self.field.join(".")
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field_path(), self.message)
+    }
+}
+
+fn check_range(
+    issues: &mut Vec<ConfigIssue>,
+    field: &[&str],
+    value: f64,
+    range: RangeInclusive<f64>,
+    unit: &str,
+) {
+    if !range.contains(&value) {
+        issues.push(ConfigIssue::error(
+            field,
+            format!(
+                "must be between {}{unit} and {}{unit}, got {value}{unit}",
+                range.start(),
+                range.end()
+            ),
+        ));
+    }
+}
+
+fn check_directory(issues: &mut Vec<ConfigIssue>, field: &str, path: &Path) {
+    if path.is_dir() {
+        return;
+    }
+
+    if path.exists() {
+        issues.push(ConfigIssue::error(
+            &[field],
+            format!("'{}' is not a directory", path.display()),
+        ));
+    } else {
+        issues.push(ConfigIssue::warning(
+            &[field],
+            format!("directory '{}' does not exist", path.display()),
+        ));
+    }
+}
+
+/// JSON Schema of `config.toml`, with the ranges [`Config::validate`] enforces
+pub fn config_json_schema() -> Schema {
+    let mut schema = schema_for!(Config);
+
+    // Directory defaults depend on the user profile, so they stay out of the schema
+    if let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        for key in ["themes_directory", "customized_themes_directory"] {
+            if let Some(property) = properties
+                .get_mut(key)
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                property.remove("default");
+            }
+        }
+    }
+
+    schema
+}
+
+fn default_config_version() -> u32 {
+    CONFIG_VERSION
+}
+
 fn default_image_format() -> ImageFormat {
     Default::default()
 }
 
+/// Formats Windows shows without installing extra codecs
+fn default_image_format_fallbacks() -> Vec<ImageFormat> {
+    vec![ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::Webp]
+}
+
 fn default_title_bar_color_follows_windows_theme() -> bool {
     DEFAULT_TITLE_BAR_COLOR_FOLLOWS_WINDOWS_THEME
 }
@@ -206,6 +573,10 @@ fn default_lock_screen_wallpaper_enabled() -> bool {
     DEFAULT_LOCK_SCREEN_WALLPAPER_ENABLED
 }
 
+fn default_image_switch_margin() -> f64 {
+    DEFAULT_IMAGE_SWITCH_MARGIN_DEGREES
+}
+
 fn default_interval() -> u16 {
     DEFAULT_INTERVAL_SECONDS
 }
@@ -222,46 +593,176 @@ fn default_monitor_specific_wallpapers() -> MonitorSpecificWallpapers {
     MonitorSpecificWallpapers::Specific(HashMap::new())
 }
 
-/// Custom deserializer for interval field with range validation
-fn deserialize_interval<'de, D>(deserializer: D) -> Result<u16, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let value = u16::deserialize(deserializer)?;
-    if !(MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS).contains(&value) {
-        return Err(serde::de::Error::custom(format!(
-            "interval must be between {} and {}, got {}",
-            MIN_INTERVAL_SECONDS, MAX_INTERVAL_SECONDS, value
-        )));
-    }
-    Ok(value)
-}
-
 impl Config {
     /// Validates the configuration values
     ///
-    /// Checks if the interval is within the valid range and if the coordinate source is valid
+    /// Warnings are logged; the configuration is rejected with every error found.
     pub fn validate(&self) -> DwallResult<()> {
-        if self.interval < MIN_INTERVAL_SECONDS || self.interval > MAX_INTERVAL_SECONDS {
+        let (errors, warnings): (Vec<ConfigIssue>, Vec<ConfigIssue>) = self
+            .issues()
+            .into_iter()
+            .partition(|issue| issue.severity() == Severity::Error);
+
+        for warning in &warnings {
+            warn!(
+                field = %warning.field_path(),
+                message = warning.message(),
+                "Configuration issue"
+            );
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        for error in &errors {
             error!(
-                "Interval validation failed: min={MIN_INTERVAL_SECONDS}, max={MAX_INTERVAL_SECONDS}, interval={}",
-                self.interval
+                field = %error.field_path(),
+                message = error.message(),
+                "Invalid configuration value"
             );
-            return Err(ConfigError::Validation {
-                reason: "Interval is out of range".to_string(),
-            }
-            .into());
+        }
+        Err(ConfigError::Validation { issues: errors }.into())
+    }
+
+    /// Checks every setting and lists the issues found, errors and warnings alike
+    ///
+    /// Settings that refer to the file system, such as theme directories and the
+    /// themes assigned to monitors, only produce warnings when missing: they may be
+    /// created later, for example by the first theme download.
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        if let Some(network) = &self.network {
+            network.check(&mut issues);
         }
 
-        if !self.position_source.validate() {
-            error!("Latitude or longitude is invalid");
-            return Err(ConfigError::Validation {
-                reason: "Latitude or longitude is invalid".to_string(),
+        if let PositionSource::Manual {
+            latitude,
+            longitude,
+            ..
+        } = self.position_source
+        {
+            check_range(
+                &mut issues,
+                &["position_source", "latitude"],
+                latitude,
+                -90.0..=90.0,
+                "°",
+            );
+            check_range(
+                &mut issues,
+                &["position_source", "longitude"],
+                longitude,
+                -180.0..=180.0,
+                "°",
+            );
+        }
+
+        if let Some(pressure) = self.atmosphere.pressure {
+            check_range(
+                &mut issues,
+                &["atmosphere", "pressure"],
+                pressure,
+                MIN_PRESSURE_HPA..=MAX_PRESSURE_HPA,
+                " hPa",
+            );
+        }
+        check_range(
+            &mut issues,
+            &["atmosphere", "temperature"],
+            self.atmosphere.temperature,
+            MIN_TEMPERATURE_CELSIUS..=MAX_TEMPERATURE_CELSIUS,
+            " °C",
+        );
+
+        check_range(
+            &mut issues,
+            &["horizon_height"],
+            self.horizon_height,
+            0.0..=MAX_HORIZON_HEIGHT_METERS,
+            " m",
+        );
+
+        if let SolarAlgorithm::Spa {
+            delta_t: Some(delta_t),
+        } = self.solar_algorithm
+        {
+            check_range(
+                &mut issues,
+                &["solar_algorithm", "delta_t"],
+                delta_t,
+                MIN_DELTA_T_SECONDS..=MAX_DELTA_T_SECONDS,
+                " s",
+            );
+        }
+
+        check_range(
+            &mut issues,
+            &["image_switch_margin"],
+            self.image_switch_margin,
+            0.0..=MAX_IMAGE_SWITCH_MARGIN_DEGREES,
+            "°",
+        );
+
+        check_directory(&mut issues, "themes_directory", &self.themes_directory);
+        check_directory(
+            &mut issues,
+            "customized_themes_directory",
+            &self.customized_themes_directory,
+        );
+        self.check_monitor_themes(&mut issues);
+
+        if !(MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS).contains(&self.interval) {
+            issues.push(ConfigIssue::error(
+                &["interval"],
+                format!(
+                    "must be between {MIN_INTERVAL_SECONDS} and {MAX_INTERVAL_SECONDS} seconds, got {}",
+                    self.interval
+                ),
+            ));
+        }
+
+        issues
+    }
+
+    /// Reports monitors assigned a theme that is in neither themes directory
+    fn check_monitor_themes(&self, issues: &mut Vec<ConfigIssue>) {
+        let mut assignments: Vec<(Vec<&str>, &str)> = match &self.monitor_specific_wallpapers {
+            MonitorSpecificWallpapers::All(theme_id) => {
+                vec![(vec!["monitor_specific_wallpapers"], theme_id.as_str())]
+            }
+            MonitorSpecificWallpapers::Specific(wallpapers) => wallpapers
+                .iter()
+                .map(|(monitor_id, theme_id)| {
+                    (
+                        vec!["monitor_specific_wallpapers", monitor_id.as_str()],
+                        theme_id.as_str(),
+                    )
+                })
+                .collect(),
+        };
+        // Map order is random; keep reports stable
+        assignments.sort();
+
+        for (field, theme_id) in assignments {
+            if theme_id.trim().is_empty() {
+                issues.push(ConfigIssue::error(&field, "theme ID is empty"));
+            } else if !self.themes_directory.join(theme_id).is_dir()
+                && !self.customized_themes_directory.join(theme_id).is_dir()
+            {
+                issues.push(ConfigIssue::warning(
+                    &field,
+                    format!(
+                        "theme '{theme_id}' is in neither the themes nor the customized themes directory"
+                    ),
+                ));
             }
-            .into());
         }
+    }
 
-        Ok(())
+    /// Returns the layout version of the configuration file
+    pub fn version(&self) -> u32 {
+        self.version
     }
 
     /// Returns the themes directory path
@@ -286,7 +787,7 @@ impl Config {
         config
     }
 
-    /// Returns the update interval in seconds
+    /// Returns the maximum wake interval in seconds
     pub fn interval(&self) -> u16 {
         self.interval
     }
@@ -296,6 +797,27 @@ impl Config {
         &self.image_format
     }
 
+    /// Returns the formats tried for themes without images in the image format
+    pub fn image_format_fallbacks(&self) -> &[ImageFormat] {
+        &self.image_format_fallbacks
+    }
+
+    /// Returns the formats to show themes in, most preferred first
+    pub fn image_format_preference(&self) -> Vec<ImageFormat> {
+        let mut preference = vec![self.image_format.clone()];
+        for format in &self.image_format_fallbacks {
+            if !preference.contains(format) {
+                preference.push(format.clone());
+            }
+        }
+        preference
+    }
+
+    /// Returns whether theme images are converted when no preferred format exists
+    pub fn convert_image_formats(&self) -> bool {
+        self.convert_image_formats
+    }
+
     /// Returns whether auto detection of color mode is enabled
     pub fn auto_detect_color_scheme(&self) -> bool {
         self.auto_detect_color_scheme
@@ -306,11 +828,51 @@ impl Config {
         self.lock_screen_wallpaper_enabled
     }
 
+    /// Returns whether neighbouring theme images are cross-faded
+    pub fn blend_wallpapers(&self) -> bool {
+        self.blend_wallpapers
+    }
+
+    /// Returns the margin in degrees another image must win by to replace the current one
+    pub fn image_switch_margin(&self) -> f64 {
+        self.image_switch_margin
+    }
+
+    /// Returns how theme images are picked, without knowledge of the current image
+    pub(crate) fn selection_policy(&self) -> SelectionPolicy {
+        SelectionPolicy::new(self.blend_wallpapers, self.image_switch_margin)
+    }
+
     /// Returns the position source
     pub fn position_source(&self) -> &PositionSource {
         &self.position_source
     }
 
+    /// Returns the atmospheric conditions configuration
+    pub fn atmosphere(&self) -> &Atmosphere {
+        &self.atmosphere
+    }
+
+    /// Returns the observer's height in meters above the terrain forming the horizon
+    pub fn horizon_height(&self) -> f64 {
+        self.horizon_height
+    }
+
+    /// Returns the configured solar position algorithm
+    pub fn solar_algorithm(&self) -> SolarAlgorithm {
+        self.solar_algorithm
+    }
+
+    /// Resolves the algorithm, atmospheric conditions and horizon at the given observer
+    /// position
+    pub fn solar_model(&self, position: &Position) -> SolarModel {
+        SolarModel::new(
+            self.solar_algorithm,
+            self.atmosphere.conditions_at(position),
+        )
+        .with_horizon_height(self.horizon_height)
+    }
+
     /// Returns the monitor-specific wallpapers map
     pub fn monitor_specific_wallpapers(&self) -> &MonitorSpecificWallpapers {
         &self.monitor_specific_wallpapers
@@ -320,103 +882,34 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             network: Default::default(),
             title_bar_color_follows_windows_theme: default_title_bar_color_follows_windows_theme(),
             image_format: Default::default(),
+            image_format_fallbacks: default_image_format_fallbacks(),
+            convert_image_formats: false,
             position_source: Default::default(),
+            atmosphere: Default::default(),
+            horizon_height: 0.0,
+            solar_algorithm: Default::default(),
             auto_detect_color_scheme: default_auto_detect_color_scheme(),
             themes_directory: default_themes_directory(),
             customized_themes_directory: default_customized_themes_directory(),
             lock_screen_wallpaper_enabled: default_lock_screen_wallpaper_enabled(),
+            blend_wallpapers: false,
+            image_switch_margin: default_image_switch_margin(),
             monitor_specific_wallpapers: default_monitor_specific_wallpapers(),
-            // On the equator, an azimuth change of 0.1 degrees takes
-            // approximately 12 seconds, and an altitude change of 0.1
-            // degrees takes about 24 seconds.
-            // Set the default time interval based on the rate of change of the azimuth.
-            // On the equator, an azimuth change of 0.1 degrees takes approximately 12 seconds,
-            // and an altitude change of 0.1 degrees takes about 24 seconds.
-            // FIXME: This default value is a rough estimate; a more precise algorithm should
-            // be used to calculate the time interval required for each 0.1 degree change.
+            // Wallpaper and color scheme changes are predicted by the scheduler,
+            // so this only bounds how stale the position and external state may get.
             interval: DEFAULT_INTERVAL_SECONDS,
         }
     }
 }
 
-/// Intermediate deserialization struct for backward compatibility with legacy configuration files.
-///
-/// The `github_mirror_template` field was deprecated in 0.2.0 and will be removed in 0.3.0.
-/// At that time, this struct should also be removed, and the standard `Deserialize` derivation of `Config` should be restored.
-#[derive(Debug, Deserialize)]
-pub struct RawConfig {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    github_mirror_template: Option<String>,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    network: Option<Network>,
-
-    #[serde(default = "default_title_bar_color_follows_windows_theme")]
-    title_bar_color_follows_windows_theme: bool,
-
-    #[serde(default = "default_image_format")]
-    image_format: ImageFormat,
-
-    #[serde(alias = "coordinate_source", default = "default_position_source")]
-    position_source: PositionSource,
-
-    #[serde(
-        alias = "auto_detect_color_mode",
-        default = "default_auto_detect_color_scheme"
-    )]
-    auto_detect_color_scheme: bool,
-
-    #[serde(default = "default_lock_screen_wallpaper_enabled")]
-    lock_screen_wallpaper_enabled: bool,
-
-    #[serde(default = "default_themes_directory")]
-    themes_directory: PathBuf,
-
-    #[serde(default = "default_customized_themes_directory")]
-    customized_themes_directory: PathBuf,
-
-    /// Wallpapers specific to each monitor, using monitor ID as key
-    #[serde(default = "default_monitor_specific_wallpapers")]
-    monitor_specific_wallpapers: MonitorSpecificWallpapers,
-
-    /// Time interval for detecting solar altitude angle and azimuth angle
-    /// Measured in seconds, range: `[MIN_INTERVAL_SECONDS, MAX_INTERVAL_SECONDS]`
-    #[serde(
-        default = "default_interval",
-        deserialize_with = "deserialize_interval"
-    )]
-    interval: u16,
-}
-
-impl From<RawConfig> for Config {
-    fn from(raw: RawConfig) -> Self {
-        // Migrate github_mirror_template
-        let network = raw.network.or_else(|| {
-            raw.github_mirror_template
-                .map(Network::GitHubMirrorTemplate)
-        });
-
-        Config {
-            title_bar_color_follows_windows_theme: raw.title_bar_color_follows_windows_theme,
-            network,
-            image_format: raw.image_format,
-            position_source: raw.position_source,
-            auto_detect_color_scheme: raw.auto_detect_color_scheme,
-            lock_screen_wallpaper_enabled: raw.lock_screen_wallpaper_enabled,
-            themes_directory: raw.themes_directory,
-            customized_themes_directory: raw.customized_themes_directory,
-            monitor_specific_wallpapers: raw.monitor_specific_wallpapers,
-            interval: raw.interval,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::testing::scratch_directory;
 
     #[test]
     fn test_config_deserialize() {
@@ -435,9 +928,260 @@ mod tests {
             "interval": 3601
         }
         "#;
-        let result = serde_json::from_str::<Config>(config_str);
-        assert!(result.is_err());
-        let error = result.unwrap_err().to_string();
-        assert!(error.starts_with("interval must be between 1 and 3600, got 3601"));
+        // Out-of-range values load and are reported by validation
+        let config: Config = serde_json::from_str(config_str).unwrap();
+        assert_eq!(config.interval, 3601);
+        assert_eq!(error_fields(&config), ["interval"]);
+    }
+
+    #[test]
+    fn test_image_format_decoding() {
+        let decodable: Vec<&ImageFormat> = ImageFormat::ALL
+            .iter()
+            .filter(|format| format.is_decodable())
+            .collect();
+        assert_eq!(
+            decodable,
+            [&ImageFormat::Jpeg, &ImageFormat::Png, &ImageFormat::Webp]
+        );
+    }
+
+    #[test]
+    fn test_atmosphere_defaults_and_validation() {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.atmosphere(), &Atmosphere::default());
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            serde_json::from_str(r#"{ "atmosphere": { "pressure": 50.0 } }"#).unwrap();
+        assert!(config.validate().is_err());
+
+        let position = Position::new(46.0, 8.0, 2000.0).unwrap();
+        let estimated = Atmosphere::default().conditions_at(&position);
+        assert!(estimated.pressure() < 800.0);
+        assert_eq!(estimated.temperature(), DEFAULT_TEMPERATURE_CELSIUS);
+
+        let measured: Atmosphere =
+            serde_json::from_str(r#"{ "pressure": 850.0, "temperature": -5.0 }"#).unwrap();
+        assert_eq!(measured.conditions_at(&position).pressure(), 850.0);
+    }
+
+    #[test]
+    fn test_horizon_height() {
+        // The altitude of the position does not raise the observer above the horizon
+        let position = Position::new(46.0, 8.0, 2000.0).unwrap();
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.solar_model(&position).horizon_height(), 0.0);
+
+        let config: Config = serde_json::from_str(r#"{ "horizon_height": 120.0 }"#).unwrap();
+        assert_eq!(config.solar_model(&position).horizon_height(), 120.0);
+        assert!(config.validate().is_ok());
+
+        let config: Config = serde_json::from_str(r#"{ "horizon_height": -5.0 }"#).unwrap();
+        assert_eq!(error_fields(&config), ["horizon_height"]);
+    }
+
+    #[test]
+    fn test_solar_algorithm() {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.solar_algorithm(), SolarAlgorithm::Standard);
+
+        let config: Config =
+            serde_json::from_str(r#"{ "solar_algorithm": { "type": "SPA" } }"#).unwrap();
+        assert_eq!(
+            config.solar_algorithm(),
+            SolarAlgorithm::Spa { delta_t: None }
+        );
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            serde_json::from_str(r#"{ "solar_algorithm": { "type": "SPA", "delta_t": 69.2 } }"#)
+                .unwrap();
+        assert_eq!(
+            config.solar_algorithm(),
+            SolarAlgorithm::Spa {
+                delta_t: Some(69.2)
+            }
+        );
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            serde_json::from_str(r#"{ "solar_algorithm": { "type": "SPA", "delta_t": 5000.0 } }"#)
+                .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_json_schema_is_current() {
+        let schema = serde_json::to_string_pretty(&config_json_schema()).unwrap();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../types/config.schema.json");
+        let checked_in = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            checked_in.trim_end(),
+            schema,
+            "{} is outdated, regenerate it with `dwall config-schema --output <FILE>`",
+            path.display()
+        );
+
+        let schema = config_json_schema().to_value();
+        let interval = &schema["properties"]["interval"];
+        assert_eq!(interval["minimum"], MIN_INTERVAL_SECONDS);
+        assert_eq!(interval["maximum"], MAX_INTERVAL_SECONDS);
+    }
+
+    #[test]
+    fn test_image_switch_margin() {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert_eq!(
+            config.image_switch_margin(),
+            DEFAULT_IMAGE_SWITCH_MARGIN_DEGREES
+        );
+
+        let config: Config = serde_json::from_str(r#"{ "image_switch_margin": 0 }"#).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = serde_json::from_str(r#"{ "image_switch_margin": -1 }"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    fn error_fields(config: &Config) -> Vec<String> {
+        config
+            .issues()
+            .iter()
+            .filter(|issue| issue.severity() == Severity::Error)
+            .map(ConfigIssue::field_path)
+            .collect()
+    }
+
+    #[test]
+    fn test_validation_collects_every_error() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "position_source": { "type": "MANUAL", "latitude": 91.0, "longitude": -200.0 },
+                "atmosphere": { "pressure": 50.0 },
+                "image_switch_margin": -1
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            error_fields(&config),
+            [
+                "position_source.latitude",
+                "position_source.longitude",
+                "atmosphere.pressure",
+                "image_switch_margin",
+            ]
+        );
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("position_source.latitude: must be between -90° and 90°, got 91°"));
+        assert!(error.contains("; atmosphere.pressure: "));
+    }
+
+    #[test]
+    fn test_network_validation() {
+        let socks5 = |host: &str, port: u16| Config {
+            network: Some(Network::Socks5 {
+                host: host.to_string(),
+                port,
+            }),
+            ..Default::default()
+        };
+        for host in ["127.0.0.1", "[::1]", "proxy.example.com", "localhost"] {
+            assert!(error_fields(&socks5(host, 1080)).is_empty(), "{host}");
+        }
+        for host in ["", "socks5://127.0.0.1", "bad host", "-proxy.example.com"] {
+            assert_eq!(
+                error_fields(&socks5(host, 1080)),
+                ["network.host"],
+                "{host}"
+            );
+        }
+        assert_eq!(error_fields(&socks5("127.0.0.1", 0)), ["network.port"]);
+
+        let template = |template: &str| Config {
+            network: Some(Network::GitHubMirrorTemplate(template.to_string())),
+            ..Default::default()
+        };
+        assert!(
+            template("")
+                .issues()
+                .iter()
+                .all(|issue| issue.field_path() != "network")
+        );
+        assert!(
+            error_fields(&template(
+                "https://mirror.example.com/<owner>/<repo>/<version>/<asset>"
+            ))
+            .is_empty()
+        );
+        for invalid in [
+            "mirror.example.com/<asset>",
+            "https://mirror.example.com/<tag>/<asset>",
+            "https://mirror.example.com/<asset",
+        ] {
+            assert_eq!(error_fields(&template(invalid)), ["network"], "{invalid}");
+        }
+
+        let without_asset = template("https://mirror.example.com/<repo>");
+        assert!(without_asset.issues().iter().any(|issue| {
+            issue.severity() == Severity::Warning && issue.field_path() == "network"
+        }));
+        assert!(without_asset.validate().is_ok());
+    }
+
+    #[test]
+    fn test_monitor_theme_validation() {
+        let directory = scratch_directory("config-themes");
+        std::fs::create_dir_all(directory.join("themes/Catalina")).unwrap();
+        std::fs::create_dir_all(directory.join("customized")).unwrap();
+
+        let config = Config {
+            themes_directory: directory.join("themes"),
+            customized_themes_directory: directory.join("customized"),
+            monitor_specific_wallpapers: MonitorSpecificWallpapers::Specific(HashMap::from([
+                ("DISPLAY1".to_string(), "Catalina".to_string()),
+                ("DISPLAY2".to_string(), "Missing".to_string()),
+                ("DISPLAY3".to_string(), String::new()),
+            ])),
+            ..Default::default()
+        };
+        let issues = config.issues();
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.severity(), issue.field_path()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    Severity::Warning,
+                    "monitor_specific_wallpapers.DISPLAY2".to_string()
+                ),
+                (
+                    Severity::Error,
+                    "monitor_specific_wallpapers.DISPLAY3".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            issues[0].field(),
+            ["monitor_specific_wallpapers", "DISPLAY2"]
+        );
+
+        let config = Config {
+            themes_directory: directory.join("missing"),
+            customized_themes_directory: directory.join("themes/Catalina"),
+            ..Default::default()
+        };
+        assert_eq!(
+            config
+                .issues()
+                .iter()
+                .map(|issue| (issue.severity(), issue.field_path()))
+                .collect::<Vec<_>>(),
+            [(Severity::Warning, "themes_directory".to_string())]
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
     }
 }
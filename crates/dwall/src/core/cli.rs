@@ -0,0 +1,909 @@
+//! Command line handling for the daemon binary
+//!
+//! Without arguments the binary runs the daemon. Subcommands provide tools for
+//! theme authors that run once and exit.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{self, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use time::{Date, Offset};
+
+use crate::{
+    Config, DwallResult, Position,
+    config::{ImageFormat, PositionSource, config_json_schema},
+    domain::visual::{
+        ImageCheck, PhotoSolarOptions, SolarPathFormat, SolarPathOptions, SolarPathTable,
+        ThemePackOptions, ThemeValidator, find_photos, pack_theme, select_image_format,
+        solar_photos, theme_processor::get_theme_directory_path, verify_theme, write_solar_theme,
+    },
+};
+
+const DEFAULT_SOLAR_PATH_STEP_SECONDS: u64 = 600;
+
+pub const USAGE: &str = "\
+Usage:
+  dwall                       Run the daemon
+  dwall solar-path [OPTIONS]  Print sun position, image and color scheme over a date range
+  dwall photo-theme [OPTIONS] Build a solar theme from photos, using their EXIF capture
+                              time and GPS position
+  dwall validate-theme [OPTIONS]
+                              Check a theme and list every issue found; exits with
+                              status 1 if any of them is an error
+  dwall pack-theme [OPTIONS]  Zip a theme with a manifest of its files and their hashes
+  dwall verify-theme [OPTIONS]
+                              Check an installed theme against its manifest; exits with
+                              status 1 if a file is missing or modified
+  dwall config-schema [OPTIONS]
+                              Print the JSON Schema of config.toml
+
+Options for solar-path:
+  --theme <ID|DIR>       Theme id or theme directory (required)
+  --from <YYYY-MM-DD>    First local date (required)
+  --to <YYYY-MM-DD>      Last local date, inclusive [default: --from]
+  --latitude <DEG>       Observer latitude [default: configured manual position]
+  --longitude <DEG>      Observer longitude [default: configured manual position]
+  --altitude <M>         Observer altitude in meters [default: 0]
+  --step <SECONDS>       Sampling step [default: 600]
+  --offset <+HH:MM>      UTC offset of the local dates [default: system offset]
+  --format <csv|json>    Output format [default: csv]
+  --output <FILE>        Write to a file instead of standard output
+
+Options for photo-theme:
+  --photos <DIR>         Directory of JPEG or PNG photos (required)
+  --output <DIR>         Theme directory to write images and solar.json to (required)
+  --latitude <DEG>       Latitude for photos without GPS position
+                         [default: configured manual position]
+  --longitude <DEG>      Longitude for photos without GPS position
+                         [default: configured manual position]
+  --altitude <M>         Altitude in meters for photos without GPS position [default: 0]
+  --offset <+HH:MM>      UTC offset of capture times that do not record one
+                         [default: system offset]
+  --max-images <N>       Keep at most N photos, evenly spaced in time [default: 256]
+
+Options for validate-theme:
+  --theme <ID|DIR>       Theme id or theme directory (required); a directory with an
+                         'images' folder is checked as a customized theme
+  --image-format <jpg|png|webp|avif|jxl>
+                         Image format of the theme [default: the first configured
+                         format the theme has images in]
+  --image-check <headers|decode>
+                         Read image headers only, or decode every image to find
+                         corrupt files [default: headers]
+  --format <text|json>   Output format [default: text]
+
+Options for pack-theme:
+  --theme <ID|DIR>       Theme id or theme directory (required)
+  --output <FILE>        Zip file to write (required)
+  --name <NAME>          Theme name [default: from metadata.toml, or the directory name]
+  --author <AUTHOR>      Theme author [default: from metadata.toml; required without it]
+  --version <N>          Theme version [default: from metadata.toml, or 1]
+  --license <LICENSE>    Theme license, such as an SPDX identifier
+                         [default: from metadata.toml]
+  --min-dwall-version <MAJOR.MINOR.PATCH>
+                         Oldest dwall version able to apply the theme
+                         [default: this version]
+
+Options for verify-theme:
+  --theme <ID|DIR>       Theme id or theme directory (required)
+  --format <text|json>   Output format [default: text]
+
+Options for config-schema:
+  --output <FILE>        Write to a file instead of standard output
+";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommandLineError {
+    #[error("Unknown command '{0}'\n\n{USAGE}")]
+    UnknownCommand(String),
+    #[error("Unknown option '{0}'\n\n{USAGE}")]
+    UnknownOption(String),
+    #[error("Missing value for option '{0}'")]
+    MissingValue(String),
+    #[error("Invalid value '{value}' for option '{option}'")]
+    InvalidValue { option: String, value: String },
+    #[error("Missing required option '{0}'")]
+    MissingOption(&'static str),
+}
+
+/// What the binary was asked to do
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Daemon,
+    Help,
+    SolarPath(SolarPathCommand),
+    PhotoTheme(PhotoThemeCommand),
+    ValidateTheme(ValidateThemeCommand),
+    PackTheme(PackThemeCommand),
+    VerifyTheme(VerifyThemeCommand),
+    ConfigSchema(ConfigSchemaCommand),
+}
+
+impl Command {
+    /// Parses the arguments following the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut args = args.into_iter();
+        match args.next().as_deref() {
+            None => Ok(Command::Daemon),
+            Some("help" | "--help" | "-h") => Ok(Command::Help),
+            Some("solar-path") => SolarPathCommand::parse(args).map(Command::SolarPath),
+            Some("photo-theme") => PhotoThemeCommand::parse(args).map(Command::PhotoTheme),
+            Some("validate-theme") => ValidateThemeCommand::parse(args).map(Command::ValidateTheme),
+            Some("pack-theme") => PackThemeCommand::parse(args).map(Command::PackTheme),
+            Some("verify-theme") => VerifyThemeCommand::parse(args).map(Command::VerifyTheme),
+            Some("config-schema") => ConfigSchemaCommand::parse(args).map(Command::ConfigSchema),
+            Some(other) => Err(CommandLineError::UnknownCommand(other.to_string())),
+        }
+    }
+}
+
+/// Arguments of the `solar-path` subcommand
+#[derive(Debug, PartialEq)]
+pub struct SolarPathCommand {
+    theme: String,
+    from: Date,
+    to: Option<Date>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    altitude: Option<f64>,
+    step_seconds: u64,
+    offset: Option<Offset>,
+    format: SolarPathFormat,
+    output: Option<PathBuf>,
+}
+
+impl SolarPathCommand {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut theme = None;
+        let mut from = None;
+        let mut to = None;
+        let mut latitude = None;
+        let mut longitude = None;
+        let mut altitude = None;
+        let mut step_seconds = DEFAULT_SOLAR_PATH_STEP_SECONDS;
+        let mut offset = None;
+        let mut format = SolarPathFormat::default();
+        let mut output = None;
+
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandLineError::MissingValue(option.clone()))?;
+
+            match option.as_str() {
+                "--theme" => theme = Some(value),
+                "--from" => from = Some(parse_value(&option, &value)?),
+                "--to" => to = Some(parse_value(&option, &value)?),
+                "--latitude" => latitude = Some(parse_value(&option, &value)?),
+                "--longitude" => longitude = Some(parse_value(&option, &value)?),
+                "--altitude" => altitude = Some(parse_value(&option, &value)?),
+                "--step" => step_seconds = parse_value(&option, &value)?,
+                "--offset" => offset = Some(parse_value(&option, &value)?),
+                "--format" => format = parse_value(&option, &value)?,
+                "--output" => output = Some(PathBuf::from(value)),
+                _ => return Err(CommandLineError::UnknownOption(option)),
+            }
+        }
+
+        Ok(Self {
+            theme: theme.ok_or(CommandLineError::MissingOption("--theme"))?,
+            from: from.ok_or(CommandLineError::MissingOption("--from"))?,
+            to,
+            latitude,
+            longitude,
+            altitude,
+            step_seconds,
+            offset,
+            format,
+            output,
+        })
+    }
+
+    /// Computes the table and writes it to the requested output
+    pub fn run(&self, config: &Config) -> DwallResult<()> {
+        let position = self.position(config)?;
+        let options = SolarPathOptions::new(
+            position,
+            config.solar_model(&position),
+            self.from,
+            self.to.unwrap_or(self.from),
+            Duration::from_secs(self.step_seconds),
+            self.offset.unwrap_or_else(Offset::local_offset),
+        )?;
+
+        let theme_directory = theme_directory(config, &self.theme);
+
+        info!(
+            theme_directory = %theme_directory.display(),
+            latitude = position.latitude(),
+            longitude = position.longitude(),
+            "Exporting solar path table"
+        );
+
+        let table = SolarPathTable::for_theme(&theme_directory, &options)?;
+        match &self.output {
+            Some(path) => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                table.write(self.format, &mut writer)?;
+                writer.flush()?;
+            }
+            None => {
+                let mut writer = io::stdout().lock();
+                table.write(self.format, &mut writer)?;
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Position from the command line, falling back to the configured manual position
+    fn position(&self, config: &Config) -> DwallResult<Position> {
+        let configured = configured_position(config);
+
+        let latitude = self
+            .latitude
+            .or(configured.map(|c| c.0))
+            .ok_or(CommandLineError::MissingOption("--latitude"))?;
+        let longitude = self
+            .longitude
+            .or(configured.map(|c| c.1))
+            .ok_or(CommandLineError::MissingOption("--longitude"))?;
+        let altitude = self.altitude.or(configured.map(|c| c.2)).unwrap_or(0.0);
+
+        Position::new(latitude, longitude, altitude)
+    }
+}
+
+/// Arguments of the `photo-theme` subcommand
+#[derive(Debug, PartialEq)]
+pub struct PhotoThemeCommand {
+    photos: PathBuf,
+    output: PathBuf,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    altitude: Option<f64>,
+    offset: Option<Offset>,
+    max_images: Option<usize>,
+}
+
+impl PhotoThemeCommand {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut photos = None;
+        let mut output = None;
+        let mut latitude = None;
+        let mut longitude = None;
+        let mut altitude = None;
+        let mut offset = None;
+        let mut max_images = None;
+
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandLineError::MissingValue(option.clone()))?;
+
+            match option.as_str() {
+                "--photos" => photos = Some(PathBuf::from(value)),
+                "--output" => output = Some(PathBuf::from(value)),
+                "--latitude" => latitude = Some(parse_value(&option, &value)?),
+                "--longitude" => longitude = Some(parse_value(&option, &value)?),
+                "--altitude" => altitude = Some(parse_value(&option, &value)?),
+                "--offset" => offset = Some(parse_value(&option, &value)?),
+                "--max-images" => max_images = Some(parse_value(&option, &value)?),
+                _ => return Err(CommandLineError::UnknownOption(option)),
+            }
+        }
+
+        Ok(Self {
+            photos: photos.ok_or(CommandLineError::MissingOption("--photos"))?,
+            output: output.ok_or(CommandLineError::MissingOption("--output"))?,
+            latitude,
+            longitude,
+            altitude,
+            offset,
+            max_images,
+        })
+    }
+
+    /// Writes the theme and prints which photo became which image
+    pub fn run(&self, config: &Config) -> DwallResult<()> {
+        let mut options = PhotoSolarOptions::new(self.offset.unwrap_or_else(Offset::local_offset));
+        if let Some(location) = self.location(config)? {
+            options = options.with_location(location);
+        }
+        if let Some(max_images) = self.max_images {
+            options = options.with_max_images(max_images);
+        }
+
+        let (photos, image_format) = find_photos(&self.photos)?;
+        let solar_photos =
+            solar_photos(&photos, &options, |position| config.solar_model(position))?;
+        write_solar_theme(&solar_photos, &image_format, &self.output)?;
+
+        info!(
+            photos_directory = %self.photos.display(),
+            theme_directory = %self.output.display(),
+            images = solar_photos.len(),
+            "Wrote solar theme from photos"
+        );
+
+        let mut writer = io::stdout().lock();
+        for photo in &solar_photos {
+            let angle = photo.angle();
+            writeln!(
+                writer,
+                "{}.{}\t{}\t{:.2}\t{:.2}\t{}",
+                u16::from(angle.index()) + 1,
+                image_format.as_str(),
+                photo.capture().captured_at(),
+                angle.altitude(),
+                angle.azimuth(),
+                photo.capture().path().display()
+            )?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Location for photos without GPS position, from the command line or the
+    /// configured manual position
+    fn location(&self, config: &Config) -> DwallResult<Option<Position>> {
+        let (latitude, longitude, altitude) = match (self.latitude, self.longitude) {
+            (Some(latitude), Some(longitude)) => {
+                (latitude, longitude, self.altitude.unwrap_or(0.0))
+            }
+            (Some(_), None) => return Err(CommandLineError::MissingOption("--longitude").into()),
+            (None, Some(_)) => return Err(CommandLineError::MissingOption("--latitude").into()),
+            (None, None) => match configured_position(config) {
+                Some(configured) => configured,
+                None => return Ok(None),
+            },
+        };
+
+        Position::new(latitude, longitude, altitude).map(Some)
+    }
+}
+
+/// Latitude, longitude and altitude of the configured manual position, if any
+fn configured_position(config: &Config) -> Option<(f64, f64, f64)> {
+    match config.position_source() {
+        PositionSource::Manual {
+            latitude,
+            longitude,
+            altitude,
+        } => Some((*latitude, *longitude, *altitude)),
+        PositionSource::Automatic { .. } => None,
+    }
+}
+
+/// Output format of the `validate-theme` subcommand
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    /// One line per issue, prefixed with its severity
+    #[default]
+    Text,
+    /// The report as serialized for the settings UI
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Arguments of the `validate-theme` subcommand
+#[derive(Debug, PartialEq)]
+pub struct ValidateThemeCommand {
+    theme: String,
+    image_format: Option<ImageFormat>,
+    image_check: ImageCheck,
+    format: ReportFormat,
+}
+
+impl ValidateThemeCommand {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut theme = None;
+        let mut image_format = None;
+        let mut image_check = ImageCheck::default();
+        let mut format = ReportFormat::default();
+
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandLineError::MissingValue(option.clone()))?;
+
+            match option.as_str() {
+                "--theme" => theme = Some(value),
+                "--image-format" => match ImageFormat::from_extension(&value) {
+                    Some(format) => image_format = Some(format),
+                    None => return Err(CommandLineError::InvalidValue { option, value }),
+                },
+                "--image-check" => {
+                    image_check = match value.as_str() {
+                        "headers" => ImageCheck::Headers,
+                        "decode" => ImageCheck::Decode,
+                        _ => return Err(CommandLineError::InvalidValue { option, value }),
+                    }
+                }
+                "--format" => format = parse_value(&option, &value)?,
+                _ => return Err(CommandLineError::UnknownOption(option)),
+            }
+        }
+
+        Ok(Self {
+            theme: theme.ok_or(CommandLineError::MissingOption("--theme"))?,
+            image_format,
+            image_check,
+            format,
+        })
+    }
+
+    /// Validates the theme and prints the report
+    ///
+    /// Returns whether the theme passed, that is whether the report has no errors.
+    pub fn run(&self, config: &Config) -> DwallResult<bool> {
+        let theme_directory = PathBuf::from(&self.theme);
+        let (theme_directory, is_customized) = if theme_directory.is_dir() {
+            let is_customized = theme_directory.join("images").is_dir();
+            (path::absolute(theme_directory)?, is_customized)
+        } else {
+            get_theme_directory_path(config, &self.theme)
+        };
+        let themes_directory = theme_directory.parent().unwrap_or(&theme_directory);
+        let theme_id = theme_directory
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy())
+            .unwrap_or_default();
+        let image_format = self.image_format.clone().unwrap_or_else(|| {
+            select_image_format(
+                &theme_directory,
+                is_customized,
+                &config.image_format_preference(),
+                config.convert_image_formats(),
+            )
+            .unwrap_or_else(|| config.image_format().clone())
+        });
+
+        info!(
+            theme_directory = %theme_directory.display(),
+            is_customized = is_customized,
+            "Validating theme"
+        );
+
+        let report = ThemeValidator::report(
+            themes_directory,
+            &theme_id,
+            is_customized,
+            &image_format,
+            self.image_check,
+        );
+        let mut writer = io::stdout().lock();
+        match self.format {
+            ReportFormat::Text => writeln!(writer, "{report}")?,
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, &report)?;
+                writeln!(writer)?;
+            }
+        }
+        writer.flush()?;
+
+        Ok(!report.has_errors())
+    }
+}
+
+/// Arguments of the `pack-theme` subcommand
+#[derive(Debug, PartialEq)]
+pub struct PackThemeCommand {
+    theme: String,
+    output: PathBuf,
+    name: Option<String>,
+    author: Option<String>,
+    version: Option<u16>,
+    license: Option<String>,
+    min_dwall_version: Option<String>,
+}
+
+impl PackThemeCommand {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut theme = None;
+        let mut output = None;
+        let mut name = None;
+        let mut author = None;
+        let mut version = None;
+        let mut license = None;
+        let mut min_dwall_version = None;
+
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandLineError::MissingValue(option.clone()))?;
+
+            match option.as_str() {
+                "--theme" => theme = Some(value),
+                "--output" => output = Some(PathBuf::from(value)),
+                "--name" => name = Some(value),
+                "--author" => author = Some(value),
+                "--version" => version = Some(parse_value(&option, &value)?),
+                "--license" => license = Some(value),
+                "--min-dwall-version" => min_dwall_version = Some(value),
+                _ => return Err(CommandLineError::UnknownOption(option)),
+            }
+        }
+
+        Ok(Self {
+            theme: theme.ok_or(CommandLineError::MissingOption("--theme"))?,
+            output: output.ok_or(CommandLineError::MissingOption("--output"))?,
+            name,
+            author,
+            version,
+            license,
+            min_dwall_version,
+        })
+    }
+
+    /// Writes the zip and prints a summary of its manifest
+    pub fn run(&self, config: &Config) -> DwallResult<()> {
+        let theme_directory = theme_directory(config, &self.theme);
+
+        let mut options = match ThemePackOptions::from_metadata(&theme_directory)? {
+            Some(options) => options,
+            None => {
+                let name = path::absolute(&theme_directory)?
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let author = self
+                    .author
+                    .clone()
+                    .ok_or(CommandLineError::MissingOption("--author"))?;
+                ThemePackOptions::new(name, author, 1)
+            }
+        };
+        if let Some(name) = &self.name {
+            options = options.with_name(name);
+        }
+        if let Some(author) = &self.author {
+            options = options.with_author(author);
+        }
+        if let Some(version) = self.version {
+            options = options.with_version(version);
+        }
+        if let Some(license) = &self.license {
+            options = options.with_license(license);
+        }
+        if let Some(min_dwall_version) = &self.min_dwall_version {
+            options = options.with_min_dwall_version(min_dwall_version)?;
+        }
+
+        let manifest = pack_theme(&theme_directory, &options, &self.output)?;
+
+        let mut writer = io::stdout().lock();
+        writeln!(
+            writer,
+            "Packed {} files of theme '{}' v{} into {}",
+            manifest.files().len(),
+            manifest.name(),
+            manifest.version(),
+            self.output.display()
+        )?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Arguments of the `verify-theme` subcommand
+#[derive(Debug, PartialEq)]
+pub struct VerifyThemeCommand {
+    theme: String,
+    format: ReportFormat,
+}
+
+impl VerifyThemeCommand {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut theme = None;
+        let mut format = ReportFormat::default();
+
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandLineError::MissingValue(option.clone()))?;
+
+            match option.as_str() {
+                "--theme" => theme = Some(value),
+                "--format" => format = parse_value(&option, &value)?,
+                _ => return Err(CommandLineError::UnknownOption(option)),
+            }
+        }
+
+        Ok(Self {
+            theme: theme.ok_or(CommandLineError::MissingOption("--theme"))?,
+            format,
+        })
+    }
+
+    /// Prints the integrity report; returns whether the theme is intact
+    pub fn run(&self, config: &Config) -> DwallResult<bool> {
+        let theme_directory = theme_directory(config, &self.theme);
+
+        info!(
+            theme_directory = %theme_directory.display(),
+            "Verifying theme against its manifest"
+        );
+
+        let report = verify_theme(&theme_directory)?;
+        let mut writer = io::stdout().lock();
+        match self.format {
+            ReportFormat::Text => writeln!(writer, "{report}")?,
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, &report)?;
+                writeln!(writer)?;
+            }
+        }
+        writer.flush()?;
+
+        Ok(report.is_intact())
+    }
+}
+
+/// Arguments of the `config-schema` subcommand
+#[derive(Debug, PartialEq)]
+pub struct ConfigSchemaCommand {
+    output: Option<PathBuf>,
+}
+
+impl ConfigSchemaCommand {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, CommandLineError> {
+        let mut output = None;
+
+        while let Some(option) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| CommandLineError::MissingValue(option.clone()))?;
+
+            match option.as_str() {
+                "--output" => output = Some(PathBuf::from(value)),
+                _ => return Err(CommandLineError::UnknownOption(option)),
+            }
+        }
+
+        Ok(Self { output })
+    }
+
+    pub fn run(&self) -> DwallResult<()> {
+        let schema = config_json_schema();
+        match &self.output {
+            Some(path) => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                serde_json::to_writer_pretty(&mut writer, &schema)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+            None => {
+                let mut writer = io::stdout().lock();
+                serde_json::to_writer_pretty(&mut writer, &schema)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Directory of a theme given by id or by path
+fn theme_directory(config: &Config, theme: &str) -> PathBuf {
+    let theme_directory = PathBuf::from(theme);
+    if theme_directory.is_dir() {
+        theme_directory
+    } else {
+        get_theme_directory_path(config, theme).0
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, CommandLineError> {
+    value.parse().map_err(|_| CommandLineError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CommandLineError> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_runs_daemon() {
+        assert_eq!(parse(&[]).unwrap(), Command::Daemon);
+        assert_eq!(parse(&["--help"]).unwrap(), Command::Help);
+        assert!(matches!(
+            parse(&["paint"]),
+            Err(CommandLineError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn parses_solar_path_options() {
+        let command = parse(&[
+            "solar-path",
+            "--theme",
+            "Big Sur",
+            "--from",
+            "2024-06-21",
+            "--latitude",
+            "51.5",
+            "--longitude",
+            "-0.12",
+            "--step",
+            "300",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+
+        let Command::SolarPath(command) = command else {
+            panic!("expected solar-path command");
+        };
+        assert_eq!(command.theme, "Big Sur");
+        assert_eq!(command.from, Date::new(2024, Month::June, 21).unwrap());
+        assert_eq!(command.to, None);
+        assert_eq!(command.latitude, Some(51.5));
+        assert_eq!(command.longitude, Some(-0.12));
+        assert_eq!(command.step_seconds, 300);
+        assert_eq!(command.format, SolarPathFormat::Json);
+    }
+
+    #[test]
+    fn rejects_incomplete_solar_path_options() {
+        assert!(matches!(
+            parse(&["solar-path", "--from", "2024-06-21"]),
+            Err(CommandLineError::MissingOption("--theme"))
+        ));
+        assert!(matches!(
+            parse(&["solar-path", "--theme", "x", "--from"]),
+            Err(CommandLineError::MissingValue(_))
+        ));
+        assert!(matches!(
+            parse(&["solar-path", "--theme", "x", "--from", "21/06/2024"]),
+            Err(CommandLineError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse(&["solar-path", "--theme", "x", "--colour", "red"]),
+            Err(CommandLineError::UnknownOption(_))
+        ));
+    }
+
+    #[test]
+    fn parses_photo_theme_options() {
+        let command = parse(&[
+            "photo-theme",
+            "--photos",
+            "timelapse",
+            "--output",
+            "theme",
+            "--offset",
+            "+09:00",
+            "--max-images",
+            "48",
+        ])
+        .unwrap();
+
+        let Command::PhotoTheme(command) = command else {
+            panic!("expected photo-theme command");
+        };
+        assert_eq!(command.photos, PathBuf::from("timelapse"));
+        assert_eq!(command.output, PathBuf::from("theme"));
+        assert_eq!(
+            command.offset.map(|offset| offset.seconds()),
+            Some(9 * 3600)
+        );
+        assert_eq!(command.max_images, Some(48));
+        assert_eq!(command.latitude, None);
+
+        assert!(matches!(
+            parse(&["photo-theme", "--photos", "timelapse"]),
+            Err(CommandLineError::MissingOption("--output"))
+        ));
+    }
+
+    #[test]
+    fn parses_pack_and_verify_theme_options() {
+        let command = parse(&[
+            "pack-theme",
+            "--theme",
+            "Sunrise",
+            "--output",
+            "sunrise.zip",
+            "--license",
+            "CC-BY-4.0",
+            "--version",
+            "3",
+        ])
+        .unwrap();
+
+        let Command::PackTheme(command) = command else {
+            panic!("expected pack-theme command");
+        };
+        assert_eq!(command.output, PathBuf::from("sunrise.zip"));
+        assert_eq!(command.license.as_deref(), Some("CC-BY-4.0"));
+        assert_eq!(command.version, Some(3));
+        assert_eq!(command.author, None);
+
+        assert!(matches!(
+            parse(&["pack-theme", "--theme", "Sunrise", "--version", "v3"]),
+            Err(CommandLineError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            parse(&["verify-theme", "--theme", "Sunrise", "--format", "json"]).unwrap(),
+            Command::VerifyTheme(VerifyThemeCommand {
+                theme: "Sunrise".to_string(),
+                format: ReportFormat::Json,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_config_schema_options() {
+        assert_eq!(
+            parse(&["config-schema"]).unwrap(),
+            Command::ConfigSchema(ConfigSchemaCommand { output: None })
+        );
+        assert_eq!(
+            parse(&["config-schema", "--output", "config.schema.json"]).unwrap(),
+            Command::ConfigSchema(ConfigSchemaCommand {
+                output: Some(PathBuf::from("config.schema.json")),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_validate_theme_options() {
+        let command = parse(&[
+            "validate-theme",
+            "--theme",
+            "Big Sur",
+            "--image-format",
+            "png",
+            "--image-check",
+            "decode",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+
+        let Command::ValidateTheme(command) = command else {
+            panic!("expected validate-theme command");
+        };
+        assert_eq!(command.theme, "Big Sur");
+        assert_eq!(command.image_format, Some(ImageFormat::Png));
+        assert_eq!(command.image_check, ImageCheck::Decode);
+        assert_eq!(command.format, ReportFormat::Json);
+
+        assert!(matches!(
+            parse(&["validate-theme"]),
+            Err(CommandLineError::MissingOption("--theme"))
+        ));
+        assert!(matches!(
+            parse(&["validate-theme", "--theme", "x", "--image-format", "gif"]),
+            Err(CommandLineError::InvalidValue { .. })
+        ));
+    }
+}
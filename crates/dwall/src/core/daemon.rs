@@ -1,13 +1,18 @@
-use std::thread::sleep;
 use std::time::Duration;
 
+use time::UtcDateTime;
+
 use crate::{
-    DwallResult, domain::visual::theme_processor::ThemeProcessor,
+    DwallResult,
+    domain::visual::{scheduler::sleep_until, theme_processor::ThemeProcessor},
     infrastructure::filesystem::config_manager::ConfigManager,
 };
 
 const MAX_CONSECUTIVE_FAILURE_THRESHOLD: u8 = 3;
 
+/// Delay before retrying a failed cycle
+const FAILURE_RETRY_DELAY: Duration = Duration::from_secs(15);
+
 /// Main daemon application
 pub struct DaemonApplication {
     config_manager: ConfigManager,
@@ -30,7 +35,7 @@ impl DaemonApplication {
             let theme_processor = ThemeProcessor::new(&config)?;
 
             info!(
-                update_interval_seconds = config.interval(),
+                max_wake_interval_seconds = config.interval(),
                 "Starting daemon with config change detection"
             );
 
@@ -39,12 +44,16 @@ impl DaemonApplication {
     }
 
     /// Runs the processor loop until config changes or max failures reached
+    ///
+    /// Between cycles the loop sleeps until the next predicted wallpaper or color
+    /// scheme change, waking early when the configuration file or the monitor
+    /// setup changes.
     fn run_processor_loop(
         &mut self,
         theme_processor: &ThemeProcessor,
         consecutive_failure_count: &mut u8,
     ) -> DwallResult<()> {
-        let update_interval = Duration::from_secs(theme_processor.update_interval().into());
+        let max_wake_interval = Duration::from_secs(theme_processor.max_wake_interval().into());
 
         loop {
             if self.config_manager.has_changed()? {
@@ -52,9 +61,10 @@ impl DaemonApplication {
                 return Ok(());
             }
 
-            match theme_processor.run_once() {
-                Ok(_) => {
+            let wake_delay = match theme_processor.run_once() {
+                Ok(delay) => {
                     *consecutive_failure_count = 0;
+                    delay
                 }
                 Err(_) => {
                     *consecutive_failure_count += 1;
@@ -64,12 +74,15 @@ impl DaemonApplication {
                         );
                         std::process::exit(1);
                     }
+                    FAILURE_RETRY_DELAY.min(max_wake_interval)
                 }
-            }
-
-            theme_processor.reload_if_monitors_changed();
+            };
 
-            sleep(update_interval);
+            let deadline = UtcDateTime::now().add_seconds(wake_delay.as_secs())?;
+            sleep_until(deadline, || {
+                self.config_manager.has_changed().unwrap_or(false)
+                    || theme_processor.reload_if_monitors_changed()
+            });
         }
     }
 }
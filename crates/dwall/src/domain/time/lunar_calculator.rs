@@ -0,0 +1,432 @@
+//! Position and phase of the moon
+//!
+//! Implements the truncated lunar theory from Meeus, "Astronomical Algorithms"
+//! (2nd ed.), chapters 47 and 48, keeping the largest periodic terms. The result
+//! is accurate to a few hundredths of a degree, which is plenty for choosing a
+//! night image.
+
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+
+use crate::Position;
+
+use super::solar_calculator::{AtmosphericConditions, refraction};
+
+mod constants {
+    pub(super) const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+    pub(super) const EPOCH_J2000: f64 = 2451545.0;
+
+    pub(super) const JULIAN_CENTURY_DAYS: f64 = 36525.0;
+
+    pub(super) const SECONDS_PER_DAY: f64 = 86400.0;
+
+    /// Mean distance between the centers of the Earth and the moon (km)
+    pub(super) const MEAN_LUNAR_DISTANCE: f64 = 385000.56;
+
+    /// Equatorial radius of the Earth (km)
+    pub(super) const EARTH_RADIUS_KM: f64 = 6378.14;
+
+    /// Astronomical unit (km)
+    pub(super) const ASTRONOMICAL_UNIT_KM: f64 = 149597870.7;
+
+    /// Eccentricity of the Earth's orbit
+    pub(super) const EARTH_ORBIT_ECCENTRICITY: f64 = 0.016708634;
+}
+
+/// Periodic terms for longitude and distance:
+/// multipliers of D, M, M′, F, then Σl (10⁻⁶ degrees) and Σr (10⁻³ km)
+const LONGITUDE_DISTANCE_TERMS: [(i8, i8, i8, i8, f64, f64); 32] = [
+    (0, 0, 1, 0, 6288774.0, -20905355.0),
+    (2, 0, -1, 0, 1274027.0, -3699111.0),
+    (2, 0, 0, 0, 658314.0, -2955968.0),
+    (0, 0, 2, 0, 213618.0, -569925.0),
+    (0, 1, 0, 0, -185116.0, 48888.0),
+    (0, 0, 0, 2, -114332.0, -3149.0),
+    (2, 0, -2, 0, 58793.0, 246158.0),
+    (2, -1, -1, 0, 57066.0, -152138.0),
+    (2, 0, 1, 0, 53322.0, -170733.0),
+    (2, -1, 0, 0, 45758.0, -204586.0),
+    (0, 1, -1, 0, -40923.0, -129620.0),
+    (1, 0, 0, 0, -34720.0, 108743.0),
+    (0, 1, 1, 0, -30383.0, 104755.0),
+    (2, 0, 0, -2, 15327.0, 10321.0),
+    (0, 0, 1, 2, -12528.0, 0.0),
+    (0, 0, 1, -2, 10980.0, 79661.0),
+    (4, 0, -1, 0, 10675.0, -34782.0),
+    (0, 0, 3, 0, 10034.0, -23210.0),
+    (4, 0, -2, 0, 8548.0, -21636.0),
+    (2, 1, -1, 0, -7888.0, 24208.0),
+    (2, 1, 0, 0, -6766.0, 30824.0),
+    (1, 0, -1, 0, -5163.0, -8379.0),
+    (1, 1, 0, 0, 4987.0, -16675.0),
+    (2, -1, 1, 0, 4036.0, -12831.0),
+    (2, 0, 2, 0, 3994.0, -10445.0),
+    (4, 0, 0, 0, 3861.0, -11650.0),
+    (2, 0, -3, 0, 3665.0, 14403.0),
+    (0, 1, -2, 0, -2689.0, -7003.0),
+    (2, 0, -1, 2, -2602.0, 0.0),
+    (2, -1, -2, 0, 2390.0, 10056.0),
+    (1, 0, 1, 0, -2348.0, 6322.0),
+    (2, -2, 0, 0, 2236.0, -9884.0),
+];
+
+/// Periodic terms for latitude: multipliers of D, M, M′, F, then Σb (10⁻⁶ degrees)
+const LATITUDE_TERMS: [(i8, i8, i8, i8, f64); 30] = [
+    (0, 0, 0, 1, 5128122.0),
+    (0, 0, 1, 1, 280602.0),
+    (0, 0, 1, -1, 277693.0),
+    (2, 0, 0, -1, 173237.0),
+    (2, 0, -1, 1, 55413.0),
+    (2, 0, -1, -1, 46271.0),
+    (2, 0, 0, 1, 32573.0),
+    (0, 0, 2, 1, 17198.0),
+    (2, 0, 1, -1, 9266.0),
+    (0, 0, 2, -1, 8822.0),
+    (2, -1, 0, -1, 8216.0),
+    (2, 0, -2, -1, 4324.0),
+    (2, 0, 1, 1, 4200.0),
+    (2, 1, 0, -1, -3359.0),
+    (2, -1, -1, 1, 2463.0),
+    (2, -1, 0, 1, 2211.0),
+    (2, -1, -1, -1, 2065.0),
+    (0, 1, -1, -1, -1870.0),
+    (4, 0, -1, -1, 1828.0),
+    (0, 1, 0, 1, -1794.0),
+    (0, 0, 0, 3, -1749.0),
+    (0, 1, -1, 1, -1565.0),
+    (1, 0, 0, 1, -1491.0),
+    (0, 1, 1, 1, -1475.0),
+    (0, 1, 1, -1, -1410.0),
+    (0, 1, 0, -1, -1344.0),
+    (1, 0, 0, -1, -1335.0),
+    (0, 0, 3, 1, 1107.0),
+    (4, 0, 0, -1, 1021.0),
+    (4, 0, -1, 1, 833.0),
+];
+
+/// Named phase of the moon, one per eighth of the synodic month
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+impl MoonPhase {
+    /// Phase centered on the given elongation of the moon east of the sun (degrees)
+    fn from_elongation(elongation: f64) -> Self {
+        const PHASES: [MoonPhase; 8] = [
+            MoonPhase::New,
+            MoonPhase::WaxingCrescent,
+            MoonPhase::FirstQuarter,
+            MoonPhase::WaxingGibbous,
+            MoonPhase::Full,
+            MoonPhase::WaningGibbous,
+            MoonPhase::LastQuarter,
+            MoonPhase::WaningCrescent,
+        ];
+
+        let octant = (elongation.rem_euclid(360.0) / 45.0).round() as usize % PHASES.len();
+        PHASES[octant]
+    }
+
+    /// Typical illuminated fraction of the disc during this phase
+    pub(crate) fn nominal_illumination(&self) -> f64 {
+        match self {
+            MoonPhase::New => 0.0,
+            MoonPhase::WaxingCrescent | MoonPhase::WaningCrescent => 0.25,
+            MoonPhase::FirstQuarter | MoonPhase::LastQuarter => 0.5,
+            MoonPhase::WaxingGibbous | MoonPhase::WaningGibbous => 0.75,
+            MoonPhase::Full => 1.0,
+        }
+    }
+}
+
+/// Geocentric ecliptic coordinates of the moon and the sun at one instant
+struct LunarContext {
+    /// Julian centuries since J2000.0
+    julian_century: f64,
+    /// Moon ecliptic longitude (degrees)
+    longitude: f64,
+    /// Moon ecliptic latitude (degrees)
+    latitude: f64,
+    /// Earth–moon distance (km)
+    distance: f64,
+    /// Sun ecliptic longitude (degrees)
+    sun_longitude: f64,
+    /// Earth–sun distance (km)
+    sun_distance: f64,
+}
+
+impl LunarContext {
+    fn new(date_time: &UtcDateTime) -> Self {
+        let julian_day = constants::UNIX_EPOCH_JULIAN_DAY
+            + date_time.timestamp() as f64 / constants::SECONDS_PER_DAY;
+        let t = (julian_day - constants::EPOCH_J2000) / constants::JULIAN_CENTURY_DAYS;
+
+        // Fundamental arguments (degrees)
+        let mean_longitude = 218.3164477 + 481267.88123421 * t - 0.0015786 * t.powi(2)
+            + t.powi(3) / 538841.0
+            - t.powi(4) / 65194000.0;
+        let elongation = 297.8501921 + 445267.1114034 * t - 0.0018819 * t.powi(2)
+            + t.powi(3) / 545868.0
+            - t.powi(4) / 113065000.0;
+        let sun_anomaly =
+            357.5291092 + 35999.0502909 * t - 0.0001536 * t.powi(2) + t.powi(3) / 24490000.0;
+        let moon_anomaly =
+            134.9633964 + 477198.8675055 * t + 0.0087414 * t.powi(2) + t.powi(3) / 69699.0
+                - t.powi(4) / 14712000.0;
+        let argument_of_latitude =
+            93.2720950 + 483202.0175233 * t - 0.0036539 * t.powi(2) - t.powi(3) / 3526000.0
+                + t.powi(4) / 863310000.0;
+        let a1 = 119.75 + 131.849 * t;
+        let a2 = 53.09 + 479264.290 * t;
+        let a3 = 313.45 + 481266.484 * t;
+
+        // Terms involving the sun's anomaly shrink with the Earth's orbital eccentricity
+        let eccentricity_factor = 1.0 - 0.002516 * t - 0.0000074 * t.powi(2);
+        let eccentricity_scale = |m: i8| eccentricity_factor.powi(m.unsigned_abs().into());
+        let argument = |d: i8, m: i8, mp: i8, f: i8| {
+            (f64::from(d) * elongation
+                + f64::from(m) * sun_anomaly
+                + f64::from(mp) * moon_anomaly
+                + f64::from(f) * argument_of_latitude)
+                .to_radians()
+        };
+
+        let (mut sum_longitude, mut sum_distance) = (0.0, 0.0);
+        for &(d, m, mp, f, l, r) in &LONGITUDE_DISTANCE_TERMS {
+            let arg = argument(d, m, mp, f);
+            sum_longitude += l * eccentricity_scale(m) * arg.sin();
+            sum_distance += r * eccentricity_scale(m) * arg.cos();
+        }
+
+        let mut sum_latitude = 0.0;
+        for &(d, m, mp, f, b) in &LATITUDE_TERMS {
+            sum_latitude += b * eccentricity_scale(m) * argument(d, m, mp, f).sin();
+        }
+
+        // Additive terms for Venus, Jupiter and the flattening of the Earth
+        sum_longitude += 3958.0 * a1.to_radians().sin()
+            + 1962.0 * (mean_longitude - argument_of_latitude).to_radians().sin()
+            + 318.0 * a2.to_radians().sin();
+        sum_latitude += -2235.0 * mean_longitude.to_radians().sin()
+            + 382.0 * a3.to_radians().sin()
+            + 175.0 * (a1 - argument_of_latitude).to_radians().sin()
+            + 175.0 * (a1 + argument_of_latitude).to_radians().sin()
+            + 127.0 * (mean_longitude - moon_anomaly).to_radians().sin()
+            - 115.0 * (mean_longitude + moon_anomaly).to_radians().sin();
+
+        // Low-precision position of the sun, needed for the phase
+        let sun_mean_longitude = 280.46646 + 36000.76983 * t;
+        let sun_anomaly_rad = sun_anomaly.to_radians();
+        let sun_center = (1.914602 - 0.004817 * t) * sun_anomaly_rad.sin()
+            + (0.019993 - 0.000101 * t) * (2.0 * sun_anomaly_rad).sin()
+            + 0.000289 * (3.0 * sun_anomaly_rad).sin();
+        let sun_true_anomaly = (sun_anomaly + sun_center).to_radians();
+        let e = constants::EARTH_ORBIT_ECCENTRICITY;
+        let sun_distance_au = 1.000001018 * (1.0 - e * e) / (1.0 + e * sun_true_anomaly.cos());
+
+        Self {
+            julian_century: t,
+            longitude: (mean_longitude + sum_longitude / 1e6).rem_euclid(360.0),
+            latitude: sum_latitude / 1e6,
+            distance: constants::MEAN_LUNAR_DISTANCE + sum_distance / 1e3,
+            sun_longitude: (sun_mean_longitude + sun_center).rem_euclid(360.0),
+            sun_distance: sun_distance_au * constants::ASTRONOMICAL_UNIT_KM,
+        }
+    }
+
+    /// Elongation of the moon east of the sun along the ecliptic (degrees, `[0, 360)`)
+    fn elongation(&self) -> f64 {
+        (self.longitude - self.sun_longitude).rem_euclid(360.0)
+    }
+
+    /// Illuminated fraction of the moon's disc, from 0 (new) to 1 (full)
+    fn illuminated_fraction(&self) -> f64 {
+        let latitude = self.latitude.to_radians();
+        let cos_elongation =
+            latitude.cos() * (self.longitude - self.sun_longitude).to_radians().cos();
+        let geocentric_elongation = cos_elongation.clamp(-1.0, 1.0).acos();
+        let phase_angle = (self.sun_distance * geocentric_elongation.sin())
+            .atan2(self.distance - self.sun_distance * cos_elongation);
+
+        (1.0 + phase_angle.cos()) / 2.0
+    }
+
+    /// Equatorial coordinates (right ascension, declination) in degrees
+    fn equatorial(&self) -> (f64, f64) {
+        let obliquity = (23.439291 - 0.0130042 * self.julian_century).to_radians();
+        let lambda = self.longitude.to_radians();
+        let beta = self.latitude.to_radians();
+
+        let right_ascension = (lambda.sin() * obliquity.cos() - beta.tan() * obliquity.sin())
+            .atan2(lambda.cos())
+            .to_degrees();
+        let declination = (beta.sin() * obliquity.cos()
+            + beta.cos() * obliquity.sin() * lambda.sin())
+        .asin()
+        .to_degrees();
+
+        (right_ascension, declination)
+    }
+
+    /// Greenwich mean sidereal time (degrees)
+    fn sidereal_time(&self) -> f64 {
+        let t = self.julian_century;
+        280.46061837
+            + 360.98564736629 * t * constants::JULIAN_CENTURY_DAYS
+            + 0.000387933 * t.powi(2)
+            - t.powi(3) / 38710000.0
+    }
+}
+
+/// Position and appearance of the moon as seen by an observer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LunarPosition {
+    altitude: f64,
+    azimuth: f64,
+    illuminated_fraction: f64,
+    phase: MoonPhase,
+}
+
+impl LunarPosition {
+    /// Topocentric position of the moon at `date_time`
+    ///
+    /// The altitude includes lunar parallax, atmospheric refraction and the dip
+    /// of the horizon, matching [`super::solar_calculator::SolarPosition`].
+    pub(crate) fn new(
+        position: &Position,
+        date_time: &UtcDateTime,
+        atmosphere: &AtmosphericConditions,
+    ) -> Self {
+        let ctx = LunarContext::new(date_time);
+        let (right_ascension, declination) = ctx.equatorial();
+
+        let latitude = position.latitude().to_radians();
+        let declination = declination.to_radians();
+        let hour_angle =
+            (ctx.sidereal_time() + position.longitude() - right_ascension).to_radians();
+
+        let geocentric_altitude = (latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos())
+        .asin();
+        let azimuth = hour_angle
+            .sin()
+            .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos())
+            .to_degrees();
+
+        // Parallax lowers the moon along its vertical circle by up to about 1°
+        let horizontal_parallax = (constants::EARTH_RADIUS_KM / ctx.distance).asin();
+        let true_altitude =
+            (geocentric_altitude - horizontal_parallax * geocentric_altitude.cos()).to_degrees();
+
+        Self {
+            altitude: true_altitude + refraction(true_altitude, atmosphere),
+            azimuth: (azimuth + 180.0).rem_euclid(360.0),
+            illuminated_fraction: ctx.illuminated_fraction(),
+            phase: MoonPhase::from_elongation(ctx.elongation()),
+        }
+    }
+
+    /// Apparent altitude above the horizon (degrees)
+    pub(crate) fn altitude(&self) -> f64 {
+        self.altitude
+    }
+
+    /// Azimuth, eastward from north (degrees)
+    pub(crate) fn azimuth(&self) -> f64 {
+        self.azimuth
+    }
+
+    /// Illuminated fraction of the disc, from 0 (new) to 1 (full)
+    pub(crate) fn illuminated_fraction(&self) -> f64 {
+        self.illuminated_fraction
+    }
+
+    pub(crate) fn phase(&self) -> MoonPhase {
+        self.phase
+    }
+
+    /// Moonlight actually reaching the observer: the illuminated fraction while
+    /// the moon is above the horizon, otherwise zero
+    pub(crate) fn visible_illumination(&self) -> f64 {
+        if self.altitude > 0.0 {
+            self.illuminated_fraction
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+    use crate::domain::time::{SolarModel, solar_calculator::SolarPosition};
+
+    #[test]
+    fn geocentric_position_matches_meeus_example() {
+        // Meeus example 47.a: 1992-04-12 0h TD
+        let ctx = LunarContext::new(&UtcDateTime::new(1992, Month::April, 12, 0, 0, 0).unwrap());
+
+        assert!((ctx.longitude - 133.162655).abs() < 0.02);
+        assert!((ctx.latitude - -3.229126).abs() < 0.01);
+        assert!((ctx.distance - 368409.7).abs() < 50.0);
+
+        // Meeus example 48.a: same instant
+        assert!((ctx.illuminated_fraction() - 0.6786).abs() < 0.005);
+    }
+
+    #[test]
+    fn phases_of_april_2024() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let atmosphere = AtmosphericConditions::default();
+
+        let new_moon = LunarPosition::new(
+            &london,
+            &UtcDateTime::new(2024, Month::April, 8, 18, 21, 0).unwrap(),
+            &atmosphere,
+        );
+        assert_eq!(new_moon.phase(), MoonPhase::New);
+        assert!(new_moon.illuminated_fraction() < 0.01);
+
+        let first_quarter = LunarPosition::new(
+            &london,
+            &UtcDateTime::new(2024, Month::April, 15, 19, 13, 0).unwrap(),
+            &atmosphere,
+        );
+        assert_eq!(first_quarter.phase(), MoonPhase::FirstQuarter);
+        assert!((first_quarter.illuminated_fraction() - 0.5).abs() < 0.02);
+
+        let full_moon = LunarPosition::new(
+            &london,
+            &UtcDateTime::new(2024, Month::April, 23, 23, 49, 0).unwrap(),
+            &atmosphere,
+        );
+        assert_eq!(full_moon.phase(), MoonPhase::Full);
+        assert!(full_moon.illuminated_fraction() > 0.99);
+    }
+
+    #[test]
+    fn moon_covers_sun_during_total_eclipse() {
+        // Totality in Dallas on 2024-04-08 around 18:42 UTC
+        let dallas = Position::new(32.7767, -96.7970, 140.0).unwrap();
+        let instant = UtcDateTime::new(2024, Month::April, 8, 18, 42, 0).unwrap();
+
+        let moon = LunarPosition::new(&dallas, &instant, &AtmosphericConditions::default());
+        let sun = SolarPosition::new(&dallas, &instant, &SolarModel::default());
+
+        assert!((moon.altitude() - sun.altitude()).abs() < 0.3);
+        assert!((moon.azimuth() - sun.azimuth()).abs() < 0.3);
+        assert!(moon.visible_illumination() < 0.01);
+    }
+}
@@ -1 +1,9 @@
+pub(crate) mod lunar_calculator;
 pub(crate) mod solar_calculator;
+mod spa;
+
+// Re-export commonly used types
+pub use lunar_calculator::MoonPhase;
+pub use solar_calculator::{
+    AtmosphericConditions, SolarAlgorithm, SolarEvent, SolarEvents, SolarModel,
+};
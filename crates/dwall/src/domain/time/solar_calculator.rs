@@ -1,7 +1,15 @@
+use std::fmt;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use time::UtcDateTime;
+use time::{Date, UtcDateTime};
+
+use crate::{
+    DwallResult, Position,
+    config::{MAX_DELTA_T_SECONDS, MIN_DELTA_T_SECONDS},
+};
 
-use crate::Position;
+use super::{lunar_calculator::MoonPhase, spa};
 
 mod constants {
     pub(super) const EPOCH_J2000: f64 = 2451545.0;
@@ -18,9 +26,46 @@ mod constants {
 
     pub(super) const MINUTES_PER_DAY: f64 = 1440.0;
 
+    pub(super) const ARCMINUTES_PER_DEGREE: f64 = 60.0;
+
     pub(super) const SECONDS_PER_DAY: f64 = 86400.0;
 
-    pub(super) const ATMOSPHERIC_REFRACTION_MAX: f64 = 0.575;
+    /// Below this true altitude (degrees) the Sæmundsson formula diverges;
+    /// refraction is extrapolated with a `1 / tan(h)` tail instead
+    pub(super) const REFRACTION_FORMULA_MIN_ALTITUDE: f64 = -1.0;
+
+    /// Reference conditions of the Sæmundsson refraction formula
+    pub(super) const REFRACTION_REFERENCE_PRESSURE: f64 = 1010.0;
+
+    pub(super) const REFRACTION_REFERENCE_TEMPERATURE: f64 = 10.0;
+
+    pub(super) const CELSIUS_TO_KELVIN: f64 = 273.15;
+
+    /// Sea-level pressure of the International Standard Atmosphere (hPa)
+    pub(super) const ISA_SEA_LEVEL_PRESSURE: f64 = 1013.25;
+
+    /// Standard refraction at the apparent horizon (degrees, 34')
+    pub(super) const HORIZON_REFRACTION: f64 = 34.0 / 60.0;
+
+    /// Apparent solar semi-diameter (degrees, 16')
+    pub(super) const SOLAR_SEMI_DIAMETER: f64 = 16.0 / 60.0;
+
+    /// Horizon dip per square root of observer height (degrees per √m), including
+    /// terrestrial refraction: dip ≈ 1.76′ × √h
+    pub(super) const HORIZON_DIP_COEFFICIENT: f64 = 1.76 / 60.0;
+
+    /// Julian day of the Unix epoch (1970-01-01T00:00:00Z)
+    pub(super) const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+    pub(super) const CIVIL_TWILIGHT_ALTITUDE: f64 = -6.0;
+
+    pub(super) const NAUTICAL_TWILIGHT_ALTITUDE: f64 = -12.0;
+
+    pub(super) const ASTRONOMICAL_TWILIGHT_ALTITUDE: f64 = -18.0;
+
+    /// Fixed-point iterations used when solving for event times;
+    /// three passes converge well below one second outside the polar regions
+    pub(super) const EVENT_SOLVER_ITERATIONS: usize = 3;
 }
 
 struct SolarContext {
@@ -76,6 +121,12 @@ impl SolarCalc {
         (jd - constants::EPOCH_J2000) / constants::JULIAN_CENTURY_DAYS
     }
 
+    /// Julian century for a fractional Unix timestamp (seconds)
+    fn julian_century_from_timestamp(timestamp: f64) -> f64 {
+        let jd = constants::UNIX_EPOCH_JULIAN_DAY + timestamp / constants::SECONDS_PER_DAY;
+        (jd - constants::EPOCH_J2000) / constants::JULIAN_CENTURY_DAYS
+    }
+
     #[inline]
     fn earth_axial_tilt(t: f64) -> f64 {
         23.43929111 - 0.0130042 * t - 0.00000164 * t.powi(2) + 0.000000503 * t.powi(3)
@@ -145,27 +196,409 @@ impl SolarCalc {
         if ha > 180.0 { ha - 360.0 } else { ha }
     }
 
-    fn atmospheric_refraction(apparent_altitude: f64) -> f64 {
-        if apparent_altitude < -0.5 {
-            return 0.0;
-        }
-
-        let tan_h = (apparent_altitude + 7.31 / (apparent_altitude + 4.4))
-            .to_radians()
-            .tan();
-
-        let correction_deg = (1.0 / tan_h) / 60.0;
+    /// Refraction (degrees) to add to a true altitude to obtain the apparent one
+    ///
+    /// Uses the Sæmundsson formula, scaled from its reference conditions
+    /// (1010 hPa, 10 °C) to the given pressure and temperature.
+    fn atmospheric_refraction(true_altitude: f64, atmosphere: &AtmosphericConditions) -> f64 {
+        let saemundsson = |h: f64| {
+            let arcminutes = 1.02 / (h + 10.3 / (h + 5.11)).to_radians().tan();
+            arcminutes / constants::ARCMINUTES_PER_DEGREE
+        };
+
+        let correction_deg = if true_altitude >= constants::REFRACTION_FORMULA_MIN_ALTITUDE {
+            saemundsson(true_altitude)
+        } else {
+            // Continue with the 1/tan(h) decay used by NOAA below the horizon,
+            // matched to the formula at its lower bound
+            let min_altitude = constants::REFRACTION_FORMULA_MIN_ALTITUDE;
+            saemundsson(min_altitude) * min_altitude.to_radians().tan()
+                / true_altitude.to_radians().tan()
+        };
         debug_assert!(
             correction_deg >= 0.0,
             "Atmospheric refraction correction must not be negative (elevation = {}°)",
-            apparent_altitude
+            true_altitude
         );
 
-        correction_deg.clamp(0.0, constants::ATMOSPHERIC_REFRACTION_MAX)
+        correction_deg.max(0.0) * atmosphere.refraction_scale()
+    }
+
+    /// Angle (degrees) by which the visible horizon lies below the astronomical
+    /// horizon for an observer `height` meters above the surrounding surface
+    fn horizon_dip(height: f64) -> f64 {
+        if height <= 0.0 {
+            return 0.0;
+        }
+        constants::HORIZON_DIP_COEFFICIENT * height.sqrt()
+    }
+}
+
+/// Refraction (degrees) to add to a true altitude, shared with the SPA and lunar implementations
+pub(super) fn refraction(true_altitude: f64, atmosphere: &AtmosphericConditions) -> f64 {
+    SolarCalc::atmospheric_refraction(true_altitude, atmosphere)
+}
+
+/// Algorithm used to compute the position of the sun
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "UPPERCASE", tag = "type")]
+pub enum SolarAlgorithm {
+    /// Low-order series, accurate to about 0.01° and cheap to evaluate
+    #[default]
+    Standard,
+
+    /// NREL Solar Position Algorithm, accurate to about 0.0003°
+    Spa {
+        /// TT − UT in seconds; estimated from the date when omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schemars(range(min = MIN_DELTA_T_SECONDS, max = MAX_DELTA_T_SECONDS))]
+        delta_t: Option<f64>,
+    },
+}
+
+/// Everything besides time and place that determines the computed sun position
+/// and the solar events
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SolarModel {
+    algorithm: SolarAlgorithm,
+    atmosphere: AtmosphericConditions,
+    /// Height (meters) of the observer above the terrain forming their horizon
+    horizon_height: f64,
+}
+
+impl SolarModel {
+    pub fn new(algorithm: SolarAlgorithm, atmosphere: AtmosphericConditions) -> Self {
+        Self {
+            algorithm,
+            atmosphere,
+            horizon_height: 0.0,
+        }
+    }
+
+    /// Lowers the visible horizon by the dip for an observer `height` meters above
+    /// the surrounding terrain, which moves sunrise earlier and sunset later
+    pub fn with_horizon_height(mut self, height: f64) -> Self {
+        self.horizon_height = height;
+        self
+    }
+
+    pub fn algorithm(&self) -> SolarAlgorithm {
+        self.algorithm
+    }
+
+    pub fn atmosphere(&self) -> &AtmosphericConditions {
+        &self.atmosphere
     }
+
+    pub fn horizon_height(&self) -> f64 {
+        self.horizon_height
+    }
+}
+
+/// Air pressure and temperature at the observer, used to scale refraction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtmosphericConditions {
+    /// Surface air pressure (hPa)
+    pressure: f64,
+    /// Surface air temperature (°C)
+    temperature: f64,
+}
+
+impl AtmosphericConditions {
+    pub fn new(pressure: f64, temperature: f64) -> Self {
+        Self {
+            pressure,
+            temperature,
+        }
+    }
+
+    /// Pressure of the International Standard Atmosphere at `altitude` meters
+    pub fn standard_pressure_at(altitude: f64) -> f64 {
+        constants::ISA_SEA_LEVEL_PRESSURE * (1.0 - 2.25577e-5 * altitude.max(0.0)).powf(5.25588)
+    }
+
+    pub fn pressure(&self) -> f64 {
+        self.pressure
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Ratio of the refraction under these conditions to the reference refraction
+    fn refraction_scale(&self) -> f64 {
+        (self.pressure / constants::REFRACTION_REFERENCE_PRESSURE)
+            * ((constants::CELSIUS_TO_KELVIN + constants::REFRACTION_REFERENCE_TEMPERATURE)
+                / (constants::CELSIUS_TO_KELVIN + self.temperature))
+    }
+}
+
+impl Default for AtmosphericConditions {
+    /// The reference conditions of the refraction formula (1010 hPa, 10 °C)
+    fn default() -> Self {
+        Self::new(
+            constants::REFRACTION_REFERENCE_PRESSURE,
+            constants::REFRACTION_REFERENCE_TEMPERATURE,
+        )
+    }
+}
+
+/// Solves for the instants at which the sun crosses given geometric altitudes
+struct SolarEventSolver {
+    latitude_rad: f64,
+    longitude: f64,
+    /// Unix timestamp (seconds) of 00:00 UTC on the requested date
+    midnight_timestamp: f64,
+}
+
+impl SolarEventSolver {
+    fn new(position: &Position, midnight: &UtcDateTime) -> Self {
+        Self {
+            latitude_rad: position.latitude().to_radians(),
+            longitude: position.longitude(),
+            midnight_timestamp: midnight.timestamp() as f64,
+        }
+    }
+
+    /// Timestamp of the local meridian transit closest to the given estimate
+    ///
+    /// The hour angle is zero when `utc_hours + longitude / 15 + eot = 12`.
+    fn transit_timestamp(&self, estimate: f64) -> f64 {
+        let t = SolarCalc::julian_century_from_timestamp(estimate);
+        let eot_hours = SolarCalc::solar_time_correction(t, SolarCalc::earth_axial_tilt(t));
+
+        self.midnight_timestamp
+            + (12.0 - self.longitude / constants::EARTH_ROTATION_RATE - eot_hours)
+                * constants::SECONDS_PER_HOUR
+    }
+
+    /// Timestamp of solar noon (upper culmination) on the requested date
+    fn solar_noon(&self) -> f64 {
+        let mut noon = self.midnight_timestamp + 12.0 * constants::SECONDS_PER_HOUR;
+        for _ in 0..constants::EVENT_SOLVER_ITERATIONS {
+            noon = self.transit_timestamp(noon);
+        }
+        noon
+    }
+
+    /// Instant at which the sun's center crosses `altitude_deg`
+    ///
+    /// `rising` selects the morning crossing; otherwise the evening crossing is returned.
+    fn crossing(&self, solar_noon: f64, altitude_deg: f64, rising: bool) -> SolarEvent {
+        let sin_target = altitude_deg.to_radians().sin();
+        let mut estimate = solar_noon;
+
+        for _ in 0..constants::EVENT_SOLVER_ITERATIONS {
+            let t = SolarCalc::julian_century_from_timestamp(estimate);
+            let declination_rad =
+                SolarCalc::solar_declination(t, SolarCalc::earth_axial_tilt(t)).to_radians();
+
+            let cos_hour_angle = (sin_target - self.latitude_rad.sin() * declination_rad.sin())
+                / (self.latitude_rad.cos() * declination_rad.cos());
+
+            if cos_hour_angle > 1.0 {
+                return SolarEvent::NeverRises;
+            }
+            if cos_hour_angle < -1.0 {
+                return SolarEvent::NeverSets;
+            }
+
+            let half_arc_seconds = cos_hour_angle.acos().to_degrees()
+                / constants::EARTH_ROTATION_RATE
+                * constants::SECONDS_PER_HOUR;
+            let transit = self.transit_timestamp(estimate);
+
+            estimate = if rising {
+                transit - half_arc_seconds
+            } else {
+                transit + half_arc_seconds
+            };
+        }
+
+        match to_utc_date_time(estimate) {
+            Some(instant) => SolarEvent::At(instant),
+            // Only reachable for events before the Unix epoch
+            None => SolarEvent::NeverRises,
+        }
+    }
+}
+
+fn to_utc_date_time(timestamp: f64) -> Option<UtcDateTime> {
+    (timestamp.is_finite() && timestamp >= 0.0)
+        .then(|| UtcDateTime::from_timestamp(timestamp.round() as u64))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The instant the sun crosses an altitude on a given day, or why it does not
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolarEvent {
+    /// The sun crosses the altitude at this instant
+    At(UtcDateTime),
+    /// The sun stays below the altitude for the whole day (e.g. polar night)
+    NeverRises,
+    /// The sun stays above the altitude for the whole day (e.g. midnight sun)
+    NeverSets,
+}
+
+impl SolarEvent {
+    /// Returns the instant of the event, if it happens on this day
+    pub fn time(&self) -> Option<UtcDateTime> {
+        match self {
+            SolarEvent::At(instant) => Some(*instant),
+            SolarEvent::NeverRises | SolarEvent::NeverSets => None,
+        }
+    }
+}
+
+impl fmt::Display for SolarEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolarEvent::At(instant) => write!(f, "{instant}"),
+            SolarEvent::NeverRises => write!(f, "never rises"),
+            SolarEvent::NeverSets => write!(f, "never sets"),
+        }
+    }
+}
+
+/// Sunrise, sunset, solar noon and twilight boundaries for one day at one place
+///
+/// The day is the calendar date at the observer's meridian: events are solved
+/// around the solar noon that falls on `date`, so sunrise precedes solar noon and
+/// sunset follows it even when they land on a different UTC date.
+///
+/// Sunrise and sunset happen when the upper limb of the sun touches the visible
+/// horizon: the geometric altitude is lowered by the solar semi-diameter, the
+/// refraction at the horizon under the given conditions and the horizon dip for
+/// the model's horizon height (-0.833° with a flat horizon under standard
+/// conditions).
+/// Civil, nautical and astronomical twilight use the geometric altitudes -6°,
+/// -12° and -18° respectively.
+///
+/// Event times are always solved with the low-order series, which is accurate
+/// to well under a minute; the culmination altitudes follow the selected
+/// [`SolarAlgorithm`].
+#[derive(Debug, Clone, Copy)]
+pub struct SolarEvents {
+    solar_noon: UtcDateTime,
+    sunrise: SolarEvent,
+    sunset: SolarEvent,
+    civil_dawn: SolarEvent,
+    civil_dusk: SolarEvent,
+    nautical_dawn: SolarEvent,
+    nautical_dusk: SolarEvent,
+    astronomical_dawn: SolarEvent,
+    astronomical_dusk: SolarEvent,
+    noon_altitude: f64,
+    midnight_altitude: f64,
+}
+
+impl SolarEvents {
+    /// Calculates the solar events of `date` for the given position
+    pub fn calculate(position: &Position, date: Date, model: &SolarModel) -> DwallResult<Self> {
+        let midnight = date.midnight_utc()?;
+        let solver = SolarEventSolver::new(position, &midnight);
+
+        let noon_timestamp = solver.solar_noon();
+        let solar_noon = to_utc_date_time(noon_timestamp).ok_or(time::Error::Overflow)?;
+
+        let noon_altitude = SolarPosition::new(position, &solar_noon, model).altitude();
+        // The lower culmination happens twelve hours from the upper one; take the
+        // lower of the two neighbouring ones as the day's minimum
+        let half_day = (constants::SECONDS_PER_DAY / 2.0) as u64;
+        let midnight_altitude = [
+            solar_noon.sub_seconds(half_day).ok(),
+            solar_noon.add_seconds(half_day).ok(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|instant| SolarPosition::new(position, &instant, model).altitude())
+        .fold(f64::INFINITY, f64::min);
+
+        let pair = |altitude: f64| {
+            (
+                solver.crossing(noon_timestamp, altitude, true),
+                solver.crossing(noon_timestamp, altitude, false),
+            )
+        };
+
+        let sunrise_altitude = -(constants::SOLAR_SEMI_DIAMETER
+            + constants::HORIZON_REFRACTION * model.atmosphere().refraction_scale()
+            + SolarCalc::horizon_dip(model.horizon_height()));
+        let (sunrise, sunset) = pair(sunrise_altitude);
+        let (civil_dawn, civil_dusk) = pair(constants::CIVIL_TWILIGHT_ALTITUDE);
+        let (nautical_dawn, nautical_dusk) = pair(constants::NAUTICAL_TWILIGHT_ALTITUDE);
+        let (astronomical_dawn, astronomical_dusk) =
+            pair(constants::ASTRONOMICAL_TWILIGHT_ALTITUDE);
+
+        Ok(Self {
+            solar_noon,
+            sunrise,
+            sunset,
+            civil_dawn,
+            civil_dusk,
+            nautical_dawn,
+            nautical_dusk,
+            astronomical_dawn,
+            astronomical_dusk,
+            noon_altitude,
+            midnight_altitude,
+        })
+    }
+
+    /// Instant of the upper culmination (the sun crosses the local meridian)
+    pub fn solar_noon(&self) -> UtcDateTime {
+        self.solar_noon
+    }
+
+    pub fn sunrise(&self) -> SolarEvent {
+        self.sunrise
+    }
+
+    pub fn sunset(&self) -> SolarEvent {
+        self.sunset
+    }
+
+    pub fn civil_dawn(&self) -> SolarEvent {
+        self.civil_dawn
+    }
+
+    pub fn civil_dusk(&self) -> SolarEvent {
+        self.civil_dusk
+    }
+
+    pub fn nautical_dawn(&self) -> SolarEvent {
+        self.nautical_dawn
+    }
+
+    pub fn nautical_dusk(&self) -> SolarEvent {
+        self.nautical_dusk
+    }
+
+    pub fn astronomical_dawn(&self) -> SolarEvent {
+        self.astronomical_dawn
+    }
+
+    pub fn astronomical_dusk(&self) -> SolarEvent {
+        self.astronomical_dusk
+    }
+
+    /// Apparent altitude at solar noon (see [`SolarPosition`]), the day's maximum
+    pub fn noon_altitude(&self) -> f64 {
+        self.noon_altitude
+    }
+
+    /// Apparent altitude at solar midnight (see [`SolarPosition`]), the day's minimum
+    pub fn midnight_altitude(&self) -> f64 {
+        self.midnight_altitude
+    }
+}
+
+/// Position of the sun as seen by an observer
+///
+/// The altitude is measured from the astronomical horizon and includes refraction
+/// for the given atmospheric conditions. The horizon dip only moves the sunrise and
+/// sunset of [`SolarEvents`], so theme images keep matching the sun's height in the
+/// sky wherever the observer stands.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub(crate) struct SolarPosition {
     altitude: f64,
     azimuth: f64,
@@ -173,20 +606,32 @@ pub(crate) struct SolarPosition {
 
 impl SolarPosition {
     #[inline]
-    pub(crate) fn new(position: &Position, date_time: &UtcDateTime) -> Self {
-        let ctx = SolarContext::new(position, date_time);
-        Self {
-            altitude: Self::altitude_from_context(&ctx),
-            azimuth: Self::azimuth_from_context(&ctx),
-        }
+    pub(crate) fn new(position: &Position, date_time: &UtcDateTime, model: &SolarModel) -> Self {
+        let (altitude, azimuth) = match model.algorithm() {
+            SolarAlgorithm::Standard => {
+                let ctx = SolarContext::new(position, date_time);
+                (
+                    Self::altitude_from_context(&ctx, model.atmosphere()),
+                    Self::azimuth_from_context(&ctx),
+                )
+            }
+            SolarAlgorithm::Spa { delta_t } => {
+                let delta_t =
+                    delta_t.unwrap_or_else(|| spa::estimate_delta_t(spa::decimal_year(date_time)));
+                let sun = spa::solar_position(position, date_time, delta_t, model.atmosphere());
+                (sun.altitude, sun.azimuth)
+            }
+        };
+
+        Self { altitude, azimuth }
     }
 
-    fn altitude_from_context(ctx: &SolarContext) -> f64 {
+    fn altitude_from_context(ctx: &SolarContext, atmosphere: &AtmosphericConditions) -> f64 {
         let sin_alt = ctx.latitude_rad.sin() * ctx.declination_rad.sin()
             + ctx.latitude_rad.cos() * ctx.declination_rad.cos() * ctx.hour_angle_rad.cos();
 
         let true_altitude = sin_alt.asin().to_degrees();
-        true_altitude + SolarCalc::atmospheric_refraction(true_altitude)
+        true_altitude + SolarCalc::atmospheric_refraction(true_altitude, atmosphere)
     }
 
     fn azimuth_from_context(ctx: &SolarContext) -> f64 {
@@ -212,23 +657,227 @@ impl SolarPosition {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SolarAngle {
     index: u8,
     altitude: f64,
     azimuth: f64,
+    /// Restricts the image to nights around this moon phase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    moon_phase: Option<MoonPhase>,
 }
 
 impl SolarAngle {
-    pub(crate) fn index(&self) -> u8 {
+    /// Sun position at which the image `index` is shown, in degrees
+    pub fn new(index: u8, altitude: f64, azimuth: f64) -> Self {
+        Self {
+            index,
+            altitude,
+            azimuth,
+            moon_phase: None,
+        }
+    }
+
+    pub fn index(&self) -> u8 {
         self.index
     }
 
-    pub(crate) fn altitude(&self) -> f64 {
+    pub fn altitude(&self) -> f64 {
         self.altitude
     }
 
-    pub(crate) fn azimuth(&self) -> f64 {
+    pub fn azimuth(&self) -> f64 {
         self.azimuth
     }
+
+    pub fn moon_phase(&self) -> Option<MoonPhase> {
+        self.moon_phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+
+    /// Allowed deviation from published almanac times, in seconds
+    const EVENT_TOLERANCE_SECS: i64 = 180;
+
+    fn assert_event_near(event: SolarEvent, expected: UtcDateTime) {
+        let actual = event
+            .time()
+            .unwrap_or_else(|| panic!("expected an event near {expected}, got {event}"));
+        let diff = actual.diff_seconds(&expected).abs();
+        assert!(
+            diff <= EVENT_TOLERANCE_SECS,
+            "event {actual} is {diff}s away from {expected}"
+        );
+    }
+
+    fn utc(year: u16, month: Month, day: u8, hour: u8, minute: u8) -> UtcDateTime {
+        UtcDateTime::new(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn london_summer_solstice() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let date = Date::new(2024, Month::June, 21).unwrap();
+        let events = SolarEvents::calculate(&london, date, &SolarModel::default()).unwrap();
+
+        assert_event_near(
+            SolarEvent::At(events.solar_noon()),
+            utc(2024, Month::June, 21, 12, 2),
+        );
+        assert_event_near(events.sunrise(), utc(2024, Month::June, 21, 3, 43));
+        assert_event_near(events.sunset(), utc(2024, Month::June, 21, 20, 21));
+        assert_event_near(events.civil_dawn(), utc(2024, Month::June, 21, 2, 58));
+        assert_event_near(events.civil_dusk(), utc(2024, Month::June, 21, 21, 7));
+
+        // The sun stays above -18° all night in London around the solstice
+        assert_eq!(events.astronomical_dawn(), SolarEvent::NeverSets);
+        assert_eq!(events.astronomical_dusk(), SolarEvent::NeverSets);
+    }
+
+    #[test]
+    fn events_are_ordered() {
+        let tokyo = Position::new(35.6762, 139.6503, 40.0).unwrap();
+        let date = Date::new(2024, Month::March, 20).unwrap();
+        let events = SolarEvents::calculate(&tokyo, date, &SolarModel::default()).unwrap();
+
+        let ordered = [
+            events.astronomical_dawn(),
+            events.nautical_dawn(),
+            events.civil_dawn(),
+            events.sunrise(),
+            SolarEvent::At(events.solar_noon()),
+            events.sunset(),
+            events.civil_dusk(),
+            events.nautical_dusk(),
+            events.astronomical_dusk(),
+        ]
+        .map(|event| event.time().unwrap());
+
+        assert!(ordered.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(events.noon_altitude() > 0.0);
+        assert!(events.midnight_altitude() < -18.0);
+    }
+
+    #[test]
+    fn midnight_sun_never_sets() {
+        let tromso = Position::new(69.6492, 18.9553, 10.0).unwrap();
+        let date = Date::new(2024, Month::June, 21).unwrap();
+        let events = SolarEvents::calculate(&tromso, date, &SolarModel::default()).unwrap();
+
+        assert_eq!(events.sunrise(), SolarEvent::NeverSets);
+        assert_eq!(events.sunset(), SolarEvent::NeverSets);
+        assert!(events.midnight_altitude() > 0.0);
+    }
+
+    #[test]
+    fn polar_night_never_rises() {
+        let longyearbyen = Position::new(78.2232, 15.6267, 10.0).unwrap();
+        let date = Date::new(2024, Month::December, 21).unwrap();
+        let events = SolarEvents::calculate(&longyearbyen, date, &SolarModel::default()).unwrap();
+
+        assert_eq!(events.sunrise(), SolarEvent::NeverRises);
+        assert_eq!(events.sunset(), SolarEvent::NeverRises);
+        assert_eq!(events.civil_dawn(), SolarEvent::NeverRises);
+        assert!(events.noon_altitude() < 0.0);
+        assert_eq!(events.sunrise().to_string(), "never rises");
+    }
+
+    #[test]
+    fn polar_night_still_has_nautical_twilight() {
+        let tromso = Position::new(69.6492, 18.9553, 10.0).unwrap();
+        let date = Date::new(2024, Month::December, 21).unwrap();
+        let events = SolarEvents::calculate(&tromso, date, &SolarModel::default()).unwrap();
+
+        assert_eq!(events.sunrise(), SolarEvent::NeverRises);
+        assert!(events.civil_dawn().time().is_some());
+        assert!(events.nautical_dawn().time().is_some());
+    }
+
+    #[test]
+    fn refraction_follows_conditions() {
+        let standard = AtmosphericConditions::default();
+        // ~34' at the horizon under the reference conditions
+        let horizon = SolarCalc::atmospheric_refraction(-0.55, &standard);
+        assert!((horizon - 34.0 / 60.0).abs() < 0.02, "{horizon}");
+
+        // Thinner, warmer air refracts less; colder, denser air refracts more
+        let thin = AtmosphericConditions::new(700.0, 25.0);
+        let dense = AtmosphericConditions::new(1040.0, -20.0);
+        assert!(SolarCalc::atmospheric_refraction(-0.55, &thin) < horizon);
+        assert!(SolarCalc::atmospheric_refraction(-0.55, &dense) > horizon);
+
+        // Continuous across the formula's lower bound and fading below the horizon
+        let at_bound = SolarCalc::atmospheric_refraction(-1.0, &standard);
+        let below_bound = SolarCalc::atmospheric_refraction(-1.0001, &standard);
+        assert!((at_bound - below_bound).abs() < 1e-3);
+        assert!(SolarCalc::atmospheric_refraction(-10.0, &standard) < 0.1);
+    }
+
+    #[test]
+    fn standard_pressure_decreases_with_altitude() {
+        let sea_level = AtmosphericConditions::standard_pressure_at(0.0);
+        assert!((sea_level - 1013.25).abs() < 1e-9);
+        // ~795 hPa at 2000 m in the standard atmosphere
+        assert!((AtmosphericConditions::standard_pressure_at(2000.0) - 795.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn elevated_observer_sees_longer_day() {
+        let date = Date::new(2024, Month::June, 21).unwrap();
+        let position = Position::new(46.0, 8.0, 0.0).unwrap();
+        let flat = SolarModel::default();
+        let summit = SolarModel::default().with_horizon_height(2000.0);
+
+        let low = SolarEvents::calculate(&position, date, &flat).unwrap();
+        let high = SolarEvents::calculate(&position, date, &summit).unwrap();
+
+        // A 2000 m horizon dip (~1.3°) brings sunrise forward by several minutes
+        let gain = low
+            .sunrise()
+            .time()
+            .unwrap()
+            .diff_seconds(&high.sunrise().time().unwrap());
+        assert!(gain > 5 * 60, "sunrise only {gain}s earlier");
+        assert!(high.sunset().time().unwrap() > low.sunset().time().unwrap());
+        // Twilight boundaries are geometric and unaffected by the dip
+        assert_eq!(low.civil_dawn(), high.civil_dawn());
+        // So is the sun position itself
+        assert_eq!(low.noon_altitude(), high.noon_altitude());
+    }
+
+    #[test]
+    fn altitude_above_sea_level_adds_no_dip() {
+        let date = Date::new(2024, Month::June, 21).unwrap();
+        let sea_level = Position::new(46.0, 8.0, 0.0).unwrap();
+        let mountain_town = Position::new(46.0, 8.0, 2000.0).unwrap();
+        let model = SolarModel::default();
+
+        let low = SolarEvents::calculate(&sea_level, date, &model).unwrap();
+        let high = SolarEvents::calculate(&mountain_town, date, &model).unwrap();
+        assert_eq!(low.sunrise(), high.sunrise());
+        assert_eq!(low.noon_altitude(), high.noon_altitude());
+    }
+
+    #[test]
+    fn spa_agrees_with_standard_series() {
+        let london = Position::new(51.5074, -0.1278, 0.0).unwrap();
+        let spa = SolarModel::new(
+            SolarAlgorithm::Spa { delta_t: None },
+            AtmosphericConditions::default(),
+        );
+
+        for hour in [6, 9, 12, 15, 18] {
+            let instant = utc(2024, Month::June, 21, hour, 0);
+            let standard = SolarPosition::new(&london, &instant, &SolarModel::default());
+            let precise = SolarPosition::new(&london, &instant, &spa);
+
+            assert!((standard.altitude() - precise.altitude()).abs() < 0.05);
+            assert!((standard.azimuth() - precise.azimuth()).abs() < 0.05);
+        }
+    }
 }
@@ -0,0 +1,715 @@
+//! NREL Solar Position Algorithm (SPA)
+//!
+//! Implementation of Reda & Andreas, "Solar Position Algorithm for Solar Radiation
+//! Applications" (NREL/TP-560-34302, revised 2008). Accurate to ±0.0003° for
+//! years -2000 to 6000, at the cost of evaluating a few hundred periodic terms.
+
+use time::UtcDateTime;
+
+use crate::Position;
+
+use super::solar_calculator::AtmosphericConditions;
+
+mod constants {
+    pub(super) const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+    pub(super) const EPOCH_J2000: f64 = 2451545.0;
+
+    pub(super) const JULIAN_CENTURY_DAYS: f64 = 36525.0;
+
+    pub(super) const SECONDS_PER_DAY: f64 = 86400.0;
+
+    /// Equatorial radius of the Earth (meters)
+    pub(super) const EARTH_RADIUS: f64 = 6378140.0;
+
+    /// Ratio of the polar to the equatorial radius of the Earth
+    pub(super) const EARTH_FLATTENING_RATIO: f64 = 0.99664719;
+}
+
+// The periodic term tables below are copied verbatim from the SPA paper; some
+// phase coefficients happen to round to π, which is not what they mean.
+
+/// Earth heliocentric longitude terms `[A, B, C]`, evaluated as `A * cos(B + C * JME)`
+const L0: [[f64; 3]; 64] = [
+    [175347046.0, 0.0, 0.0],
+    [3341656.0, 4.6692568, 6283.07585],
+    [34894.0, 4.6261, 12566.1517],
+    [3497.0, 2.7441, 5753.3849],
+    [3418.0, 2.8289, 3.5231],
+    [3136.0, 3.6277, 77713.7715],
+    [2676.0, 4.4181, 7860.4194],
+    [2343.0, 6.1352, 3930.2097],
+    [1324.0, 0.7425, 11506.7698],
+    [1273.0, 2.0371, 529.691],
+    [1199.0, 1.1096, 1577.3435],
+    [990.0, 5.233, 5884.927],
+    [902.0, 2.045, 26.298],
+    [857.0, 3.508, 398.149],
+    [780.0, 1.179, 5223.694],
+    [753.0, 2.533, 5507.553],
+    [505.0, 4.583, 18849.228],
+    [492.0, 4.205, 775.523],
+    [357.0, 2.92, 0.067],
+    [317.0, 5.849, 11790.629],
+    [284.0, 1.899, 796.298],
+    [271.0, 0.315, 10977.079],
+    [243.0, 0.345, 5486.778],
+    [206.0, 4.806, 2544.314],
+    [205.0, 1.869, 5573.143],
+    [202.0, 2.458, 6069.777],
+    [156.0, 0.833, 213.299],
+    [132.0, 3.411, 2942.463],
+    [126.0, 1.083, 20.775],
+    [115.0, 0.645, 0.98],
+    [103.0, 0.636, 4694.003],
+    [102.0, 0.976, 15720.839],
+    [102.0, 4.267, 7.114],
+    [99.0, 6.21, 2146.17],
+    [98.0, 0.68, 155.42],
+    [86.0, 5.98, 161000.69],
+    [85.0, 1.3, 6275.96],
+    [85.0, 3.67, 71430.7],
+    [80.0, 1.81, 17260.15],
+    [79.0, 3.04, 12036.46],
+    [75.0, 1.76, 5088.63],
+    [74.0, 3.5, 3154.69],
+    [74.0, 4.68, 801.82],
+    [70.0, 0.83, 9437.76],
+    [62.0, 3.98, 8827.39],
+    [61.0, 1.82, 7084.9],
+    [57.0, 2.78, 6286.6],
+    [56.0, 4.39, 14143.5],
+    [56.0, 3.47, 6279.55],
+    [52.0, 0.19, 12139.55],
+    [52.0, 1.33, 1748.02],
+    [51.0, 0.28, 5856.48],
+    [49.0, 0.49, 1194.45],
+    [41.0, 5.37, 8429.24],
+    [41.0, 2.4, 19651.05],
+    [39.0, 6.17, 10447.39],
+    [37.0, 6.04, 10213.29],
+    [37.0, 2.57, 1059.38],
+    [36.0, 1.71, 2352.87],
+    [36.0, 1.78, 6812.77],
+    [33.0, 0.59, 17789.85],
+    [30.0, 0.44, 83996.85],
+    [30.0, 2.74, 1349.87],
+    [25.0, 3.16, 4690.48],
+];
+
+const L1: [[f64; 3]; 34] = [
+    [628331966747.0, 0.0, 0.0],
+    [206059.0, 2.678235, 6283.07585],
+    [4303.0, 2.6351, 12566.1517],
+    [425.0, 1.59, 3.523],
+    [119.0, 5.796, 26.298],
+    [109.0, 2.966, 1577.344],
+    [93.0, 2.59, 18849.23],
+    [72.0, 1.14, 529.69],
+    [68.0, 1.87, 398.15],
+    [67.0, 4.41, 5507.55],
+    [59.0, 2.89, 5223.69],
+    [56.0, 2.17, 155.42],
+    [45.0, 0.4, 796.3],
+    [36.0, 0.47, 775.52],
+    [29.0, 2.65, 7.11],
+    [21.0, 5.34, 0.98],
+    [19.0, 1.85, 5486.78],
+    [19.0, 4.97, 213.3],
+    [17.0, 2.99, 6275.96],
+    [16.0, 0.03, 2544.31],
+    [16.0, 1.43, 2146.17],
+    [15.0, 1.21, 10977.08],
+    [12.0, 2.83, 1748.02],
+    [12.0, 3.26, 5088.63],
+    [12.0, 5.27, 1194.45],
+    [12.0, 2.08, 4694.0],
+    [11.0, 0.77, 553.57],
+    [10.0, 1.3, 6286.6],
+    [10.0, 4.24, 1349.87],
+    [9.0, 2.7, 242.73],
+    [9.0, 5.64, 951.72],
+    [8.0, 5.3, 2352.87],
+    [6.0, 2.65, 9437.76],
+    [6.0, 4.67, 4690.48],
+];
+
+const L2: [[f64; 3]; 20] = [
+    [52919.0, 0.0, 0.0],
+    [8720.0, 1.0721, 6283.0758],
+    [309.0, 0.867, 12566.152],
+    [27.0, 0.05, 3.52],
+    [16.0, 5.19, 26.3],
+    [16.0, 3.68, 155.42],
+    [10.0, 0.76, 18849.23],
+    [9.0, 2.06, 77713.77],
+    [7.0, 0.83, 775.52],
+    [5.0, 4.66, 1577.34],
+    [4.0, 1.03, 7.11],
+    [4.0, 3.44, 5573.14],
+    [3.0, 5.14, 796.3],
+    [3.0, 6.05, 5507.55],
+    [3.0, 1.19, 242.73],
+    [3.0, 6.12, 529.69],
+    [3.0, 0.31, 398.15],
+    [3.0, 2.28, 553.57],
+    [2.0, 4.38, 5223.69],
+    [2.0, 3.75, 0.98],
+];
+
+const L3: [[f64; 3]; 7] = [
+    [289.0, 5.844, 6283.076],
+    [35.0, 0.0, 0.0],
+    [17.0, 5.49, 12566.15],
+    [3.0, 5.2, 155.42],
+    [1.0, 4.72, 3.52],
+    [1.0, 5.3, 18849.23],
+    [1.0, 5.97, 242.73],
+];
+
+#[allow(clippy::approx_constant)]
+const L4: [[f64; 3]; 3] = [
+    [114.0, 3.142, 0.0],
+    [8.0, 4.13, 6283.08],
+    [1.0, 3.84, 12566.15],
+];
+
+#[allow(clippy::approx_constant)]
+const L5: [[f64; 3]; 1] = [[1.0, 3.14, 0.0]];
+
+/// Earth heliocentric latitude terms
+const B0: [[f64; 3]; 5] = [
+    [280.0, 3.199, 84334.662],
+    [102.0, 5.422, 5507.553],
+    [80.0, 3.88, 5223.69],
+    [44.0, 3.7, 2352.87],
+    [32.0, 4.0, 1577.34],
+];
+
+const B1: [[f64; 3]; 2] = [[9.0, 3.9, 5507.55], [6.0, 1.73, 5223.69]];
+
+/// Earth radius vector terms
+const R0: [[f64; 3]; 40] = [
+    [100013989.0, 0.0, 0.0],
+    [1670700.0, 3.0984635, 6283.07585],
+    [13956.0, 3.05525, 12566.1517],
+    [3084.0, 5.1985, 77713.7715],
+    [1628.0, 1.1739, 5753.3849],
+    [1576.0, 2.8469, 7860.4194],
+    [925.0, 5.453, 11506.77],
+    [542.0, 4.564, 3930.21],
+    [472.0, 3.661, 5884.927],
+    [346.0, 0.964, 5507.553],
+    [329.0, 5.9, 5223.694],
+    [307.0, 0.299, 5573.143],
+    [243.0, 4.273, 11790.629],
+    [212.0, 5.847, 1577.344],
+    [186.0, 5.022, 10977.079],
+    [175.0, 3.012, 18849.228],
+    [110.0, 5.055, 5486.778],
+    [98.0, 0.89, 6069.78],
+    [86.0, 5.69, 15720.84],
+    [86.0, 1.27, 161000.69],
+    [65.0, 0.27, 17260.15],
+    [63.0, 0.92, 529.69],
+    [57.0, 2.01, 83996.85],
+    [56.0, 5.24, 71430.7],
+    [49.0, 3.25, 2544.31],
+    [47.0, 2.58, 775.52],
+    [45.0, 5.54, 9437.76],
+    [43.0, 6.01, 6275.96],
+    [39.0, 5.36, 4694.0],
+    [38.0, 2.39, 8827.39],
+    [37.0, 0.83, 19651.05],
+    [37.0, 4.9, 12139.55],
+    [36.0, 1.67, 12036.46],
+    [35.0, 1.84, 2942.46],
+    [33.0, 0.24, 7084.9],
+    [32.0, 0.18, 5088.63],
+    [32.0, 1.78, 398.15],
+    [28.0, 1.21, 6286.6],
+    [28.0, 1.9, 6279.55],
+    [26.0, 4.59, 10447.39],
+];
+
+#[allow(clippy::approx_constant)]
+const R1: [[f64; 3]; 10] = [
+    [103019.0, 1.10749, 6283.07585],
+    [1721.0, 1.0644, 12566.1517],
+    [702.0, 3.142, 0.0],
+    [32.0, 1.02, 18849.23],
+    [31.0, 2.84, 5507.55],
+    [25.0, 1.32, 5223.69],
+    [18.0, 1.42, 1577.34],
+    [10.0, 5.91, 10977.08],
+    [9.0, 1.42, 6275.96],
+    [9.0, 0.27, 5486.78],
+];
+
+#[allow(clippy::approx_constant)]
+const R2: [[f64; 3]; 6] = [
+    [4359.0, 5.7846, 6283.0758],
+    [124.0, 5.579, 12566.152],
+    [12.0, 3.14, 0.0],
+    [9.0, 3.63, 77713.77],
+    [6.0, 1.87, 5573.14],
+    [3.0, 5.47, 18849.23],
+];
+
+const R3: [[f64; 3]; 2] = [[145.0, 4.273, 6283.076], [7.0, 3.92, 12566.15]];
+
+const R4: [[f64; 3]; 1] = [[4.0, 2.56, 6283.08]];
+
+/// Multipliers of the fundamental arguments X0..X4 for each nutation term
+const NUTATION_ARGUMENTS: [[i8; 5]; 63] = [
+    [0, 0, 0, 0, 1],
+    [-2, 0, 0, 2, 2],
+    [0, 0, 0, 2, 2],
+    [0, 0, 0, 0, 2],
+    [0, 1, 0, 0, 0],
+    [0, 0, 1, 0, 0],
+    [-2, 1, 0, 2, 2],
+    [0, 0, 0, 2, 1],
+    [0, 0, 1, 2, 2],
+    [-2, -1, 0, 2, 2],
+    [-2, 0, 1, 0, 0],
+    [-2, 0, 0, 2, 1],
+    [0, 0, -1, 2, 2],
+    [2, 0, 0, 0, 0],
+    [0, 0, 1, 0, 1],
+    [2, 0, -1, 2, 2],
+    [0, 0, -1, 0, 1],
+    [0, 0, 1, 2, 1],
+    [-2, 0, 2, 0, 0],
+    [0, 0, -2, 2, 1],
+    [2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 2],
+    [0, 0, 2, 0, 0],
+    [-2, 0, 1, 2, 2],
+    [0, 0, 0, 2, 0],
+    [-2, 0, 0, 2, 0],
+    [0, 0, -1, 2, 1],
+    [0, 2, 0, 0, 0],
+    [2, 0, -1, 0, 1],
+    [-2, 2, 0, 2, 2],
+    [0, 1, 0, 0, 1],
+    [-2, 0, 1, 0, 1],
+    [0, -1, 0, 0, 1],
+    [0, 0, 2, -2, 0],
+    [2, 0, -1, 2, 1],
+    [2, 0, 1, 2, 2],
+    [0, 1, 0, 2, 2],
+    [-2, 1, 1, 0, 0],
+    [0, -1, 0, 2, 2],
+    [2, 0, 0, 2, 1],
+    [2, 0, 1, 0, 0],
+    [-2, 0, 2, 2, 2],
+    [-2, 0, 1, 2, 1],
+    [2, 0, -2, 0, 1],
+    [2, 0, 0, 0, 1],
+    [0, -1, 1, 0, 0],
+    [-2, -1, 0, 2, 1],
+    [-2, 0, 0, 0, 1],
+    [0, 0, 2, 2, 1],
+    [-2, 0, 2, 0, 1],
+    [-2, 1, 0, 2, 1],
+    [0, 0, 1, -2, 0],
+    [-1, 0, 1, 0, 0],
+    [-2, 1, 0, 0, 0],
+    [1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0],
+    [0, 0, -2, 2, 2],
+    [-1, -1, 1, 0, 0],
+    [0, 1, 1, 0, 0],
+    [0, -1, 1, 2, 2],
+    [2, -1, -1, 2, 2],
+    [0, 0, 3, 2, 2],
+    [2, -1, 0, 2, 2],
+];
+
+/// Nutation coefficients `[a, b, c, d]` in units of 0.0001″:
+/// Δψ term = `(a + b * JCE) * sin(arg)`, Δε term = `(c + d * JCE) * cos(arg)`
+const NUTATION_COEFFICIENTS: [[f64; 4]; 63] = [
+    [-171996.0, -174.2, 92025.0, 8.9],
+    [-13187.0, -1.6, 5736.0, -3.1],
+    [-2274.0, -0.2, 977.0, -0.5],
+    [2062.0, 0.2, -895.0, 0.5],
+    [1426.0, -3.4, 54.0, -0.1],
+    [712.0, 0.1, -7.0, 0.0],
+    [-517.0, 1.2, 224.0, -0.6],
+    [-386.0, -0.4, 200.0, 0.0],
+    [-301.0, 0.0, 129.0, -0.1],
+    [217.0, -0.5, -95.0, 0.3],
+    [-158.0, 0.0, 0.0, 0.0],
+    [129.0, 0.1, -70.0, 0.0],
+    [123.0, 0.0, -53.0, 0.0],
+    [63.0, 0.0, 0.0, 0.0],
+    [63.0, 0.1, -33.0, 0.0],
+    [-59.0, 0.0, 26.0, 0.0],
+    [-58.0, -0.1, 32.0, 0.0],
+    [-51.0, 0.0, 27.0, 0.0],
+    [48.0, 0.0, 0.0, 0.0],
+    [46.0, 0.0, -24.0, 0.0],
+    [-38.0, 0.0, 16.0, 0.0],
+    [-31.0, 0.0, 13.0, 0.0],
+    [29.0, 0.0, 0.0, 0.0],
+    [29.0, 0.0, -12.0, 0.0],
+    [26.0, 0.0, 0.0, 0.0],
+    [-22.0, 0.0, 0.0, 0.0],
+    [21.0, 0.0, -10.0, 0.0],
+    [17.0, -0.1, 0.0, 0.0],
+    [16.0, 0.0, -8.0, 0.0],
+    [-16.0, 0.1, 7.0, 0.0],
+    [-15.0, 0.0, 9.0, 0.0],
+    [-13.0, 0.0, 7.0, 0.0],
+    [-12.0, 0.0, 6.0, 0.0],
+    [11.0, 0.0, 0.0, 0.0],
+    [-10.0, 0.0, 5.0, 0.0],
+    [-8.0, 0.0, 3.0, 0.0],
+    [7.0, 0.0, -3.0, 0.0],
+    [-7.0, 0.0, 0.0, 0.0],
+    [-7.0, 0.0, 3.0, 0.0],
+    [-7.0, 0.0, 3.0, 0.0],
+    [6.0, 0.0, 0.0, 0.0],
+    [6.0, 0.0, -3.0, 0.0],
+    [6.0, 0.0, -3.0, 0.0],
+    [-6.0, 0.0, 3.0, 0.0],
+    [-6.0, 0.0, 3.0, 0.0],
+    [5.0, 0.0, 0.0, 0.0],
+    [-5.0, 0.0, 3.0, 0.0],
+    [-5.0, 0.0, 3.0, 0.0],
+    [-5.0, 0.0, 3.0, 0.0],
+    [4.0, 0.0, 0.0, 0.0],
+    [4.0, 0.0, 0.0, 0.0],
+    [4.0, 0.0, 0.0, 0.0],
+    [-4.0, 0.0, 0.0, 0.0],
+    [-4.0, 0.0, 0.0, 0.0],
+    [-4.0, 0.0, 0.0, 0.0],
+    [3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+    [-3.0, 0.0, 0.0, 0.0],
+];
+
+/// Estimates ΔT = TT − UT (seconds) for a decimal year
+///
+/// Uses the polynomial expressions of Espenak & Meeus (NASA, 2006). Only dates
+/// from 1970 onwards can be represented in this crate, so earlier ranges are omitted.
+pub(crate) fn estimate_delta_t(decimal_year: f64) -> f64 {
+    if decimal_year < 1986.0 {
+        let t = decimal_year - 1975.0;
+        45.45 + 1.067 * t - t.powi(2) / 260.0 - t.powi(3) / 718.0
+    } else if decimal_year < 2005.0 {
+        let t = decimal_year - 2000.0;
+        63.86 + 0.3345 * t - 0.060374 * t.powi(2)
+            + 0.0017275 * t.powi(3)
+            + 0.000651814 * t.powi(4)
+            + 0.00002373599 * t.powi(5)
+    } else if decimal_year < 2050.0 {
+        let t = decimal_year - 2000.0;
+        62.92 + 0.32217 * t + 0.005589 * t.powi(2)
+    } else if decimal_year < 2150.0 {
+        let u = (decimal_year - 1820.0) / 100.0;
+        -20.0 + 32.0 * u.powi(2) - 0.5628 * (2150.0 - decimal_year)
+    } else {
+        let u = (decimal_year - 1820.0) / 100.0;
+        -20.0 + 32.0 * u.powi(2)
+    }
+}
+
+/// Topocentric position of the sun as computed by SPA
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SpaPosition {
+    /// Topocentric elevation angle, including refraction (degrees)
+    pub(crate) altitude: f64,
+    /// Topocentric azimuth, eastward from north (degrees)
+    pub(crate) azimuth: f64,
+}
+
+/// Intermediate SPA quantities, exposed for checking against the reference output
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(test), allow(dead_code))]
+struct SpaTerms {
+    /// Earth heliocentric longitude (degrees)
+    heliocentric_longitude: f64,
+    /// Earth heliocentric latitude (degrees)
+    heliocentric_latitude: f64,
+    /// Earth radius vector (AU)
+    radius_vector: f64,
+    /// Nutation in longitude (degrees)
+    nutation_longitude: f64,
+    /// Nutation in obliquity (degrees)
+    nutation_obliquity: f64,
+    /// True obliquity of the ecliptic (degrees)
+    obliquity: f64,
+    /// Observer local hour angle (degrees)
+    hour_angle: f64,
+    /// Geocentric sun declination (degrees)
+    declination: f64,
+    /// Topocentric elevation angle without refraction (degrees)
+    true_altitude: f64,
+    /// Topocentric azimuth, eastward from north (degrees)
+    azimuth: f64,
+}
+
+/// Computes the topocentric solar position with SPA
+///
+/// `delta_t` is TT − UT in seconds; see [`estimate_delta_t`].
+pub(crate) fn solar_position(
+    position: &Position,
+    date_time: &UtcDateTime,
+    delta_t: f64,
+    atmosphere: &AtmosphericConditions,
+) -> SpaPosition {
+    let terms = SpaTerms::calculate(position, date_time, delta_t);
+    SpaPosition {
+        altitude: terms.true_altitude
+            + super::solar_calculator::refraction(terms.true_altitude, atmosphere),
+        azimuth: terms.azimuth,
+    }
+}
+
+/// Decimal year of the instant, used to estimate ΔT
+pub(crate) fn decimal_year(date_time: &UtcDateTime) -> f64 {
+    let year_start = UtcDateTime::new(date_time.year(), time::Month::January, 1, 0, 0, 0)
+        .map(|start| start.timestamp())
+        .unwrap_or_default();
+    let days_in_year = if date_time.is_leap_year() {
+        366.0
+    } else {
+        365.0
+    };
+    let elapsed_days =
+        date_time.timestamp().saturating_sub(year_start) as f64 / constants::SECONDS_PER_DAY;
+
+    date_time.year() as f64 + elapsed_days / days_in_year
+}
+
+impl SpaTerms {
+    fn calculate(position: &Position, date_time: &UtcDateTime, delta_t: f64) -> Self {
+        let jd = constants::UNIX_EPOCH_JULIAN_DAY
+            + date_time.timestamp() as f64 / constants::SECONDS_PER_DAY;
+        let jde = jd + delta_t / constants::SECONDS_PER_DAY;
+        let jc = (jd - constants::EPOCH_J2000) / constants::JULIAN_CENTURY_DAYS;
+        let jce = (jde - constants::EPOCH_J2000) / constants::JULIAN_CENTURY_DAYS;
+        let jme = jce / 10.0;
+
+        // Heliocentric coordinates of the Earth
+        let heliocentric_longitude =
+            limit_degrees(series(&[&L0, &L1, &L2, &L3, &L4, &L5], jme).to_degrees());
+        let heliocentric_latitude = series(&[&B0, &B1], jme).to_degrees();
+        let radius_vector = series(&[&R0, &R1, &R2, &R3, &R4], jme);
+
+        // Geocentric coordinates of the sun
+        let geocentric_longitude = limit_degrees(heliocentric_longitude + 180.0);
+        let geocentric_latitude = -heliocentric_latitude;
+
+        // Nutation and obliquity
+        let (nutation_longitude, nutation_obliquity) = nutation(jce);
+        let u = jme / 10.0;
+        let mean_obliquity_arcsec = 84381.448
+            + u * (-4680.93
+                + u * (-1.55
+                    + u * (1999.25
+                        + u * (-51.38
+                            + u * (-249.67
+                                + u * (-39.05
+                                    + u * (7.12 + u * (27.87 + u * (5.79 + u * 2.45)))))))));
+        let obliquity = mean_obliquity_arcsec / 3600.0 + nutation_obliquity;
+
+        // Aberration correction and apparent sun longitude
+        let aberration = -20.4898 / (3600.0 * radius_vector);
+        let apparent_longitude = geocentric_longitude + nutation_longitude + aberration;
+
+        // Apparent sidereal time at Greenwich
+        let mean_sidereal_time = limit_degrees(
+            280.46061837
+                + 360.98564736629 * (jd - constants::EPOCH_J2000)
+                + 0.000387933 * jc.powi(2)
+                - jc.powi(3) / 38710000.0,
+        );
+        let sidereal_time = mean_sidereal_time + nutation_longitude * obliquity.to_radians().cos();
+
+        // Geocentric right ascension and declination
+        let lambda = apparent_longitude.to_radians();
+        let epsilon = obliquity.to_radians();
+        let beta = geocentric_latitude.to_radians();
+        let right_ascension = limit_degrees(
+            (lambda.sin() * epsilon.cos() - beta.tan() * epsilon.sin())
+                .atan2(lambda.cos())
+                .to_degrees(),
+        );
+        let declination = (beta.sin() * epsilon.cos() + beta.cos() * epsilon.sin() * lambda.sin())
+            .asin()
+            .to_degrees();
+
+        let hour_angle = limit_degrees(sidereal_time + position.longitude() - right_ascension);
+
+        // Topocentric parallax correction
+        let latitude = position.latitude().to_radians();
+        let equatorial_parallax = (8.794 / (3600.0 * radius_vector)).to_radians();
+        let reduced_latitude = (constants::EARTH_FLATTENING_RATIO * latitude.tan()).atan();
+        let height_ratio = position.altitude() / constants::EARTH_RADIUS;
+        let x = reduced_latitude.cos() + height_ratio * latitude.cos();
+        let y = constants::EARTH_FLATTENING_RATIO * reduced_latitude.sin()
+            + height_ratio * latitude.sin();
+
+        let h = hour_angle.to_radians();
+        let delta = declination.to_radians();
+        let parallax_denominator = delta.cos() - x * equatorial_parallax.sin() * h.cos();
+        let parallax_right_ascension =
+            (-x * equatorial_parallax.sin() * h.sin()).atan2(parallax_denominator);
+        let topocentric_declination = ((delta.sin() - y * equatorial_parallax.sin())
+            * parallax_right_ascension.cos())
+        .atan2(parallax_denominator);
+        let topocentric_hour_angle = h - parallax_right_ascension;
+
+        // Topocentric elevation and azimuth
+        let true_altitude = (latitude.sin() * topocentric_declination.sin()
+            + latitude.cos() * topocentric_declination.cos() * topocentric_hour_angle.cos())
+        .asin()
+        .to_degrees();
+        let astronomers_azimuth = topocentric_hour_angle
+            .sin()
+            .atan2(
+                topocentric_hour_angle.cos() * latitude.sin()
+                    - topocentric_declination.tan() * latitude.cos(),
+            )
+            .to_degrees();
+        let azimuth = limit_degrees(astronomers_azimuth + 180.0);
+
+        Self {
+            heliocentric_longitude,
+            heliocentric_latitude,
+            radius_vector,
+            nutation_longitude,
+            nutation_obliquity,
+            obliquity,
+            hour_angle,
+            declination,
+            true_altitude,
+            azimuth,
+        }
+    }
+}
+
+/// Evaluates `Σ_i (Σ_j A cos(B + C·JME)) · JME^i / 10^8` for the given term tables
+fn series(tables: &[&[[f64; 3]]], jme: f64) -> f64 {
+    tables
+        .iter()
+        .enumerate()
+        .map(|(power, table)| {
+            let sum: f64 = table.iter().map(|[a, b, c]| a * (b + c * jme).cos()).sum();
+            sum * jme.powi(power as i32)
+        })
+        .sum::<f64>()
+        / 1.0e8
+}
+
+/// Nutation in longitude and obliquity (degrees)
+fn nutation(jce: f64) -> (f64, f64) {
+    let cubic = |a: f64, b: f64, c: f64, d: f64| a + jce * (b + jce * (c + jce / d));
+
+    let fundamental_arguments = [
+        // Mean elongation of the moon from the sun
+        cubic(297.85036, 445267.111480, -0.0019142, 189474.0),
+        // Mean anomaly of the sun
+        cubic(357.52772, 35999.050340, -0.0001603, -300000.0),
+        // Mean anomaly of the moon
+        cubic(134.96298, 477198.867398, 0.0086972, 56250.0),
+        // Moon's argument of latitude
+        cubic(93.27191, 483202.017538, -0.0036825, 327270.0),
+        // Longitude of the ascending node of the moon's mean orbit
+        cubic(125.04452, -1934.136261, 0.0020708, 450000.0),
+    ];
+
+    let (delta_psi, delta_epsilon) = NUTATION_ARGUMENTS
+        .iter()
+        .zip(NUTATION_COEFFICIENTS.iter())
+        .fold((0.0, 0.0), |(psi, eps), (multipliers, [a, b, c, d])| {
+            let argument: f64 = multipliers
+                .iter()
+                .zip(fundamental_arguments.iter())
+                .map(|(&m, x)| f64::from(m) * x)
+                .sum::<f64>()
+                .to_radians();
+            (
+                psi + (a + b * jce) * argument.sin(),
+                eps + (c + d * jce) * argument.cos(),
+            )
+        });
+
+    (delta_psi / 36_000_000.0, delta_epsilon / 36_000_000.0)
+}
+
+fn limit_degrees(degrees: f64) -> f64 {
+    degrees.rem_euclid(360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+
+    /// Inputs of the reference example in the SPA report (Table A5.1):
+    /// 2003-10-17 12:30:30 at UTC−7, Golden, Colorado
+    fn reference() -> (Position, UtcDateTime) {
+        (
+            Position::new(39.742476, -105.1786, 1830.14).unwrap(),
+            UtcDateTime::new(2003, Month::October, 17, 19, 30, 30).unwrap(),
+        )
+    }
+
+    const REFERENCE_DELTA_T: f64 = 67.0;
+
+    fn assert_close(name: &str, actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{name}: expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn matches_reference_intermediate_values() {
+        let (position, date_time) = reference();
+        let terms = SpaTerms::calculate(&position, &date_time, REFERENCE_DELTA_T);
+
+        // Reference output (Table A5.2)
+        assert_close("L", terms.heliocentric_longitude, 24.0182616917, 1e-8);
+        assert_close("B", terms.heliocentric_latitude, -0.0001011219, 1e-9);
+        assert_close("R", terms.radius_vector, 0.9965422974, 1e-9);
+        assert_close("Δψ", terms.nutation_longitude, -0.00399840, 1e-7);
+        assert_close("Δε", terms.nutation_obliquity, 0.00166657, 1e-7);
+        assert_close("ε", terms.obliquity, 23.440465, 1e-6);
+        assert_close("H", terms.hour_angle, 11.105900, 1e-5);
+        assert_close("δ", terms.declination, -9.314340, 1e-5);
+    }
+
+    #[test]
+    fn matches_reference_topocentric_position() {
+        let (position, date_time) = reference();
+        // Pressure and temperature of the reference example
+        let atmosphere = AtmosphericConditions::new(820.0, 11.0);
+        let sun = solar_position(&position, &date_time, REFERENCE_DELTA_T, &atmosphere);
+
+        // Topocentric zenith angle 50.11162° and azimuth 194.34024°
+        assert_close("zenith", 90.0 - sun.altitude, 50.11162, 1e-4);
+        assert_close("azimuth", sun.azimuth, 194.34024, 1e-4);
+    }
+
+    #[test]
+    fn delta_t_estimates_are_plausible() {
+        // Observed values: ~50.5 s (1980), ~63.8 s (2000), ~69.4 s (2020)
+        assert_close("ΔT 1980", estimate_delta_t(1980.0), 50.5, 4.0);
+        assert_close("ΔT 2000", estimate_delta_t(2000.0), 63.86, 0.01);
+        assert_close("ΔT 2020", estimate_delta_t(2020.0), 71.6, 3.0);
+
+        let mid_2024 = UtcDateTime::new(2024, Month::July, 2, 0, 0, 0).unwrap();
+        assert_close("year", decimal_year(&mid_2024), 2024.5, 0.01);
+    }
+}
@@ -0,0 +1,236 @@
+//! Renders cross-faded wallpapers between two neighbouring theme images
+//!
+//! Blends are written to a cache directory keyed by the source files and the
+//! weight, so each intermediate picture is rendered once and then reused.
+
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use image::{ImageFormat, Rgb, RgbImage, codecs::jpeg::JpegEncoder, imageops::FilterType};
+
+use crate::{DwallResult, domain::visual::wallpaper::BLEND_STEPS, lazy::DWALL_CACHE_DIR};
+
+/// Subdirectory of [`DWALL_CACHE_DIR`] holding rendered blends
+const BLEND_CACHE_DIRECTORY: &str = "blends";
+
+/// JPEG quality used for rendered blends
+const JPEG_QUALITY: u8 = 92;
+
+/// Rendered blends kept on disk; the least recently used are removed beyond this
+const MAX_CACHED_BLENDS: usize = 64;
+
+/// Directory where rendered blends are cached
+pub(crate) fn blend_cache_directory() -> PathBuf {
+    DWALL_CACHE_DIR.join(BLEND_CACHE_DIRECTORY)
+}
+
+/// Returns a file showing `from` cross-faded towards `to` by `weight / BLEND_STEPS`
+///
+/// The result is a JPEG when `from` is one and a PNG otherwise, with the dimensions
+/// of `from`; `to` is resized if its dimensions differ. A cached file is returned as
+/// is when the sources are unchanged.
+pub(crate) fn render_blend(
+    from: &Path,
+    to: &Path,
+    weight: u8,
+    cache_directory: &Path,
+) -> DwallResult<PathBuf> {
+    let is_jpeg = from
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_none_or(|extension| matches!(extension.to_ascii_lowercase().as_str(), "jpg" | "jpeg"));
+    let extension = if is_jpeg { "jpg" } else { "png" };
+    let output = cache_directory.join(format!("{:016x}.{extension}", cache_key(from, to, weight)?));
+
+    if output.is_file() {
+        debug!(path = %output.display(), "Reusing cached wallpaper blend");
+        // Mark as recently used so pruning keeps it
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&output)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            warn!(error = %e, path = %output.display(), "Failed to refresh cached blend");
+        }
+        return Ok(output);
+    }
+
+    fs::create_dir_all(cache_directory)?;
+
+    let from_image = image::open(from)?.to_rgb8();
+    let mut to_image = image::open(to)?.to_rgb8();
+    let (width, height) = from_image.dimensions();
+    if to_image.dimensions() != (width, height) {
+        to_image = image::imageops::resize(&to_image, width, height, FilterType::Triangle);
+    }
+
+    let t = f32::from(weight) / f32::from(BLEND_STEPS);
+    let blended = RgbImage::from_fn(width, height, |x, y| {
+        let a = from_image.get_pixel(x, y);
+        let b = to_image.get_pixel(x, y);
+        Rgb(std::array::from_fn(|c| {
+            (f32::from(a[c]) * (1.0 - t) + f32::from(b[c]) * t).round() as u8
+        }))
+    });
+
+    // Render under a temporary name so a crash never leaves a truncated cache entry
+    let temporary = output.with_extension(format!("{extension}.tmp"));
+    if is_jpeg {
+        let encoder =
+            JpegEncoder::new_with_quality(BufWriter::new(File::create(&temporary)?), JPEG_QUALITY);
+        blended.write_with_encoder(encoder)?;
+    } else {
+        blended.save_with_format(&temporary, ImageFormat::Png)?;
+    }
+    fs::rename(&temporary, &output)?;
+
+    info!(
+        from = %from.display(),
+        to = %to.display(),
+        weight = weight,
+        path = %output.display(),
+        "Rendered wallpaper blend"
+    );
+
+    prune_cache(cache_directory, MAX_CACHED_BLENDS);
+
+    Ok(output)
+}
+
+/// Identifies a blend by its sources, their size and modification time, and the weight
+fn cache_key(from: &Path, to: &Path, weight: u8) -> DwallResult<u64> {
+    let mut hasher = DefaultHasher::new();
+    for source in [from, to] {
+        let metadata = fs::metadata(source)?;
+        source.canonicalize()?.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        metadata.modified()?.hash(&mut hasher);
+    }
+    weight.hash(&mut hasher);
+    BLEND_STEPS.hash(&mut hasher);
+
+    Ok(hasher.finish())
+}
+
+/// Removes the least recently used blends beyond `max_entries`
+fn prune_cache(cache_directory: &Path, max_entries: usize) {
+    let Ok(entries) = fs::read_dir(cache_directory) else {
+        return;
+    };
+
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata
+                .is_file()
+                .then(|| Some((metadata.modified().ok()?, entry.path())))?
+        })
+        .collect();
+
+    if files.len() <= max_entries {
+        return;
+    }
+
+    files.sort();
+    let excess = files.len() - max_entries;
+    for (_, path) in files.into_iter().take(excess) {
+        if let Err(e) = fs::remove_file(&path) {
+            warn!(error = %e, path = %path.display(), "Failed to remove cached blend");
+        } else {
+            debug!(path = %path.display(), "Removed least recently used blend");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+
+    fn solid(path: &Path, width: u32, height: u32, color: [u8; 3]) {
+        RgbImage::from_pixel(width, height, Rgb(color))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn blends_png_and_reuses_cache() {
+        let directory = scratch_directory("blend-png");
+        let (red, blue) = (directory.join("1.png"), directory.join("2.png"));
+        solid(&red, 4, 2, [255, 0, 0]);
+        // Different size on purpose: the second image is resized to the first
+        solid(&blue, 8, 4, [0, 0, 255]);
+        let cache = directory.join("cache");
+
+        let output = render_blend(&red, &blue, BLEND_STEPS / 4, &cache).unwrap();
+        assert_eq!(output.extension().unwrap(), "png");
+        let blended = image::open(&output).unwrap().to_rgb8();
+        assert_eq!(blended.dimensions(), (4, 2));
+        assert_eq!(blended.get_pixel(1, 1), &Rgb([191, 0, 64]));
+
+        // A second request returns the cached file without rendering again
+        fs::write(&output, b"cached").unwrap();
+        assert_eq!(
+            render_blend(&red, &blue, BLEND_STEPS / 4, &cache).unwrap(),
+            output
+        );
+        assert_eq!(fs::read(&output).unwrap(), b"cached");
+
+        // Another weight is a different entry
+        assert_ne!(
+            render_blend(&red, &blue, BLEND_STEPS / 2, &cache).unwrap(),
+            output
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn blends_jpeg() {
+        let directory = scratch_directory("blend-jpeg");
+        let (black, white) = (directory.join("1.jpg"), directory.join("2.jpg"));
+        solid(&black, 16, 16, [0, 0, 0]);
+        solid(&white, 16, 16, [255, 255, 255]);
+
+        let output =
+            render_blend(&black, &white, BLEND_STEPS / 2, &directory.join("cache")).unwrap();
+        let blended = image::open(&output).unwrap().to_rgb8();
+        let value = blended.get_pixel(8, 8)[0];
+        assert!((120..=136).contains(&value), "got {value}");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn prunes_least_recently_used() {
+        let directory = scratch_directory("blend-prune");
+        for index in 0..5 {
+            let path = directory.join(format!("{index}.png"));
+            fs::write(&path, b"x").unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(index))
+                .unwrap();
+        }
+
+        prune_cache(&directory, 3);
+
+        let mut remaining: Vec<String> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, ["2.png", "3.png", "4.png"]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
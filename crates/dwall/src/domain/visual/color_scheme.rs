@@ -1,6 +1,6 @@
 use std::fmt;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use time::{Date, OffsetDateTime};
 use windows::Win32::{
     Foundation::{LPARAM, WPARAM},
@@ -9,8 +9,11 @@ use windows::Win32::{
 };
 
 use crate::{
-    Position, domain::time::solar_calculator::SolarPosition, error::DwallResult,
-    infrastructure::platform::windows::registry_client::RegistryKey, utils::string::WideStringExt,
+    Position,
+    domain::time::solar_calculator::{SolarEvents, SolarModel, SolarPosition},
+    error::DwallResult,
+    infrastructure::platform::windows::registry_client::RegistryKey,
+    utils::string::WideStringExt,
 };
 
 // ─────────────────────────────────────────────────────────────
@@ -83,7 +86,7 @@ const THRESHOLD_MAX: f64 = -4.5;
 // Color Scheme
 // ─────────────────────────────────────────────────────────────
 
-#[derive(Debug, PartialEq, Deserialize, Copy, Clone)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Copy, Clone)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum ColorScheme {
     Light,
@@ -91,6 +94,14 @@ pub enum ColorScheme {
 }
 
 impl ColorScheme {
+    /// Name as serialized, such as `LIGHT`
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ColorScheme::Light => "LIGHT",
+            ColorScheme::Dark => "DARK",
+        }
+    }
+
     #[inline]
     const fn as_u32(&self) -> u32 {
         match self {
@@ -214,19 +225,15 @@ impl ThresholdConfig {
 
 /// The extreme daylight state for a given day
 ///
-/// Determined by sampling [`SolarPosition::altitude()`] (which already includes
-/// refraction correction) at 24 whole hours of the day. This should be updated
-/// once a day around local sunrise and cached; do not recompute on the main
-/// call path.
+/// Determined from the day's real altitude extremes: the apparent altitude at
+/// solar noon (upper culmination) and at solar midnight (lower culmination), as
+/// solved by [`SolarEvents`]. Both values are taken from
+/// [`SolarPosition::altitude()`], so refraction is already included. This should
+/// be updated once a day and cached; do not recompute on the main call path.
 ///
-/// # Why sampling instead of an analytic formula
-///
-/// The analytic formula for the extreme (`90° - |φ - δ|`) requires accessing the
-/// declination from outside `SolarPosition`, breaking encapsulation. Moreover,
-/// the formula itself does not include refraction correction and would require
-/// manually adding a constant, but `altitude()` already returns the apparent
-/// altitude after refraction; the two cannot be mixed directly. The overhead
-/// of 24 samples per day is far outweighed by the correctness and simplicity gains.
+/// Sampling whole hours instead would miss the true extremes by up to half an
+/// hour of solar motion, which matters precisely in the borderline white-night
+/// cases this state exists to detect.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub enum DaylightState {
@@ -259,34 +266,44 @@ pub enum DaylightState {
 }
 
 impl DaylightState {
-    /// Detect the extreme state by sampling solar altitudes at 24 whole hours of the day
+    /// Detect the extreme state from the culmination altitudes of the day
     ///
     /// # Parameters
     /// - `position`: geographic coordinates of the observer
-    /// - `date`: the local date to evaluate (UTC)
+    /// - `date`: the local date to evaluate
+    /// - `model`: solar algorithm and atmospheric conditions at the observer
     /// - `config`: threshold configuration for comparison with the extremes
     ///
     /// # When to call
     /// Recommended to call once around local midnight each day and cache the result.
     /// Should not be triggered on every call to `determine_color_scheme_with_hysteresis`.
-    pub fn detect(position: &Position, date: Date, config: &ThresholdConfig) -> Self {
-        // Sample apparent altitudes for hours 0–23 (refraction already included;
-        // no extra constant needed)
-        let altitudes: Vec<f64> = (0u8..24)
-            .filter_map(|h| {
-                date.with_hms(h, 0, 0)
-                    .ok()
-                    .map(|dt| SolarPosition::new(position, &dt.assume_utc()).altitude())
-            })
-            .collect();
-
-        // If sampling fails, degrade to normal state (conservative)
-        if altitudes.is_empty() {
-            return DaylightState::Normal;
-        }
+    pub fn detect(
+        position: &Position,
+        date: Date,
+        model: &SolarModel,
+        config: &ThresholdConfig,
+    ) -> Self {
+        let events = match SolarEvents::calculate(position, date, model) {
+            Ok(events) => events,
+            Err(e) => {
+                // If solving fails, degrade to normal state (conservative)
+                warn!(error = %e, "Failed to calculate solar events, assuming normal day/night cycle");
+                return DaylightState::Normal;
+            }
+        };
+
+        info!(
+            date = ?date,
+            solar_noon = %events.solar_noon(),
+            sunrise = %events.sunrise(),
+            sunset = %events.sunset(),
+            civil_dawn = %events.civil_dawn(),
+            civil_dusk = %events.civil_dusk(),
+            "Calculated solar events for the day"
+        );
 
-        let min_alt = altitudes.iter().cloned().fold(f64::INFINITY, f64::min);
-        let max_alt = altitudes.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let min_alt = events.midnight_altitude();
+        let max_alt = events.noon_altitude();
 
         // Midnight sun: the minimum is still above the base threshold → sun never enters twilight
         if min_alt >= config.base_threshold {
@@ -448,6 +465,50 @@ pub(crate) fn determine_color_scheme_with_hysteresis(
     }
 }
 
+/// Inputs of the hysteresis decision that stay fixed within one cycle
+///
+/// Shared by the system color scheme and by light/dark appearance themes, so the
+/// wallpaper and the system appearance always flip at the same moment.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ColorSchemeContext {
+    current_scheme: ColorScheme,
+    config: ThresholdConfig,
+    daylight_state: DaylightState,
+}
+
+impl ColorSchemeContext {
+    pub(crate) fn new(
+        current_scheme: ColorScheme,
+        config: ThresholdConfig,
+        daylight_state: DaylightState,
+    ) -> Self {
+        Self {
+            current_scheme,
+            config,
+            daylight_state,
+        }
+    }
+
+    pub(crate) fn current_scheme(&self) -> ColorScheme {
+        self.current_scheme
+    }
+
+    /// Color scheme chosen by [`determine_color_scheme_with_hysteresis`] for this sun and clock
+    pub(crate) fn decide(
+        &self,
+        solar_position: &SolarPosition,
+        local_time: &OffsetDateTime,
+    ) -> ColorScheme {
+        determine_color_scheme_with_hysteresis(
+            solar_position,
+            &self.current_scheme,
+            &self.config,
+            local_time,
+            &self.daylight_state,
+        )
+    }
+}
+
 /// Color scheme manager for Windows system theme management
 pub(crate) struct ColorSchemeManager;
 
@@ -794,7 +855,7 @@ mod tests {
 
     fn solar_position(position: &Position, local: OffsetDateTime) -> SolarPosition {
         let utc = local.utc().unwrap();
-        SolarPosition::new(position, &utc)
+        SolarPosition::new(position, &utc, &SolarModel::default())
     }
 
     fn test_location(
@@ -820,7 +881,8 @@ mod tests {
             .unwrap();
             let position = Position::from_raw_position(lat, lon, 0.);
             let sun = solar_position(&position, time);
-            let daylight_state = DaylightState::detect(&position, time.date(), &config);
+            let daylight_state =
+                DaylightState::detect(&position, time.date(), &SolarModel::default(), &config);
             let actual = determine_color_scheme_with_hysteresis(
                 &sun,
                 &ColorScheme::Dark, // initial scheme set to Dark; does not affect core logic (except hysteresis, tests use fixed initial value)
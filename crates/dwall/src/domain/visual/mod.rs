@@ -1,7 +1,38 @@
+pub(crate) mod blend;
 pub mod color_scheme;
+pub mod photo_solar;
+pub(crate) mod scheduler;
+pub mod solar_path;
+pub(crate) mod theme_definition;
+pub mod theme_images;
+pub mod theme_import;
+pub mod theme_metadata;
+pub mod theme_pack;
 pub mod theme_processor;
+pub mod theme_validator;
+pub mod time_of_day;
 pub(crate) mod wallpaper;
 
 // Re-export commonly used types
 pub use color_scheme::{ColorScheme, DaylightState, ThresholdConfig};
-pub use theme_processor::{ThemeError, ThemeValidator, apply_solar_theme};
+pub use photo_solar::{
+    PhotoCapture, PhotoSolarError, PhotoSolarOptions, SolarPhoto, find_photos, solar_photos,
+    write_solar_theme,
+};
+pub use solar_path::{
+    SolarPathError, SolarPathFormat, SolarPathOptions, SolarPathRow, SolarPathTable,
+};
+pub use theme_definition::AppearancePair;
+pub use theme_images::{ThemeImages, available_image_formats, select_image_format};
+pub use theme_import::ThemeImportError;
+pub use theme_metadata::{CUSTOMIZED_THEME_METADATA_FILENAME, CustomizedThemeMetadata};
+pub use theme_pack::{
+    IntegrityIssue, IntegrityItem, IntegrityReport, ThemeManifest, ThemePackError,
+    ThemePackOptions, pack_theme, verify_theme,
+};
+pub use theme_processor::{ThemeError, apply_solar_theme};
+pub use theme_validator::{
+    ImageCheck, ImageDetails, Severity, ThemeValidator, ValidationIssue, ValidationItem,
+    ValidationReport,
+};
+pub use time_of_day::{TimeOfDay, TimeOfDayError, TimeSlot};
@@ -0,0 +1,597 @@
+//! Solar theme descriptions from photo capture metadata
+//!
+//! A photo's EXIF capture time and GPS position are enough to know where the sun
+//! was when it was taken, so a timelapse can become a solar theme without measuring
+//! any angle by hand. Photos are ordered by capture time, and their places in that
+//! order become the image indices of `solar.json`.
+
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use exif::{Exif, In, Reader, Tag, Value};
+use time::{Month, Offset, OffsetDateTime, UtcDateTime};
+
+use crate::{
+    DwallResult, Position,
+    config::ImageFormat,
+    domain::{
+        time::solar_calculator::{SolarAngle, SolarModel, SolarPosition},
+        visual::theme_definition::SOLAR_CONFIG_FILENAME,
+    },
+};
+
+/// Most images a theme can index
+pub const MAX_THEME_IMAGES: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PhotoSolarError {
+    #[error("No photos found in '{0}'")]
+    NoPhotos(String),
+    #[error("Photos in '{0}' mix image formats")]
+    MixedImageFormats(String),
+    #[error("Cannot read EXIF data of '{path}': {reason}")]
+    UnreadableExif { path: String, reason: String },
+    #[error("Photo '{0}' has no capture time (DateTimeOriginal)")]
+    MissingCaptureTime(String),
+    #[error("Photo '{path}' has an invalid capture time: {reason}")]
+    InvalidCaptureTime { path: String, reason: String },
+    #[error("Photo '{0}' has no GPS position and no location was given")]
+    MissingLocation(String),
+    #[error("Photo '{0}' has an invalid GPS position")]
+    InvalidGpsPosition(String),
+    #[error("Image limit must be between 1 and {MAX_THEME_IMAGES}, got {0}")]
+    InvalidImageLimit(usize),
+}
+
+/// How capture metadata is completed and how many photos are kept
+#[derive(Debug, Clone)]
+pub struct PhotoSolarOptions {
+    location: Option<Position>,
+    offset: Offset,
+    max_images: usize,
+}
+
+impl PhotoSolarOptions {
+    /// `offset` is the UTC offset of capture times for photos that do not record one
+    pub fn new(offset: Offset) -> Self {
+        Self {
+            location: None,
+            offset,
+            max_images: MAX_THEME_IMAGES,
+        }
+    }
+
+    /// Observer position for photos without a GPS position
+    pub fn with_location(mut self, location: Position) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Number of photos to keep at most, see [`solar_photos`]
+    pub fn with_max_images(mut self, max_images: usize) -> Self {
+        self.max_images = max_images;
+        self
+    }
+}
+
+/// Capture metadata read from a photo's EXIF data
+#[derive(Debug, Clone)]
+pub struct PhotoCapture {
+    path: PathBuf,
+    captured_at: UtcDateTime,
+    /// Sub-second part of the capture time, ordering frames shot within one second
+    nanosecond: u32,
+    /// Position recorded by the camera, if any
+    position: Option<Position>,
+}
+
+impl PhotoCapture {
+    /// Reads `DateTimeOriginal` with its sub-seconds and offset, and the GPS position
+    ///
+    /// `fallback_offset` applies when the photo has no `OffsetTimeOriginal`.
+    pub fn read(path: &Path, fallback_offset: Offset) -> Result<Self, PhotoSolarError> {
+        let display_path = || path.display().to_string();
+        let unreadable = |reason: String| PhotoSolarError::UnreadableExif {
+            path: display_path(),
+            reason,
+        };
+        let invalid_time = |reason: String| PhotoSolarError::InvalidCaptureTime {
+            path: display_path(),
+            reason,
+        };
+
+        let file = File::open(path).map_err(|e| unreadable(e.to_string()))?;
+        let exif = Reader::new()
+            .read_from_container(&mut BufReader::new(file))
+            .map_err(|e| unreadable(e.to_string()))?;
+
+        let date_time = ascii_field(&exif, Tag::DateTimeOriginal)
+            .ok_or_else(|| PhotoSolarError::MissingCaptureTime(display_path()))?;
+        let mut date_time =
+            exif::DateTime::from_ascii(date_time).map_err(|e| invalid_time(e.to_string()))?;
+        // Sub-seconds only refine the order of frames, a malformed value is ignored
+        if let Some(subsec) = ascii_field(&exif, Tag::SubSecTimeOriginal) {
+            let _ = date_time.parse_subsec(subsec);
+        }
+        if let Some(offset) = ascii_field(&exif, Tag::OffsetTimeOriginal) {
+            date_time
+                .parse_offset(offset)
+                .map_err(|e| invalid_time(e.to_string()))?;
+        }
+
+        let offset = match date_time.offset {
+            Some(minutes) => Offset::from_seconds(i32::from(minutes) * 60)
+                .map_err(|e| invalid_time(e.to_string()))?,
+            None => fallback_offset,
+        };
+        let month = Month::try_from(date_time.month).map_err(|e| invalid_time(e.to_string()))?;
+        let captured_at = OffsetDateTime::new(
+            date_time.year,
+            month,
+            date_time.day,
+            date_time.hour,
+            date_time.minute,
+            date_time.second,
+            offset,
+        )
+        .and_then(|local| local.utc())
+        .map_err(|e| invalid_time(e.to_string()))?;
+
+        let position = match gps_coordinates(&exif) {
+            Some((latitude, longitude, altitude)) => Some(
+                Position::new(latitude, longitude, altitude)
+                    .map_err(|_| PhotoSolarError::InvalidGpsPosition(display_path()))?,
+            ),
+            None => None,
+        };
+
+        trace!(
+            path = %path.display(),
+            captured_at = %captured_at,
+            offset = %offset,
+            has_gps_position = position.is_some(),
+            "Read photo capture metadata"
+        );
+        Ok(Self {
+            path: path.to_path_buf(),
+            captured_at,
+            nanosecond: date_time.nanosecond.unwrap_or_default(),
+            position,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn captured_at(&self) -> UtcDateTime {
+        self.captured_at
+    }
+
+    pub fn position(&self) -> Option<&Position> {
+        self.position.as_ref()
+    }
+}
+
+/// A photo with the sun position at its capture time
+#[derive(Debug, Clone)]
+pub struct SolarPhoto {
+    capture: PhotoCapture,
+    angle: SolarAngle,
+}
+
+impl SolarPhoto {
+    pub fn capture(&self) -> &PhotoCapture {
+        &self.capture
+    }
+
+    /// Sun position, keyed to the photo's index in capture order
+    pub fn angle(&self) -> &SolarAngle {
+        &self.angle
+    }
+}
+
+/// Lists the photos in `directory` and the theme image format they share
+///
+/// Files in every theme image format are considered except JPEG XL, whose container
+/// the EXIF reader cannot read the capture metadata from.
+pub fn find_photos(directory: &Path) -> DwallResult<(Vec<PathBuf>, ImageFormat)> {
+    let mut photos = Vec::new();
+    let mut image_format = None;
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let Some(format) = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension)
+            .filter(|format| *format != ImageFormat::Jxl)
+        else {
+            continue;
+        };
+        if !path.is_file() {
+            continue;
+        }
+
+        match &image_format {
+            None => image_format = Some(format),
+            Some(expected) if *expected == format => {}
+            Some(_) => {
+                return Err(
+                    PhotoSolarError::MixedImageFormats(directory.display().to_string()).into(),
+                );
+            }
+        }
+        photos.push(path);
+    }
+
+    let image_format =
+        image_format.ok_or_else(|| PhotoSolarError::NoPhotos(directory.display().to_string()))?;
+    photos.sort();
+    Ok((photos, image_format))
+}
+
+/// Reads every photo and computes the sun position at its capture time
+///
+/// Photos are sorted by capture time and indexed in that order. Beyond the image
+/// limit of the options, photos are picked at even spacing through the sequence,
+/// always keeping the first and the last one. `solar_model` resolves the solar
+/// model at each photo's position.
+pub fn solar_photos(
+    photos: &[PathBuf],
+    options: &PhotoSolarOptions,
+    solar_model: impl Fn(&Position) -> SolarModel,
+) -> Result<Vec<SolarPhoto>, PhotoSolarError> {
+    if !(1..=MAX_THEME_IMAGES).contains(&options.max_images) {
+        return Err(PhotoSolarError::InvalidImageLimit(options.max_images));
+    }
+
+    let mut captures = photos
+        .iter()
+        .map(|path| PhotoCapture::read(path, options.offset))
+        .collect::<Result<Vec<_>, _>>()?;
+    if captures.is_empty() {
+        return Err(PhotoSolarError::NoPhotos(String::new()));
+    }
+    captures.sort_by(|a, b| {
+        (a.captured_at, a.nanosecond, &a.path).cmp(&(b.captured_at, b.nanosecond, &b.path))
+    });
+    let captures = pick_evenly(captures, options.max_images);
+
+    let solar_photos = captures
+        .into_iter()
+        .enumerate()
+        .map(|(index, capture)| {
+            let position = capture.position.or(options.location).ok_or_else(|| {
+                PhotoSolarError::MissingLocation(capture.path.display().to_string())
+            })?;
+            let sun = SolarPosition::new(&position, &capture.captured_at, &solar_model(&position));
+            let angle = SolarAngle::new(
+                index as u8,
+                round_degrees(sun.altitude()),
+                round_degrees(sun.azimuth()),
+            );
+
+            Ok(SolarPhoto { capture, angle })
+        })
+        .collect::<Result<Vec<_>, PhotoSolarError>>()?;
+
+    info!(
+        photos = photos.len(),
+        images = solar_photos.len(),
+        "Computed sun positions from photo capture metadata"
+    );
+    Ok(solar_photos)
+}
+
+/// Writes `photos` as a solar theme in `theme_directory`, laid out like a customized theme
+///
+/// Each photo is copied to `images/<index + 1>.<format>` and the sun positions are
+/// written to `solar.json`.
+pub fn write_solar_theme(
+    photos: &[SolarPhoto],
+    image_format: &ImageFormat,
+    theme_directory: &Path,
+) -> DwallResult<()> {
+    let images_directory = theme_directory.join("images");
+    fs::create_dir_all(&images_directory)?;
+
+    for photo in photos {
+        let file_name = format!(
+            "{}.{}",
+            u16::from(photo.angle.index()) + 1,
+            image_format.as_str()
+        );
+        fs::copy(&photo.capture.path, images_directory.join(file_name))?;
+    }
+
+    let angles: Vec<&SolarAngle> = photos.iter().map(|photo| &photo.angle).collect();
+    fs::write(
+        theme_directory.join(SOLAR_CONFIG_FILENAME),
+        serde_json::to_string_pretty(&angles)?,
+    )?;
+
+    info!(
+        theme_directory = %theme_directory.display(),
+        images = photos.len(),
+        "Wrote solar theme from photos"
+    );
+    Ok(())
+}
+
+/// Keeps `limit` items at even spacing, including the first and the last
+fn pick_evenly<T>(items: Vec<T>, limit: usize) -> Vec<T> {
+    let count = items.len();
+    if count <= limit {
+        return items;
+    }
+    if limit == 1 {
+        return items.into_iter().take(1).collect();
+    }
+
+    // Rounding down keeps positions distinct, as the step exceeds one
+    let mut positions = (0..limit).map(|i| i * (count - 1) / (limit - 1)).peekable();
+    items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, item)| (positions.next_if_eq(&position).is_some()).then_some(item))
+        .collect()
+}
+
+fn round_degrees(degrees: f64) -> f64 {
+    (degrees * 100.0).round() / 100.0
+}
+
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<&[u8]> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values.first().map(Vec::as_slice),
+        _ => None,
+    }
+}
+
+/// Latitude, longitude and altitude from the GPS tags, in degrees and meters
+///
+/// A missing altitude counts as sea level.
+fn gps_coordinates(exif: &Exif) -> Option<(f64, f64, f64)> {
+    let degrees = |tag: Tag, reference_tag: Tag, negative_reference: u8| {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let [degrees, minutes, seconds] = parts.get(..3)? else {
+            return None;
+        };
+        let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+        let reference = ascii_field(exif, reference_tag)?.first()?;
+
+        Some(if reference.eq_ignore_ascii_case(&negative_reference) {
+            -value
+        } else {
+            value
+        })
+    };
+
+    let latitude = degrees(Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let longitude = degrees(Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    let altitude = match &exif
+        .get_field(Tag::GPSAltitude, In::PRIMARY)
+        .map(|f| &f.value)
+    {
+        Some(Value::Rational(altitude)) if !altitude.is_empty() => {
+            let below_sea_level = exif
+                .get_field(Tag::GPSAltitudeRef, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+                == Some(1);
+            let altitude = altitude[0].to_f64();
+            if below_sea_level { -altitude } else { altitude }
+        }
+        _ => 0.0,
+    };
+
+    Some((latitude, longitude, altitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use exif::{Field, Rational, experimental::Writer};
+    use image::RgbImage;
+
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+    use crate::{domain::visual::theme_definition::ThemeDefinition, error::DwallError};
+
+    fn ascii(tag: Tag, value: &str) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+        }
+    }
+
+    fn rationals(tag: Tag, values: &[(u32, u32)]) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(
+                values
+                    .iter()
+                    .map(|&(num, denom)| Rational { num, denom })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Writes a small JPEG whose APP1 segment holds `fields`
+    fn write_photo(path: &Path, fields: &[Field]) {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        let mut jpeg = Vec::new();
+        RgbImage::new(2, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(&tiff);
+        let length = u16::try_from(segment.len() + 2).unwrap();
+        let mut photo = jpeg[..2].to_vec();
+        photo.extend_from_slice(&[0xFF, 0xE1]);
+        photo.extend_from_slice(&length.to_be_bytes());
+        photo.extend_from_slice(&segment);
+        photo.extend_from_slice(&jpeg[2..]);
+        fs::write(path, photo).unwrap();
+    }
+
+    fn model(_: &Position) -> SolarModel {
+        SolarModel::default()
+    }
+
+    #[test]
+    fn reads_capture_time_offset_and_gps_position() {
+        let directory = scratch_directory("photos-capture");
+        let path = directory.join("frame.jpg");
+        write_photo(
+            &path,
+            &[
+                ascii(Tag::DateTimeOriginal, "2024:06:21 14:30:00"),
+                ascii(Tag::OffsetTimeOriginal, "+02:00"),
+                ascii(Tag::SubSecTimeOriginal, "25"),
+                ascii(Tag::GPSLatitudeRef, "S"),
+                rationals(Tag::GPSLatitude, &[(33, 1), (51, 1), (3_600, 100)]),
+                ascii(Tag::GPSLongitudeRef, "E"),
+                rationals(Tag::GPSLongitude, &[(151, 1), (12, 1), (0, 1)]),
+                rationals(Tag::GPSAltitude, &[(58, 1)]),
+            ],
+        );
+
+        let offset = Offset::from_seconds(-5 * 3600).unwrap();
+        let capture = PhotoCapture::read(&path, offset).unwrap();
+        assert_eq!(
+            capture.captured_at(),
+            UtcDateTime::new(2024, Month::June, 21, 12, 30, 0).unwrap()
+        );
+        assert_eq!(capture.nanosecond, 250_000_000);
+        let position = capture.position().unwrap();
+        assert!((position.latitude() - -33.86).abs() < 1e-9);
+        assert!((position.longitude() - 151.2).abs() < 1e-9);
+        assert_eq!(position.altitude(), 58.0);
+
+        // Without an offset tag the fallback offset applies
+        write_photo(
+            &path,
+            &[ascii(Tag::DateTimeOriginal, "2024:06:21 14:30:00")],
+        );
+        let capture = PhotoCapture::read(&path, offset).unwrap();
+        assert_eq!(
+            capture.captured_at(),
+            UtcDateTime::new(2024, Month::June, 21, 19, 30, 0).unwrap()
+        );
+        assert!(capture.position().is_none());
+
+        write_photo(&path, &[ascii(Tag::Make, "Camera")]);
+        assert!(matches!(
+            PhotoCapture::read(&path, offset),
+            Err(PhotoSolarError::MissingCaptureTime(_))
+        ));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn indexes_photos_by_capture_time() {
+        let directory = scratch_directory("photos-theme");
+        let photos_directory = directory.join("photos");
+        fs::create_dir_all(&photos_directory).unwrap();
+        // File names deliberately disagree with the capture order
+        for (file_name, time) in [
+            ("a.jpg", "2024:03:20 18:00:00"),
+            ("b.jpg", "2024:03:20 06:00:00"),
+            ("c.JPG", "2024:03:20 12:00:00"),
+        ] {
+            write_photo(
+                &photos_directory.join(file_name),
+                &[
+                    ascii(Tag::DateTimeOriginal, time),
+                    ascii(Tag::OffsetTimeOriginal, "+00:00"),
+                ],
+            );
+        }
+        fs::write(photos_directory.join("notes.txt"), b"").unwrap();
+        fs::write(photos_directory.join("d.jxl"), b"").unwrap();
+
+        let (photos, image_format) = find_photos(&photos_directory).unwrap();
+        assert_eq!(photos.len(), 3);
+        assert_eq!(image_format, ImageFormat::Jpeg);
+
+        let options = PhotoSolarOptions::new(Offset::from_seconds(0).unwrap());
+        assert!(matches!(
+            solar_photos(&photos, &options, model),
+            Err(PhotoSolarError::MissingLocation(_))
+        ));
+
+        let options = options.with_location(Position::new(0.0, 0.0, 0.0).unwrap());
+        let solar = solar_photos(&photos, &options, model).unwrap();
+        let order: Vec<(u8, &str)> = solar
+            .iter()
+            .map(|photo| {
+                let file_name = photo.capture().path().file_name().unwrap();
+                (photo.angle().index(), file_name.to_str().unwrap())
+            })
+            .collect();
+        assert_eq!(order, [(0, "b.jpg"), (1, "c.JPG"), (2, "a.jpg")]);
+        // Near the equinox on the equator the sun rises in the east and culminates high
+        assert!(solar[0].angle().altitude().abs() < 5.0);
+        assert!((solar[0].angle().azimuth() - 90.0).abs() < 5.0);
+        assert!(solar[1].angle().altitude() > 80.0);
+
+        let theme_directory = directory.join("theme");
+        write_solar_theme(&solar, &image_format, &theme_directory).unwrap();
+        assert!(theme_directory.join("images/3.jpg").is_file());
+        let definition = ThemeDefinition::load(&theme_directory).unwrap();
+        assert_eq!(definition.image_indices(), [0, 1, 2]);
+
+        let options = options.with_max_images(2);
+        let solar = solar_photos(&photos, &options, model).unwrap();
+        assert_eq!(solar.len(), 2);
+        assert_eq!(solar[1].capture().path(), photos_directory.join("a.jpg"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn picks_evenly_spaced_items() {
+        assert_eq!(pick_evenly((0..10).collect(), 4), [0, 3, 6, 9]);
+        assert_eq!(pick_evenly((0..3).collect(), 5), [0, 1, 2]);
+        assert_eq!(pick_evenly((0..3).collect(), 1), [0]);
+    }
+
+    #[test]
+    fn finds_photos_in_other_theme_formats() {
+        let directory = scratch_directory("photo-solar-formats");
+        fs::write(directory.join("a.webp"), b"").unwrap();
+        fs::write(directory.join("b.WEBP"), b"").unwrap();
+
+        let (photos, image_format) = find_photos(&directory).unwrap();
+        assert_eq!(photos.len(), 2);
+        assert_eq!(image_format, ImageFormat::Webp);
+
+        fs::write(directory.join("c.avif"), b"").unwrap();
+        assert!(matches!(
+            find_photos(&directory),
+            Err(DwallError::PhotoSolar(PhotoSolarError::MixedImageFormats(
+                _
+            )))
+        ));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
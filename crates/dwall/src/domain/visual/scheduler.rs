@@ -0,0 +1,365 @@
+//! Predicts when the next visible change is due so the daemon can sleep until then
+//!
+//! Wallpaper selection and the color-scheme decision are both step functions of
+//! time: they only change when the sun crosses a boundary between two images or a
+//! hysteresis switching point. Instead of re-running the whole cycle on a fixed
+//! tick, the scheduler scans forward from now in coarse steps, bisects the first
+//! step whose outcome differs, and reports that instant.
+
+use std::{thread::sleep, time::Duration};
+
+use time::{Offset, OffsetDateTime, UtcDateTime};
+
+use crate::{
+    Position,
+    domain::{
+        time::{
+            lunar_calculator::LunarPosition,
+            solar_calculator::{SolarModel, SolarPosition},
+        },
+        visual::{
+            color_scheme::ColorSchemeContext,
+            theme_definition::{SelectionMoment, ThemeDefinition},
+            wallpaper::{SelectionPolicy, SkyPosition},
+        },
+    },
+};
+
+/// Coarse scan step (seconds)
+///
+/// The sun moves about 0.25° per minute, far less than the spacing between the
+/// images of any real theme, so a change cannot start and end inside one step.
+const SCAN_STEP_SECONDS: u64 = 60;
+
+/// Shortest delay ever returned, so a prediction that lands in the past cannot
+/// turn the caller into a busy loop
+const MIN_WAKE_DELAY: Duration = Duration::from_secs(1);
+
+/// Margin added after a predicted change so the next cycle observes the new state
+const WAKE_MARGIN: Duration = Duration::from_secs(1);
+
+/// How often a sleeping caller is woken to check for configuration or monitor changes
+pub(crate) const WATCH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Finds the next instant at which the selected wallpaper or color scheme changes
+pub(crate) struct ChangeScheduler<'a> {
+    position: &'a Position,
+    model: SolarModel,
+    from: UtcDateTime,
+    /// Longest time the caller is allowed to sleep; no change is searched beyond it
+    max_wake_interval: Duration,
+}
+
+impl<'a> ChangeScheduler<'a> {
+    pub(crate) fn new(
+        position: &'a Position,
+        model: SolarModel,
+        from: UtcDateTime,
+        max_wake_interval: Duration,
+    ) -> Self {
+        Self {
+            position,
+            model,
+            from,
+            max_wake_interval,
+        }
+    }
+
+    /// Next instant at which [`ThemeDefinition::select`] picks a different image or blend
+    ///
+    /// `offset` is the UTC offset of the local clock read by time-of-day themes;
+    /// appearance themes follow the decision of `color_scheme`.
+    pub(crate) fn next_wallpaper_change(
+        &self,
+        theme_definition: &ThemeDefinition,
+        policy: &SelectionPolicy,
+        color_scheme: &ColorSchemeContext,
+        offset: Offset,
+    ) -> Option<UtcDateTime> {
+        // The moon is only worth computing for themes that can select on it
+        let track_moon = theme_definition.tracks_moon();
+        self.first_change(|instant| {
+            let local_time = OffsetDateTime::from_utc(*instant, offset).ok()?;
+            let sky = self.sky_at(instant, track_moon);
+            let scheme = color_scheme.decide(sky.sun(), &local_time);
+            theme_definition.select(&SelectionMoment::new(sky, local_time, scheme), policy)
+        })
+    }
+
+    /// Next instant at which the hysteresis decision departs from its outcome at `from`
+    pub(crate) fn next_color_scheme_change(
+        &self,
+        color_scheme: &ColorSchemeContext,
+        offset: Offset,
+    ) -> Option<UtcDateTime> {
+        self.first_change(|instant| {
+            let sun = SolarPosition::new(self.position, instant, &self.model);
+            // The polar night fallback reads the local clock, so evaluate it at `instant` too
+            let local_time = OffsetDateTime::from_utc(*instant, offset).ok()?;
+            Some(color_scheme.decide(&sun, &local_time))
+        })
+    }
+
+    fn sky_at(&self, instant: &UtcDateTime, track_moon: bool) -> SkyPosition {
+        SkyPosition::new(
+            SolarPosition::new(self.position, instant, &self.model),
+            track_moon.then(|| LunarPosition::new(self.position, instant, self.model.atmosphere())),
+        )
+    }
+
+    /// How long to sleep until `next_change`, capped by the maximum wake interval
+    pub(crate) fn delay_until(&self, next_change: Option<UtcDateTime>) -> Duration {
+        let Some(next_change) = next_change else {
+            return self.max_wake_interval;
+        };
+
+        let delay = next_change
+            .duration_since(&self.from)
+            .unwrap_or_default()
+            .saturating_add(WAKE_MARGIN);
+
+        delay.clamp(MIN_WAKE_DELAY, self.max_wake_interval.max(MIN_WAKE_DELAY))
+    }
+
+    /// Returns the first instant after `from` at which `outcome` differs from its value at `from`
+    ///
+    /// Scans in [`SCAN_STEP_SECONDS`] steps up to the maximum wake interval, then
+    /// bisects the step containing the change down to one second.
+    fn first_change<T, F>(&self, outcome: F) -> Option<UtcDateTime>
+    where
+        T: PartialEq,
+        F: Fn(&UtcDateTime) -> T,
+    {
+        let baseline = outcome(&self.from);
+        let horizon = self.max_wake_interval.as_secs();
+
+        // Offsets (seconds from `from`) bracketing the change: `same` keeps the
+        // baseline outcome, `changed` does not
+        let mut same = 0;
+        let mut changed = None;
+        let mut offset = 0;
+        while offset < horizon {
+            offset = (offset + SCAN_STEP_SECONDS).min(horizon);
+            if outcome(&self.from.add_seconds(offset).ok()?) != baseline {
+                changed = Some(offset);
+                break;
+            }
+            same = offset;
+        }
+
+        let mut changed = changed?;
+        while changed - same > 1 {
+            let middle = same + (changed - same) / 2;
+            if outcome(&self.from.add_seconds(middle).ok()?) == baseline {
+                same = middle;
+            } else {
+                changed = middle;
+            }
+        }
+
+        self.from.add_seconds(changed).ok()
+    }
+}
+
+/// Sleeps until `deadline`, waking every [`WATCH_INTERVAL`] to poll `should_interrupt`
+///
+/// The deadline is compared against the wall clock on each wake, so time spent in
+/// system sleep or hibernation counts towards it.
+///
+/// Returns `true` if the wait was cut short by `should_interrupt`.
+pub(crate) fn sleep_until(
+    deadline: UtcDateTime,
+    mut should_interrupt: impl FnMut() -> bool,
+) -> bool {
+    loop {
+        let remaining = match deadline.duration_since(&UtcDateTime::now()) {
+            Ok(remaining) if !remaining.is_zero() => remaining,
+            _ => return false,
+        };
+
+        sleep(remaining.min(WATCH_INTERVAL));
+
+        if should_interrupt() {
+            return true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+    use crate::domain::{
+        time::solar_calculator::SolarAngle,
+        visual::{
+            ColorScheme, DaylightState, ThresholdConfig, theme_definition::AppearancePair,
+            time_of_day::TimeSlot, wallpaper::WallpaperSelector,
+        },
+    };
+
+    fn solar_angles(json: &str) -> Vec<SolarAngle> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn noon_in_london() -> UtcDateTime {
+        UtcDateTime::new(2024, Month::June, 21, 12, 0, 0).unwrap()
+    }
+
+    fn light_color_scheme(position: &Position) -> ColorSchemeContext {
+        ColorSchemeContext::new(
+            ColorScheme::Light,
+            ThresholdConfig::from_position(position),
+            DaylightState::Normal,
+        )
+    }
+
+    #[test]
+    fn wallpaper_change_matches_selection_boundary() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        // Two images split around the meridian: morning (east) and afternoon (west)
+        let angles = solar_angles(
+            r#"[
+                {"index": 0, "altitude": 60.0, "azimuth": 150.0},
+                {"index": 1, "altitude": 60.0, "azimuth": 210.0}
+            ]"#,
+        );
+        let from = noon_in_london().sub_hours(1).unwrap();
+        let scheduler = ChangeScheduler::new(
+            &london,
+            SolarModel::default(),
+            from,
+            Duration::from_secs(3 * 3600),
+        );
+
+        let change = scheduler
+            .next_wallpaper_change(
+                &ThemeDefinition::Solar(angles.clone()),
+                &SelectionPolicy::default(),
+                &light_color_scheme(&london),
+                "+00:00".parse().unwrap(),
+            )
+            .unwrap();
+        let before = SolarPosition::new(
+            &london,
+            &change.sub_seconds(1).unwrap(),
+            &SolarModel::default(),
+        );
+        let after = SolarPosition::new(&london, &change, &SolarModel::default());
+
+        let pick = |sun: &SolarPosition| {
+            WallpaperSelector::select_image(&angles, &SkyPosition::new(*sun, None))
+        };
+        assert_eq!(pick(&before), Some(0));
+        assert_eq!(pick(&after), Some(1));
+        // The sun crosses 180° azimuth shortly after 12:00 UTC in London
+        assert!(change.diff_seconds(&noon_in_london()).abs() < 15 * 60);
+    }
+
+    #[test]
+    fn no_change_within_horizon() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let angles = solar_angles(r#"[{"index": 0, "altitude": 0.0, "azimuth": 0.0}]"#);
+        let scheduler = ChangeScheduler::new(
+            &london,
+            SolarModel::default(),
+            noon_in_london(),
+            Duration::from_secs(600),
+        );
+
+        assert_eq!(
+            scheduler.next_wallpaper_change(
+                &ThemeDefinition::Solar(angles),
+                &SelectionPolicy::default(),
+                &light_color_scheme(&london),
+                "+00:00".parse().unwrap()
+            ),
+            None
+        );
+        assert_eq!(scheduler.delay_until(None), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn time_of_day_change_follows_local_clock() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let slots: Vec<TimeSlot> = serde_json::from_str(
+            r#"[{"index": 0, "time": "07:00"}, {"index": 1, "time": "14:30"}]"#,
+        )
+        .unwrap();
+        let scheduler = ChangeScheduler::new(
+            &london,
+            SolarModel::default(),
+            noon_in_london(),
+            Duration::from_secs(3 * 3600),
+        );
+
+        // 12:00 UTC is 13:00 at +01:00, so 14:30 local is 90 minutes away
+        let change = scheduler
+            .next_wallpaper_change(
+                &ThemeDefinition::TimeOfDay(slots),
+                &SelectionPolicy::default(),
+                &light_color_scheme(&london),
+                "+01:00".parse().unwrap(),
+            )
+            .unwrap();
+        assert_eq!(change, noon_in_london().add_seconds(90 * 60).unwrap());
+    }
+
+    #[test]
+    fn color_scheme_change_at_dusk() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let from = UtcDateTime::new(2024, Month::June, 21, 19, 0, 0).unwrap();
+        let scheduler = ChangeScheduler::new(
+            &london,
+            SolarModel::default(),
+            from,
+            Duration::from_secs(3 * 3600),
+        );
+        let color_scheme = light_color_scheme(&london);
+        let offset = "+00:00".parse().unwrap();
+
+        let change = scheduler
+            .next_color_scheme_change(&color_scheme, offset)
+            .unwrap();
+
+        // An appearance theme flips at the very same instant
+        let pair = ThemeDefinition::Appearance(AppearancePair::new(0, 1));
+        assert_eq!(
+            scheduler.next_wallpaper_change(
+                &pair,
+                &SelectionPolicy::default(),
+                &color_scheme,
+                offset
+            ),
+            Some(change)
+        );
+
+        // Sunset is around 20:21 UTC; the dark switch point lies below the horizon
+        let sunset = UtcDateTime::new(2024, Month::June, 21, 20, 21, 0).unwrap();
+        assert!(change > sunset);
+        let sun = SolarPosition::new(&london, &change, &SolarModel::default());
+        assert!(sun.altitude() < 0.0);
+    }
+
+    #[test]
+    fn delay_is_clamped() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let from = noon_in_london();
+        let scheduler = ChangeScheduler::new(
+            &london,
+            SolarModel::default(),
+            from,
+            Duration::from_secs(600),
+        );
+
+        assert_eq!(
+            scheduler.delay_until(Some(from.add_seconds(120).unwrap())),
+            Duration::from_secs(121)
+        );
+        assert_eq!(
+            scheduler.delay_until(Some(from.add_seconds(7200).unwrap())),
+            Duration::from_secs(600)
+        );
+        assert_eq!(scheduler.delay_until(Some(from)), Duration::from_secs(1));
+    }
+}
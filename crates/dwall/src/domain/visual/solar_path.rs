@@ -0,0 +1,385 @@
+//! Tabulates how a theme behaves at a place over a range of days
+//!
+//! Each row samples the sun at one instant and records the image and color
+//! scheme the daemon would pick, so a theme can be checked for any city without
+//! waiting for the day to pass.
+
+use std::{io::Write, path::Path, str::FromStr, time::Duration};
+
+use serde::Serialize;
+use time::{Date, Offset, OffsetDateTime, UtcDateTime};
+
+use crate::{
+    DwallResult, Position,
+    domain::{
+        time::{
+            lunar_calculator::LunarPosition,
+            solar_calculator::{SolarModel, SolarPosition},
+        },
+        visual::{
+            ColorScheme, DaylightState, ThresholdConfig,
+            color_scheme::determine_color_scheme_with_hysteresis,
+            theme_definition::{SelectionMoment, ThemeDefinition},
+            theme_processor::load_cached_theme_definition,
+            wallpaper::{SelectionPolicy, SkyPosition},
+        },
+    },
+};
+
+const CSV_HEADER: &str = "time,altitude,azimuth,image_index,color_scheme";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SolarPathError {
+    #[error("Step must be at least one second")]
+    InvalidStep,
+    #[error("End date {to} is before start date {from}")]
+    InvalidDateRange { from: Date, to: Date },
+    #[error("Unknown output format '{0}', expected 'csv' or 'json'")]
+    UnknownFormat(String),
+}
+
+/// Output format of a [`SolarPathTable`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolarPathFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl FromStr for SolarPathFormat {
+    type Err = SolarPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(SolarPathError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// Where, when and how densely to sample the sun
+#[derive(Debug, Clone, Copy)]
+pub struct SolarPathOptions {
+    position: Position,
+    model: SolarModel,
+    /// First local date, inclusive
+    from: Date,
+    /// Last local date, inclusive
+    to: Date,
+    step: Duration,
+    /// UTC offset used for the local dates and the reported times
+    offset: Offset,
+}
+
+impl SolarPathOptions {
+    pub fn new(
+        position: Position,
+        model: SolarModel,
+        from: Date,
+        to: Date,
+        step: Duration,
+        offset: Offset,
+    ) -> Result<Self, SolarPathError> {
+        if step.as_secs() == 0 {
+            return Err(SolarPathError::InvalidStep);
+        }
+        if to < from {
+            return Err(SolarPathError::InvalidDateRange { from, to });
+        }
+
+        Ok(Self {
+            position,
+            model,
+            from,
+            to,
+            step,
+            offset,
+        })
+    }
+
+    /// First sampled instant: local midnight at the start of `from`
+    fn start(&self) -> DwallResult<UtcDateTime> {
+        Ok(local_midnight(self.from, self.offset)?.utc()?)
+    }
+
+    /// End of the sampled range: local midnight after `to`, exclusive
+    fn end(&self) -> DwallResult<UtcDateTime> {
+        Ok(local_midnight(self.to, self.offset)?.add_days(1)?.utc()?)
+    }
+}
+
+fn local_midnight(date: Date, offset: Offset) -> Result<OffsetDateTime, time::Error> {
+    OffsetDateTime::new(date.year(), date.month(), date.day(), 0, 0, 0, offset)
+}
+
+/// One sampled instant
+#[derive(Debug, Clone, Serialize)]
+pub struct SolarPathRow {
+    /// Local time in RFC 3339
+    time: String,
+    altitude: f64,
+    azimuth: f64,
+    image_index: Option<u8>,
+    color_scheme: ColorScheme,
+}
+
+impl SolarPathRow {
+    pub fn time(&self) -> &str {
+        &self.time
+    }
+
+    pub fn altitude(&self) -> f64 {
+        self.altitude
+    }
+
+    pub fn azimuth(&self) -> f64 {
+        self.azimuth
+    }
+
+    pub fn image_index(&self) -> Option<u8> {
+        self.image_index
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.color_scheme
+    }
+}
+
+/// Sun position, selected image and color scheme sampled over a date range
+#[derive(Debug, Clone)]
+pub struct SolarPathTable {
+    rows: Vec<SolarPathRow>,
+}
+
+impl SolarPathTable {
+    /// Builds the table for the theme stored in `theme_directory`
+    pub fn for_theme(theme_directory: &Path, options: &SolarPathOptions) -> DwallResult<Self> {
+        let theme_definition = load_cached_theme_definition(theme_directory)?;
+        Self::calculate(&theme_definition, options)
+    }
+
+    /// Builds the table for the given theme configuration
+    ///
+    /// The color scheme starts from the plain day/night decision at the first
+    /// sample and then carries over, so the hysteresis behaves as in the daemon.
+    pub(crate) fn calculate(
+        theme_definition: &ThemeDefinition,
+        options: &SolarPathOptions,
+    ) -> DwallResult<Self> {
+        let position = &options.position;
+        let end = options.end()?;
+        let track_moon = theme_definition.tracks_moon();
+        let threshold_config = ThresholdConfig::from_position(position);
+
+        let mut rows = Vec::new();
+        let mut daylight: Option<(Date, DaylightState)> = None;
+        let mut color_scheme: Option<ColorScheme> = None;
+        let mut instant = options.start()?;
+
+        while instant < end {
+            let local_time = OffsetDateTime::from_utc(instant, options.offset)?;
+            let local_date = local_time.date();
+
+            let daylight_state = match daylight {
+                Some((date, state)) if date == local_date => state,
+                _ => {
+                    let state = DaylightState::detect(
+                        position,
+                        local_date,
+                        &options.model,
+                        &threshold_config,
+                    );
+                    daylight = Some((local_date, state));
+                    state
+                }
+            };
+
+            let sun = SolarPosition::new(position, &instant, &options.model);
+            let moon = track_moon
+                .then(|| LunarPosition::new(position, &instant, options.model.atmosphere()));
+            let current_scheme = color_scheme.unwrap_or(if sun.altitude() >= 0.0 {
+                ColorScheme::Light
+            } else {
+                ColorScheme::Dark
+            });
+            let next_scheme = determine_color_scheme_with_hysteresis(
+                &sun,
+                &current_scheme,
+                &threshold_config,
+                &local_time,
+                &daylight_state,
+            );
+            color_scheme = Some(next_scheme);
+
+            let moment = SelectionMoment::new(SkyPosition::new(sun, moon), local_time, next_scheme);
+            let image_index = theme_definition
+                .select(&moment, &SelectionPolicy::default())
+                .map(|choice| choice.primary_index());
+
+            rows.push(SolarPathRow {
+                time: local_time.to_rfc3339(),
+                altitude: sun.altitude(),
+                azimuth: sun.azimuth(),
+                image_index,
+                color_scheme: next_scheme,
+            });
+
+            instant = instant.add_seconds(options.step.as_secs())?;
+        }
+
+        debug!(
+            rows = rows.len(),
+            from = %options.from,
+            to = %options.to,
+            step_seconds = options.step.as_secs(),
+            "Calculated solar path table"
+        );
+
+        Ok(Self { rows })
+    }
+
+    pub fn rows(&self) -> &[SolarPathRow] {
+        &self.rows
+    }
+
+    /// Writes the table in the given format
+    pub fn write(&self, format: SolarPathFormat, writer: &mut impl Write) -> DwallResult<()> {
+        match format {
+            SolarPathFormat::Csv => {
+                writeln!(writer, "{CSV_HEADER}")?;
+                for row in &self.rows {
+                    writeln!(
+                        writer,
+                        "{},{:.4},{:.4},{},{}",
+                        row.time,
+                        row.altitude,
+                        row.azimuth,
+                        row.image_index.map(|i| i.to_string()).unwrap_or_default(),
+                        // Same names as in JSON
+                        row.color_scheme.as_str()
+                    )?;
+                }
+            }
+            SolarPathFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, &self.rows)?;
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+
+    fn london_midsummer(step: Duration) -> SolarPathOptions {
+        let date = Date::new(2024, Month::June, 21).unwrap();
+        SolarPathOptions::new(
+            Position::new(51.5074, -0.1278, 11.0).unwrap(),
+            SolarModel::default(),
+            date,
+            date,
+            step,
+            "+01:00".parse().unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn day_and_night() -> ThemeDefinition {
+        ThemeDefinition::Solar(
+            serde_json::from_str(
+                r#"[
+                    {"index": 0, "altitude": 45.0, "azimuth": 180.0},
+                    {"index": 1, "altitude": -30.0, "azimuth": 0.0}
+                ]"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn samples_whole_local_days() {
+        let table = SolarPathTable::calculate(
+            &day_and_night(),
+            &london_midsummer(Duration::from_secs(3600)),
+        )
+        .unwrap();
+        let rows = table.rows();
+
+        assert_eq!(rows.len(), 24);
+        assert_eq!(rows[0].time(), "2024-06-21T00:00:00+01:00");
+        assert_eq!(rows[23].time(), "2024-06-21T23:00:00+01:00");
+
+        // Local noon picks the day image in light mode, midnight the night image in dark mode
+        assert_eq!(rows[13].image_index(), Some(0));
+        assert_eq!(rows[13].color_scheme(), ColorScheme::Light);
+        assert_eq!(rows[1].image_index(), Some(1));
+        assert_eq!(rows[1].color_scheme(), ColorScheme::Dark);
+    }
+
+    #[test]
+    fn writes_csv_and_json() {
+        let table = SolarPathTable::calculate(
+            &day_and_night(),
+            &london_midsummer(Duration::from_secs(6 * 3600)),
+        )
+        .unwrap();
+
+        let mut csv = Vec::new();
+        table.write(SolarPathFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("2024-06-21T00:00:00+01:00,"));
+        assert!(lines[1].ends_with(",1,DARK"));
+
+        let mut json = Vec::new();
+        table.write(SolarPathFormat::Json, &mut json).unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 4);
+        assert_eq!(parsed[2]["color_scheme"], "LIGHT");
+        assert_eq!(parsed[2]["image_index"], 0);
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let position = Position::new(0.0, 0.0, 0.0).unwrap();
+        let from = Date::new(2024, Month::June, 21).unwrap();
+        let to = Date::new(2024, Month::June, 20).unwrap();
+        let offset = "+00:00".parse().unwrap();
+
+        assert!(matches!(
+            SolarPathOptions::new(
+                position,
+                SolarModel::default(),
+                from,
+                to,
+                Duration::from_secs(60),
+                offset
+            ),
+            Err(SolarPathError::InvalidDateRange { .. })
+        ));
+        assert!(matches!(
+            SolarPathOptions::new(
+                position,
+                SolarModel::default(),
+                from,
+                from,
+                Duration::ZERO,
+                offset
+            ),
+            Err(SolarPathError::InvalidStep)
+        ));
+        assert_eq!(
+            "JSON".parse::<SolarPathFormat>().unwrap(),
+            SolarPathFormat::Json
+        );
+        assert!("xml".parse::<SolarPathFormat>().is_err());
+    }
+}
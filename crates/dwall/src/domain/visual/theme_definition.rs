@@ -0,0 +1,324 @@
+//! Theme descriptions found in a theme directory
+//!
+//! A theme keys its images to sun positions in `solar.json`, to local clock times
+//! in `time.json`, or to the light and dark color schemes in `appearance.json`.
+//! When several files exist they are preferred in that order.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use time::OffsetDateTime;
+
+use crate::{
+    DwallResult,
+    domain::{
+        time::solar_calculator::SolarAngle,
+        visual::{
+            ColorScheme, ThemeError,
+            time_of_day::{TimeOfDay, TimeSlot, select_by_time},
+            wallpaper::{SelectionPolicy, SkyPosition, WallpaperChoice, WallpaperSelector},
+        },
+    },
+};
+
+pub(crate) const SOLAR_CONFIG_FILENAME: &str = "solar.json";
+pub(crate) const TIME_CONFIG_FILENAME: &str = "time.json";
+pub(crate) const APPEARANCE_CONFIG_FILENAME: &str = "appearance.json";
+
+/// Description files in order of preference
+const CONFIG_FILENAMES: [&str; 3] = [
+    SOLAR_CONFIG_FILENAME,
+    TIME_CONFIG_FILENAME,
+    APPEARANCE_CONFIG_FILENAME,
+];
+
+/// Sky, local wall clock and color scheme at the instant a wallpaper is selected
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SelectionMoment {
+    sky: SkyPosition,
+    local_time: OffsetDateTime,
+    /// Decision of the color scheme engine at this instant
+    color_scheme: ColorScheme,
+}
+
+impl SelectionMoment {
+    pub(crate) fn new(
+        sky: SkyPosition,
+        local_time: OffsetDateTime,
+        color_scheme: ColorScheme,
+    ) -> Self {
+        Self {
+            sky,
+            local_time,
+            color_scheme,
+        }
+    }
+
+    pub(crate) fn sky(&self) -> &SkyPosition {
+        &self.sky
+    }
+
+    pub(crate) fn local_time(&self) -> &OffsetDateTime {
+        &self.local_time
+    }
+
+    pub(crate) fn color_scheme(&self) -> ColorScheme {
+        self.color_scheme
+    }
+}
+
+/// Content of `appearance.json`: one image for each color scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppearancePair {
+    light: u8,
+    dark: u8,
+}
+
+impl AppearancePair {
+    pub fn new(light: u8, dark: u8) -> Self {
+        Self { light, dark }
+    }
+
+    pub fn light(&self) -> u8 {
+        self.light
+    }
+
+    pub fn dark(&self) -> u8 {
+        self.dark
+    }
+
+    /// Image shown while `color_scheme` is active
+    pub fn image_for(&self, color_scheme: ColorScheme) -> u8 {
+        match color_scheme {
+            ColorScheme::Light => self.light,
+            ColorScheme::Dark => self.dark,
+        }
+    }
+}
+
+/// How a theme maps the current moment to one of its images
+#[derive(Debug, Clone)]
+pub(crate) enum ThemeDefinition {
+    /// Images keyed to sun positions, from `solar.json`
+    Solar(Vec<SolarAngle>),
+
+    /// Images keyed to local clock times, from `time.json`
+    TimeOfDay(Vec<TimeSlot>),
+
+    /// One light and one dark image, from `appearance.json`
+    ///
+    /// The image flips together with the system color scheme and is never blended.
+    Appearance(AppearancePair),
+}
+
+impl fmt::Display for ThemeDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeDefinition::Solar(_) => write!(f, "solar"),
+            ThemeDefinition::TimeOfDay(_) => write!(f, "time-of-day"),
+            ThemeDefinition::Appearance(_) => write!(f, "appearance"),
+        }
+    }
+}
+
+impl ThemeDefinition {
+    /// Reads the theme description from `theme_directory`
+    pub(crate) fn load(theme_directory: &Path) -> DwallResult<Self> {
+        let definition = Self::read(theme_directory)?;
+        if let ThemeDefinition::Solar(angles) = &definition {
+            validate_solar_angles(angles)?;
+        }
+
+        Ok(definition)
+    }
+
+    /// Reads the theme description from `theme_directory` without checking its values
+    pub(crate) fn read(theme_directory: &Path) -> DwallResult<Self> {
+        let solar_config_path = theme_directory.join(SOLAR_CONFIG_FILENAME);
+        if solar_config_path.is_file() {
+            return read_json(&solar_config_path).map(ThemeDefinition::Solar);
+        }
+
+        let time_config_path = theme_directory.join(TIME_CONFIG_FILENAME);
+        if time_config_path.is_file() {
+            return read_json(&time_config_path).map(ThemeDefinition::TimeOfDay);
+        }
+
+        let appearance_config_path = theme_directory.join(APPEARANCE_CONFIG_FILENAME);
+        if appearance_config_path.is_file() {
+            return read_json(&appearance_config_path).map(ThemeDefinition::Appearance);
+        }
+
+        error!(
+            theme_directory = %theme_directory.display(),
+            "Theme directory contains none of 'solar.json', 'time.json' or 'appearance.json'"
+        );
+        Err(ThemeError::ThemeConfigurationMissing.into())
+    }
+
+    /// Indices of all images the theme refers to, in file order
+    pub(crate) fn image_indices(&self) -> Vec<u8> {
+        match self {
+            ThemeDefinition::Solar(angles) => angles.iter().map(SolarAngle::index).collect(),
+            ThemeDefinition::TimeOfDay(slots) => slots.iter().map(TimeSlot::index).collect(),
+            ThemeDefinition::Appearance(pair) => vec![pair.light, pair.dark],
+        }
+    }
+
+    /// Whether selection needs the moon's position
+    pub(crate) fn tracks_moon(&self) -> bool {
+        match self {
+            ThemeDefinition::Solar(angles) => WallpaperSelector::has_moon_keyed_images(angles),
+            ThemeDefinition::TimeOfDay(_) | ThemeDefinition::Appearance(_) => false,
+        }
+    }
+
+    /// Selects the image, or the pair of images to blend, for `moment`
+    pub(crate) fn select(
+        &self,
+        moment: &SelectionMoment,
+        policy: &SelectionPolicy,
+    ) -> Option<WallpaperChoice> {
+        match self {
+            ThemeDefinition::Solar(angles) => {
+                WallpaperSelector::select(angles, moment.sky(), policy)
+            }
+            ThemeDefinition::TimeOfDay(slots) => {
+                select_by_time(slots, TimeOfDay::of(moment.local_time()), policy.blend())
+            }
+            ThemeDefinition::Appearance(pair) => Some(WallpaperChoice::Single(
+                pair.image_for(moment.color_scheme()),
+            )),
+        }
+    }
+}
+
+/// Path of the description file [`ThemeDefinition::read`] would use, if any
+pub(crate) fn configuration_path(theme_directory: &Path) -> Option<PathBuf> {
+    CONFIG_FILENAMES
+        .iter()
+        .map(|file_name| theme_directory.join(file_name))
+        .find(|path| path.is_file())
+}
+
+/// Angles that cannot describe a sun position, such as NaN or an altitude above 90°
+///
+/// Yields the image index, the name of the offending field and its value.
+pub(crate) fn invalid_solar_angle_fields(
+    angles: &[SolarAngle],
+) -> impl Iterator<Item = (u8, &'static str, f64)> + '_ {
+    angles.iter().flat_map(|angle| {
+        [
+            ("altitude", angle.altitude(), -90.0..=90.0),
+            ("azimuth", angle.azimuth(), 0.0..=360.0),
+        ]
+        .into_iter()
+        // `contains` is false for NaN
+        .filter(|(_, value, range)| !range.contains(value))
+        .map(|(field, value, _)| (angle.index(), field, value))
+    })
+}
+
+/// Rejects the first angle that cannot describe a sun position
+fn validate_solar_angles(angles: &[SolarAngle]) -> Result<(), ThemeError> {
+    match invalid_solar_angle_fields(angles).next() {
+        Some((index, field, value)) => {
+            error!(
+                image_index = index,
+                field = field,
+                value = value,
+                "Invalid solar angle in theme configuration"
+            );
+            Err(ThemeError::InvalidSolarAngle {
+                index,
+                field,
+                value,
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> DwallResult<T> {
+    let content = fs::read_to_string(path).map_err(|io_error| {
+        error!(
+            error = %io_error,
+            config_path = %path.display(),
+            "Failed to read theme configuration file"
+        );
+        io_error
+    })?;
+
+    let parsed = serde_json::from_str(&content).map_err(|json_error| {
+        error!(
+            error = %json_error,
+            config_path = %path.display(),
+            "Failed to parse theme configuration JSON"
+        );
+        json_error
+    })?;
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+
+    #[test]
+    fn detects_theme_kind() {
+        let directory = scratch_directory("theme-kind");
+        assert!(ThemeDefinition::load(&directory).is_err());
+
+        fs::write(
+            directory.join(APPEARANCE_CONFIG_FILENAME),
+            r#"{"light": 0, "dark": 1}"#,
+        )
+        .unwrap();
+        let definition = ThemeDefinition::load(&directory).unwrap();
+        assert!(matches!(
+            definition,
+            ThemeDefinition::Appearance(pair) if pair.image_for(ColorScheme::Dark) == 1
+        ));
+        assert_eq!(definition.image_indices(), [0, 1]);
+
+        fs::write(
+            directory.join(TIME_CONFIG_FILENAME),
+            r#"[{"index": 0, "time": "06:00"}, {"index": 1, "time": "18:00"}]"#,
+        )
+        .unwrap();
+        // A time-of-day description takes precedence over an appearance pair
+        let definition = ThemeDefinition::load(&directory).unwrap();
+        assert!(matches!(definition, ThemeDefinition::TimeOfDay(_)));
+        assert_eq!(definition.image_indices(), [0, 1]);
+        assert!(!definition.tracks_moon());
+
+        // A solar description takes precedence
+        fs::write(
+            directory.join(SOLAR_CONFIG_FILENAME),
+            r#"[{"index": 0, "altitude": 10.0, "azimuth": 90.0}]"#,
+        )
+        .unwrap();
+        let definition = ThemeDefinition::load(&directory).unwrap();
+        assert!(matches!(definition, ThemeDefinition::Solar(_)));
+        assert_eq!(definition.to_string(), "solar");
+
+        // Out of range angles are a validation error, not a panic later on
+        fs::write(
+            directory.join(SOLAR_CONFIG_FILENAME),
+            r#"[{"index": 0, "altitude": 95.0, "azimuth": 90.0}]"#,
+        )
+        .unwrap();
+        let error = ThemeDefinition::load(&directory).unwrap_err();
+        assert!(
+            error.to_string().contains("altitude"),
+            "unexpected error: {error}"
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,560 @@
+//! Image format of each theme
+//!
+//! Built-in themes keep their images in one directory per format, such as `jpg/` and
+//! `png/`, while customized themes keep them in `images/`. A theme is shown in the
+//! first preferred format it has images in. The images of a theme without any can be
+//! converted into the cache instead, from a format the image library can decode into
+//! one it can encode.
+//!
+//! An image directory may also hold resolution variants in subdirectories such as
+//! `jpg/3840x2160/`, each with the full image set. Every monitor is shown the variant
+//! that fits it best.
+
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use image::codecs::jpeg::JpegEncoder;
+
+use crate::{
+    DwallResult,
+    config::ImageFormat,
+    domain::visual::{ThemeError, theme_validator::image_number_of},
+    infrastructure::display::Resolution,
+    lazy::DWALL_CACHE_DIR,
+};
+
+/// Subdirectory of [`DWALL_CACHE_DIR`] holding converted theme images
+const CONVERTED_CACHE_DIRECTORY: &str = "converted";
+
+/// JPEG quality used for converted images
+const JPEG_QUALITY: u8 = 92;
+
+/// Directory where converted theme images are cached
+pub(crate) fn converted_cache_directory() -> PathBuf {
+    DWALL_CACHE_DIR.join(CONVERTED_CACHE_DIRECTORY)
+}
+
+/// The directory holding the images a theme is shown with, and their format
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeImages {
+    directory: PathBuf,
+    format: ImageFormat,
+}
+
+impl ThemeImages {
+    /// Images of the theme in `theme_directory` in `format`, whether they exist or not
+    pub fn new(theme_directory: &Path, format: ImageFormat, is_customized: bool) -> Self {
+        let directory = if is_customized {
+            theme_directory.join("images")
+        } else {
+            theme_directory.join(format.as_str())
+        };
+        Self { directory, format }
+    }
+
+    /// Images of the theme in the first format of `preference` it has
+    ///
+    /// A theme with images in other formats only is converted into `cache_directory`
+    /// when `convert` is set, and rejected otherwise. A theme without any images gets
+    /// the most preferred format, so the missing files are reported once opened.
+    pub fn resolve(
+        theme_directory: &Path,
+        is_customized: bool,
+        preference: &[ImageFormat],
+        convert: bool,
+        cache_directory: &Path,
+    ) -> DwallResult<Self> {
+        let available = available_image_formats(theme_directory, is_customized);
+        if let Some(format) = preference.iter().find(|format| available.contains(format)) {
+            return Ok(Self::new(theme_directory, format.clone(), is_customized));
+        }
+
+        if available.is_empty() {
+            let format = preference.first().cloned().unwrap_or_default();
+            return Ok(Self::new(theme_directory, format, is_customized));
+        }
+
+        if !convert {
+            error!(
+                theme_directory = %theme_directory.display(),
+                available = %format_list(&available),
+                preferred = %format_list(preference),
+                "Theme has no images in a preferred format"
+            );
+            return Err(ThemeError::NoPreferredImageFormat {
+                available: format_list(&available),
+                preferred: format_list(preference),
+            }
+            .into());
+        }
+
+        convert_theme_images(
+            theme_directory,
+            is_customized,
+            &available,
+            preference,
+            cache_directory,
+        )
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn format(&self) -> &ImageFormat {
+        &self.format
+    }
+
+    /// Resolution variants in subdirectories of the image directory, smallest first
+    pub fn resolution_variants(&self) -> Vec<Resolution> {
+        resolution_variants(&self.directory)
+    }
+
+    /// The variant of these images that best fits a monitor of `resolution`
+    ///
+    /// Variants of the monitor's orientation are preferred, and among them the
+    /// smallest one covering the monitor, or else the largest one. Images outside
+    /// any variant are used when the theme has no variants, or when the resolution
+    /// is unknown and the theme has such images.
+    pub fn for_resolution(&self, resolution: Option<Resolution>) -> Self {
+        let variants = self.resolution_variants();
+        let variant = match resolution {
+            Some(resolution) => best_fitting_variant(&variants, resolution),
+            None if has_images(&self.directory, &self.format) => None,
+            None => variants.last().copied(),
+        };
+
+        variant.map_or_else(|| self.clone(), |variant| self.variant(variant))
+    }
+
+    fn variant(&self, resolution: Resolution) -> Self {
+        Self {
+            directory: self.directory.join(resolution.to_string()),
+            format: self.format.clone(),
+        }
+    }
+
+    /// File of the image with the zero-based `index`
+    pub fn image_path(&self, index: u8) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", u16::from(index) + 1, self.format.as_str()))
+    }
+}
+
+/// Formats the theme in `theme_directory` has images in, in [`ImageFormat::ALL`] order
+pub fn available_image_formats(theme_directory: &Path, is_customized: bool) -> Vec<ImageFormat> {
+    ImageFormat::ALL
+        .into_iter()
+        .filter(|format| {
+            let images = ThemeImages::new(theme_directory, format.clone(), is_customized);
+            has_images(images.directory(), format)
+                || images
+                    .resolution_variants()
+                    .into_iter()
+                    .any(|variant| has_images(images.variant(variant).directory(), format))
+        })
+        .collect()
+}
+
+/// Resolutions named by the subdirectories of `images_directory`, smallest first
+pub(crate) fn resolution_variants(images_directory: &Path) -> Vec<Resolution> {
+    let Ok(entries) = fs::read_dir(images_directory) else {
+        return Vec::new();
+    };
+
+    let mut variants: Vec<Resolution> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Resolution::parse(entry.file_name().to_str()?))
+        .collect();
+    variants.sort_by_key(|variant| (variant.pixel_count(), variant.width()));
+    variants.dedup();
+    variants
+}
+
+/// Variant to show on a monitor of `resolution`, see [`ThemeImages::for_resolution`]
+fn best_fitting_variant(variants: &[Resolution], resolution: Resolution) -> Option<Resolution> {
+    let same_orientation: Vec<Resolution> = variants
+        .iter()
+        .filter(|variant| variant.orientation() == resolution.orientation())
+        .copied()
+        .collect();
+    let candidates = if same_orientation.is_empty() {
+        variants
+    } else {
+        &same_orientation
+    };
+
+    // Candidates are sorted by size, so the first covering one is the smallest
+    candidates
+        .iter()
+        .find(|variant| variant.covers(resolution))
+        .or_else(|| candidates.last())
+        .copied()
+}
+
+/// Whether `directory` directly holds image files in `format`
+fn has_images(directory: &Path, format: &ImageFormat) -> bool {
+    fs::read_dir(directory).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let path = entry.path();
+            path.is_file() && image_format_of(&path).as_ref() == Some(format)
+        })
+    })
+}
+
+/// Format in which the theme's images on disk are shown: the first preferred one it
+/// has, or when `convert` is set, the one its images would be converted from
+pub fn select_image_format(
+    theme_directory: &Path,
+    is_customized: bool,
+    preference: &[ImageFormat],
+    convert: bool,
+) -> Option<ImageFormat> {
+    let available = available_image_formats(theme_directory, is_customized);
+    preference
+        .iter()
+        .find(|format| available.contains(format))
+        .or_else(|| {
+            available
+                .iter()
+                .find(|format| convert && format.is_decodable())
+        })
+        .cloned()
+}
+
+/// Converts the theme's images into `cache_directory`, skipping up-to-date files
+fn convert_theme_images(
+    theme_directory: &Path,
+    is_customized: bool,
+    available: &[ImageFormat],
+    preference: &[ImageFormat],
+    cache_directory: &Path,
+) -> DwallResult<ThemeImages> {
+    let source = available.iter().find(|format| format.is_decodable());
+    let target = preference.iter().find_map(|format| {
+        format
+            .codec()
+            .filter(|codec| codec.writing_enabled())
+            .map(|codec| (format, codec))
+    });
+    let (Some(source), Some((target, codec)), Some(theme_id)) =
+        (source, target, theme_directory.file_name())
+    else {
+        error!(
+            theme_directory = %theme_directory.display(),
+            available = %format_list(available),
+            preferred = %format_list(preference),
+            "Theme images cannot be converted to a preferred format"
+        );
+        return Err(ThemeError::ImageConversionUnavailable {
+            available: format_list(available),
+            preferred: format_list(preference),
+        }
+        .into());
+    };
+
+    let source = ThemeImages::new(theme_directory, source.clone(), is_customized);
+    let converted = ThemeImages {
+        directory: cache_directory.join(theme_id).join(target.as_str()),
+        format: target.clone(),
+    };
+
+    let mut converted_count =
+        convert_directory(source.directory(), source.format(), &converted, codec)?;
+    for variant in source.resolution_variants() {
+        converted_count += convert_directory(
+            source.variant(variant).directory(),
+            source.format(),
+            &converted.variant(variant),
+            codec,
+        )?;
+    }
+
+    if converted_count > 0 {
+        info!(
+            theme_directory = %theme_directory.display(),
+            from = source.format.as_str(),
+            to = converted.format.as_str(),
+            images = converted_count,
+            cache_directory = %converted.directory.display(),
+            "Converted theme images"
+        );
+    }
+
+    Ok(converted)
+}
+
+/// Converts the images in `format` directly in `source_directory` into `output`,
+/// returning how many were not up to date
+fn convert_directory(
+    source_directory: &Path,
+    format: &ImageFormat,
+    output: &ThemeImages,
+    codec: image::ImageFormat,
+) -> DwallResult<usize> {
+    if !has_images(source_directory, format) {
+        return Ok(0);
+    }
+    fs::create_dir_all(&output.directory)?;
+
+    let mut converted_count = 0;
+    for entry in fs::read_dir(source_directory)? {
+        let path = entry?.path();
+        if image_format_of(&path).as_ref() != Some(format) {
+            continue;
+        }
+        let Some(index) = image_number_of(&path) else {
+            continue;
+        };
+
+        let output_path = output.image_path(index);
+        if is_up_to_date(&output_path, &path) {
+            continue;
+        }
+        convert_image(&path, &output_path, codec)?;
+        converted_count += 1;
+    }
+    Ok(converted_count)
+}
+
+/// Writes `source` to `output` encoded with `codec`, through a temporary file
+fn convert_image(source: &Path, output: &Path, codec: image::ImageFormat) -> DwallResult<()> {
+    let image = image::open(source)?;
+
+    // Convert under a temporary name so a crash never leaves a truncated cache entry
+    let mut temporary = output.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    if codec == image::ImageFormat::Jpeg {
+        let encoder =
+            JpegEncoder::new_with_quality(BufWriter::new(File::create(&temporary)?), JPEG_QUALITY);
+        image.to_rgb8().write_with_encoder(encoder)?;
+    } else {
+        image.save_with_format(&temporary, codec)?;
+    }
+    fs::rename(&temporary, output)?;
+
+    debug!(
+        source = %source.display(),
+        output = %output.display(),
+        "Converted theme image"
+    );
+    Ok(())
+}
+
+/// Whether `output` exists and was written after `source` last changed
+fn is_up_to_date(output: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(output), modified(source)) {
+        (Ok(output), Ok(source)) => output >= source,
+        _ => false,
+    }
+}
+
+fn image_format_of(path: &Path) -> Option<ImageFormat> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(ImageFormat::from_extension)
+}
+
+fn format_list(formats: &[ImageFormat]) -> String {
+    formats
+        .iter()
+        .map(ImageFormat::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+
+    /// Writes a small gradient, in the format given by the extension of `path`
+    fn write_image(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        RgbImage::from_fn(8, 4, |x, y| Rgb([(x * 30) as u8, (y * 60) as u8, 128]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn picks_first_preferred_format_of_each_theme() {
+        let theme_directory = scratch_directory("images-preference");
+        write_image(&theme_directory.join("png/1.png"));
+        write_image(&theme_directory.join("webp/1.webp"));
+        fs::create_dir_all(theme_directory.join("jpg")).unwrap();
+
+        assert_eq!(
+            available_image_formats(&theme_directory, false),
+            [ImageFormat::Png, ImageFormat::Webp]
+        );
+
+        let images = ThemeImages::resolve(
+            &theme_directory,
+            false,
+            &[ImageFormat::Jpeg, ImageFormat::Webp, ImageFormat::Png],
+            false,
+            &theme_directory.join("cache"),
+        )
+        .unwrap();
+        assert_eq!(images.format(), &ImageFormat::Webp);
+        assert_eq!(images.image_path(0), theme_directory.join("webp/1.webp"));
+
+        let error = ThemeImages::resolve(
+            &theme_directory,
+            false,
+            &[ImageFormat::Jpeg],
+            false,
+            &theme_directory.join("cache"),
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("png, webp"),
+            "unexpected error: {error}"
+        );
+
+        fs::remove_dir_all(&theme_directory).unwrap();
+    }
+
+    #[test]
+    fn converts_images_into_cache() {
+        let directory = scratch_directory("images-convert");
+        let theme_directory = directory.join("theme");
+        write_image(&theme_directory.join("images/1.png"));
+        write_image(&theme_directory.join("images/2.png"));
+        let cache_directory = directory.join("cache");
+
+        let preference = [ImageFormat::Avif, ImageFormat::Jpeg];
+        assert_eq!(
+            select_image_format(&theme_directory, true, &preference, true),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            select_image_format(&theme_directory, true, &preference, false),
+            None
+        );
+
+        let images =
+            ThemeImages::resolve(&theme_directory, true, &preference, true, &cache_directory)
+                .unwrap();
+        assert_eq!(images.format(), &ImageFormat::Jpeg);
+        assert_eq!(images.directory(), cache_directory.join("theme/jpg"));
+        for index in 0..2 {
+            let path = images.image_path(index);
+            assert_eq!(
+                image::ImageReader::open(&path)
+                    .unwrap()
+                    .with_guessed_format()
+                    .unwrap()
+                    .format(),
+                Some(image::ImageFormat::Jpeg)
+            );
+        }
+
+        // Converted files are reused until their source changes
+        let modified = fs::metadata(images.image_path(0))
+            .unwrap()
+            .modified()
+            .unwrap();
+        ThemeImages::resolve(&theme_directory, true, &preference, true, &cache_directory).unwrap();
+        assert_eq!(
+            fs::metadata(images.image_path(0))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn picks_best_fitting_resolution_variant() {
+        let theme_directory = scratch_directory("images-variants");
+        for variant in ["1920x1080", "3840x2160", "1080x1920"] {
+            write_image(&theme_directory.join(format!("jpg/{variant}/1.jpg")));
+        }
+        fs::create_dir_all(theme_directory.join("jpg/thumbnails")).unwrap();
+
+        // Variants alone make the format available
+        assert_eq!(
+            available_image_formats(&theme_directory, false),
+            [ImageFormat::Jpeg]
+        );
+
+        let images = ThemeImages::new(&theme_directory, ImageFormat::Jpeg, false);
+        assert_eq!(
+            images.resolution_variants(),
+            [
+                Resolution::new(1080, 1920),
+                Resolution::new(1920, 1080),
+                Resolution::new(3840, 2160)
+            ]
+        );
+
+        let variant_for = |width, height| {
+            images
+                .for_resolution(Some(Resolution::new(width, height)))
+                .image_path(0)
+        };
+        assert_eq!(
+            variant_for(1920, 1080),
+            theme_directory.join("jpg/1920x1080/1.jpg")
+        );
+        assert_eq!(
+            variant_for(2560, 1440),
+            theme_directory.join("jpg/3840x2160/1.jpg")
+        );
+        assert_eq!(
+            variant_for(7680, 4320),
+            theme_directory.join("jpg/3840x2160/1.jpg")
+        );
+        assert_eq!(
+            variant_for(1440, 2560),
+            theme_directory.join("jpg/1080x1920/1.jpg")
+        );
+        assert_eq!(
+            images.for_resolution(None).image_path(0),
+            theme_directory.join("jpg/3840x2160/1.jpg")
+        );
+
+        // Images outside the variants are kept for monitors of unknown size
+        write_image(&theme_directory.join("jpg/1.jpg"));
+        assert_eq!(
+            images.for_resolution(None).image_path(0),
+            theme_directory.join("jpg/1.jpg")
+        );
+
+        fs::remove_dir_all(&theme_directory).unwrap();
+    }
+
+    #[test]
+    fn refuses_formats_without_decoder() {
+        let directory = scratch_directory("images-undecodable");
+        let theme_directory = directory.join("theme");
+        fs::create_dir_all(theme_directory.join("jxl")).unwrap();
+        fs::write(theme_directory.join("jxl/1.jxl"), [0xFF, 0x0A]).unwrap();
+
+        let error = ThemeImages::resolve(
+            &theme_directory,
+            false,
+            &[ImageFormat::Jpeg],
+            true,
+            &directory.join("cache"),
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("cannot be converted"),
+            "unexpected error: {error}"
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
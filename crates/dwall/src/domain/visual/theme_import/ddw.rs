@@ -0,0 +1,343 @@
+//! WinDynamicDesktop `.ddw` theme packages
+//!
+//! A `.ddw` file is a zip archive with a `theme.json` and the images it names. The
+//! theme splits the day into sunrise, day, sunset and night segments, each with its own
+//! list of images. Dwall has no segments, so each list is spread evenly over its
+//! segment of a reference sun path and written as `solar.json`.
+
+use std::{
+    collections::HashMap,
+    f64::consts::TAU,
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{SolarAngle, config::ImageFormat, domain::visual::CustomizedThemeMetadata};
+
+use super::{
+    DwallResult, ImportedTheme, ImportedThemeConfiguration, ThemeImportError, read_package_entry,
+    sanitize_file_name,
+};
+
+const THEME_CONFIG_FILENAME: &str = "theme.json";
+
+/// Author recorded for themes whose `theme.json` credits nobody
+const DEFAULT_AUTHOR: &str = "WinDynamicDesktop";
+
+/// Half the length of the sunrise and sunset segments, as a fraction of the day
+const TWILIGHT_HALF_SPAN: f64 = 1.0 / 24.0;
+
+/// Altitude of the reference sun at noon, and its depth below the horizon at midnight
+const REFERENCE_PEAK_ALTITUDE: f64 = 45.0;
+
+/// Content of `theme.json`
+///
+/// Image lists hold the numbers that replace `*` in `imageFilename`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DdwThemeConfig {
+    display_name: Option<String>,
+    image_filename: String,
+    image_credits: Option<String>,
+    #[serde(default)]
+    sunrise_image_list: Option<Vec<u32>>,
+    #[serde(default)]
+    day_image_list: Option<Vec<u32>>,
+    #[serde(default)]
+    sunset_image_list: Option<Vec<u32>>,
+    #[serde(default)]
+    night_image_list: Option<Vec<u32>>,
+}
+
+/// Imports the `.ddw` package at `ddw_path` as a customized theme
+///
+/// Returns the ID of the new theme.
+pub fn import_ddw_theme(
+    ddw_path: &Path,
+    customized_themes_directory: &Path,
+) -> DwallResult<String> {
+    info!(ddw_path = %ddw_path.display(), "Importing WinDynamicDesktop theme");
+
+    let file = File::open(ddw_path).inspect_err(|e| {
+        error!(
+            ddw_path = %ddw_path.display(),
+            error = %e,
+            "Failed to open theme package"
+        );
+    })?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(ThemeImportError::from)?;
+
+    // Packages made by hand sometimes wrap everything in a folder; match on file names only
+    let entries: HashMap<String, String> = archive
+        .file_names()
+        .filter_map(|name| {
+            let file_name = name.rsplit('/').next()?;
+            (!file_name.is_empty()).then(|| (file_name.to_lowercase(), name.to_string()))
+        })
+        .collect();
+
+    let config: DdwThemeConfig =
+        serde_json::from_slice(&read_entry(&mut archive, &entries, THEME_CONFIG_FILENAME)?)
+            .inspect_err(|e| error!(error = %e, "Failed to parse theme.json"))?;
+    debug!(config = ?config, "Parsed WinDynamicDesktop theme configuration");
+
+    let image_format = image_format_of(&config.image_filename)?;
+
+    let segments = Segments::of(&config);
+    let mut images = Vec::new();
+    let mut solar_angles = Vec::new();
+    for (image_ids, (start, end)) in segments.lists() {
+        let step = (end - start) / image_ids.len() as f64;
+        for (position, image_id) in image_ids.iter().enumerate() {
+            let entry_name = config.image_filename.replace('*', &image_id.to_string());
+            let (altitude, azimuth) =
+                reference_sun_position(start + step * (position as f64 + 0.5));
+
+            // Each occurrence gets its own file, so every index appears once in solar.json
+            let index = u8::try_from(images.len())
+                .map_err(|_| ThemeImportError::TooManyImages(images.len() + 1))?;
+            images.push(read_entry(&mut archive, &entries, &entry_name)?);
+            solar_angles.push(SolarAngle::new(index, altitude, azimuth));
+        }
+    }
+
+    let theme_name = config
+        .display_name
+        .as_deref()
+        .or_else(|| ddw_path.file_stem().and_then(|stem| stem.to_str()))
+        .map(sanitize_file_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "WinDynamicDesktop".to_string());
+    let author = config
+        .image_credits
+        .as_deref()
+        .map(sanitize_file_name)
+        .filter(|author| !author.is_empty())
+        .unwrap_or_else(|| DEFAULT_AUTHOR.to_string());
+
+    let metadata = CustomizedThemeMetadata::new(theme_name, author, 1, image_format);
+    ImportedTheme::new(
+        metadata,
+        images,
+        ImportedThemeConfiguration::Solar(solar_angles),
+    )?
+    .write(customized_themes_directory)
+}
+
+/// Boundaries of the four segments of the day, as fractions of the day from midnight
+///
+/// Sunrise is at 0.25 and sunset at 0.75. A theme without sunrise or sunset images has
+/// no twilight segment there, and its day and night meet at the horizon.
+struct Segments<'a> {
+    config: &'a DdwThemeConfig,
+    sunrise_half_span: f64,
+    sunset_half_span: f64,
+}
+
+impl<'a> Segments<'a> {
+    fn of(config: &'a DdwThemeConfig) -> Self {
+        let half_span = |list: &Option<Vec<u32>>| {
+            if list.as_ref().is_some_and(|ids| !ids.is_empty()) {
+                TWILIGHT_HALF_SPAN
+            } else {
+                0.0
+            }
+        };
+
+        Self {
+            config,
+            sunrise_half_span: half_span(&config.sunrise_image_list),
+            sunset_half_span: half_span(&config.sunset_image_list),
+        }
+    }
+
+    /// Non-empty image lists with their segments, in order through the day
+    fn lists(&self) -> impl Iterator<Item = (&'a [u32], (f64, f64))> {
+        let (sunrise, sunset) = (0.25, 0.75);
+        [
+            (
+                &self.config.sunrise_image_list,
+                (
+                    sunrise - self.sunrise_half_span,
+                    sunrise + self.sunrise_half_span,
+                ),
+            ),
+            (
+                &self.config.day_image_list,
+                (
+                    sunrise + self.sunrise_half_span,
+                    sunset - self.sunset_half_span,
+                ),
+            ),
+            (
+                &self.config.sunset_image_list,
+                (
+                    sunset - self.sunset_half_span,
+                    sunset + self.sunset_half_span,
+                ),
+            ),
+            (
+                &self.config.night_image_list,
+                (
+                    sunset + self.sunset_half_span,
+                    1.0 + sunrise - self.sunrise_half_span,
+                ),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(list, segment)| {
+            list.as_deref()
+                .filter(|ids| !ids.is_empty())
+                .map(|ids| (ids, segment))
+        })
+    }
+}
+
+/// Sun position on the reference path at `fraction` of the day from midnight
+///
+/// The reference sun rises due east at 0.25, culminates due south at noon and sets due
+/// west at 0.75, like on an equinox at mid-latitudes. Returns altitude and azimuth
+/// in degrees, rounded to hundredths.
+fn reference_sun_position(fraction: f64) -> (f64, f64) {
+    let round = |degrees: f64| (degrees * 100.0).round() / 100.0;
+
+    let altitude = REFERENCE_PEAK_ALTITUDE * (TAU * (fraction - 0.25)).sin();
+    let azimuth = (fraction * 360.0).rem_euclid(360.0);
+
+    (round(altitude), round(azimuth))
+}
+
+fn image_format_of(image_filename: &str) -> DwallResult<ImageFormat> {
+    let extension = Path::new(image_filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+
+    ImageFormat::from_extension(extension).ok_or_else(|| {
+        error!(
+            image_filename = image_filename,
+            "Unsupported image format in theme package"
+        );
+        ThemeImportError::UnsupportedImageFormat(extension.to_string()).into()
+    })
+}
+
+fn read_entry<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    entries: &HashMap<String, String>,
+    file_name: &str,
+) -> DwallResult<Vec<u8>> {
+    let entry_name = entries.get(&file_name.to_lowercase()).ok_or_else(|| {
+        error!(file_name = file_name, "Theme package entry not found");
+        ThemeImportError::MissingEntry(file_name.to_string())
+    })?;
+
+    let entry = archive
+        .by_name(entry_name)
+        .map_err(ThemeImportError::from)?;
+    read_package_entry(entry_name, entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        domain::visual::CUSTOMIZED_THEME_METADATA_FILENAME,
+        error::DwallError,
+        utils::testing::{png_image, scratch_directory, write_zip},
+    };
+
+    const THEME_JSON: &str = r#"{
+        "displayName": "Desert Dunes",
+        "imageFilename": "dunes_*.png",
+        "imageCredits": "Jane",
+        "sunriseImageList": [1],
+        "dayImageList": [2, 3],
+        "sunsetImageList": [4],
+        "nightImageList": [5]
+    }"#;
+
+    fn images() -> Vec<Vec<u8>> {
+        (1..=5u8).map(|n| png_image([n * 40, 0, 0])).collect()
+    }
+
+    #[test]
+    fn imports_segments_in_order_of_the_day() {
+        let directory = scratch_directory("ddw-segments");
+        let images = images();
+        let names: Vec<String> = (1..=5).map(|n| format!("Dunes/dunes_{n}.png")).collect();
+        let mut entries: Vec<(&str, &[u8])> = vec![("Dunes/theme.json", THEME_JSON.as_bytes())];
+        entries.extend(
+            names
+                .iter()
+                .map(String::as_str)
+                .zip(images.iter().map(Vec::as_slice)),
+        );
+        let ddw_path = directory.join("dunes.ddw");
+        write_zip(&ddw_path, &entries);
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_ddw_theme(&ddw_path, &themes_directory).unwrap();
+        assert_eq!(theme_id, "Desert-Dunes-Jane-v1");
+
+        let theme_directory = themes_directory.join(&theme_id);
+        let metadata: CustomizedThemeMetadata = toml::from_str(
+            &fs::read_to_string(theme_directory.join(CUSTOMIZED_THEME_METADATA_FILENAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(metadata.theme_name(), "Desert Dunes");
+        assert_eq!(metadata.author(), "Jane");
+        assert_eq!(metadata.image_format(), &ImageFormat::Png);
+
+        for (position, image) in images.iter().enumerate() {
+            let written =
+                fs::read(theme_directory.join(format!("images/{}.png", position + 1))).unwrap();
+            assert_eq!(&written, image);
+        }
+
+        let angles: Vec<SolarAngle> =
+            serde_json::from_str(&fs::read_to_string(theme_directory.join("solar.json")).unwrap())
+                .unwrap();
+        let positions: Vec<_> = angles
+            .iter()
+            .map(|angle| (angle.index(), angle.altitude(), angle.azimuth()))
+            .collect();
+        // Sunrise and sunset sit on the horizon, the day above it and the night below
+        assert_eq!(positions[0], (0, 0.0, 90.0));
+        assert!(positions[1].1 > 0.0 && positions[1].2 > 90.0);
+        assert!(positions[2].1 > 0.0 && positions[2].2 > positions[1].2);
+        assert_eq!(positions[3], (3, 0.0, 270.0));
+        assert_eq!(positions[4], (4, -REFERENCE_PEAK_ALTITUDE, 0.0));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_packages_missing_an_image() {
+        let directory = scratch_directory("ddw-missing");
+        let image = png_image([0, 0, 0]);
+        let ddw_path = directory.join("dunes.ddw");
+        write_zip(
+            &ddw_path,
+            &[
+                ("theme.json", THEME_JSON.as_bytes()),
+                ("dunes_1.png", &image),
+            ],
+        );
+
+        let themes_directory = directory.join("themes");
+        let error = import_ddw_theme(&ddw_path, &themes_directory).unwrap_err();
+        assert!(matches!(
+            error,
+            DwallError::ThemeImport(ThemeImportError::MissingEntry(name)) if name == "dunes_2.png"
+        ));
+        assert!(!themes_directory.join("Desert-Dunes-Jane-v1").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,579 @@
+//! GNOME timed slideshow backgrounds
+//!
+//! GNOME describes a dynamic background as an XML file: a `<starttime>` followed by
+//! `<static>` images shown for a duration and `<transition>` cross-fades between two
+//! images. The whole sequence repeats once it ends.
+//!
+//! A slideshow whose sequence lasts one day is imported as a time-of-day theme, with
+//! each image starting halfway through the transition that fades it in. Solar themes
+//! are exported for one place and date, with each image shown while it is the one
+//! closest to the sun.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use quick_xml::{
+    escape::{escape, resolve_predefined_entity},
+    events::Event,
+};
+use time::{Date, Offset};
+
+use crate::{
+    Position, SolarModel,
+    config::ImageFormat,
+    domain::visual::{
+        CustomizedThemeMetadata, SolarPathOptions, SolarPathTable, TimeOfDay, TimeSlot,
+    },
+};
+
+use super::{
+    DwallResult, ImportedTheme, ImportedThemeConfiguration, ThemeImportError, sanitize_file_name,
+};
+
+const SECONDS_PER_DAY: f64 = 24.0 * 3600.0;
+
+/// Author recorded for imported themes; the XML does not name one
+const DEFAULT_AUTHOR: &str = "GNOME";
+
+/// Sampling step of the exported day; image changes are placed to the minute
+const EXPORT_STEP: Duration = Duration::from_secs(60);
+
+/// An XML element with its text and child elements
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: HashMap<String, String>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+
+    fn child_number(&self, name: &str) -> DwallResult<f64> {
+        self.child_text(name)
+            .and_then(|text| text.parse().ok())
+            .filter(|number: &f64| number.is_finite() && *number >= 0.0)
+            .ok_or_else(|| {
+                ThemeImportError::InvalidMetadata(format!("<{}> has no valid <{name}>", self.name))
+                    .into()
+            })
+    }
+}
+
+/// Imports the GNOME background XML at `xml_path` as a time-of-day customized theme
+///
+/// Image paths that do not exist as written are looked up next to the XML file.
+/// Returns the ID of the new theme.
+pub fn import_gnome_background(
+    xml_path: &Path,
+    customized_themes_directory: &Path,
+) -> DwallResult<String> {
+    info!(xml_path = %xml_path.display(), "Importing GNOME background");
+
+    let content = fs::read_to_string(xml_path).inspect_err(|e| {
+        error!(
+            xml_path = %xml_path.display(),
+            error = %e,
+            "Failed to read GNOME background XML"
+        );
+    })?;
+    let background = parse_xml(&content)?;
+    if background.name != "background" {
+        return Err(ThemeImportError::InvalidMetadata(format!(
+            "root element is <{}>, expected <background>",
+            background.name
+        ))
+        .into());
+    }
+
+    let start_time = background
+        .child("starttime")
+        .ok_or_else(|| ThemeImportError::MissingEntry("<starttime>".to_string()))?;
+    let start = start_time.child_number("hour")? * 3600.0
+        + start_time.child_number("minute")? * 60.0
+        + start_time.child_number("second").unwrap_or_default();
+
+    // Image changes as (image path, seconds since the start of the sequence)
+    let xml_directory = xml_path.parent().unwrap_or(Path::new("."));
+    let mut changes: Vec<(PathBuf, f64)> = Vec::new();
+    let mut elapsed = 0.0;
+    for element in &background.children {
+        let (file, change_at, duration) = match element.name.as_str() {
+            "static" => (
+                largest_file(element, "file")?,
+                elapsed,
+                element.child_number("duration")?,
+            ),
+            "transition" => {
+                let duration = element.child_number("duration")?;
+                (
+                    largest_file(element, "to")?,
+                    elapsed + duration / 2.0,
+                    duration,
+                )
+            }
+            _ => continue,
+        };
+
+        // The same image may be written differently in a transition and a static
+        let path = resolve_image_path(file, xml_directory)?;
+        if changes.last().is_none_or(|(last, _)| *last != path) {
+            changes.push((path, change_at));
+        }
+        elapsed += duration;
+    }
+
+    if changes.is_empty() {
+        return Err(ThemeImportError::NoImages.into());
+    }
+    if (elapsed - SECONDS_PER_DAY).abs() > 1.0 {
+        error!(
+            cycle_seconds = elapsed,
+            "GNOME background does not repeat daily"
+        );
+        return Err(ThemeImportError::InvalidMetadata(format!(
+            "the slideshow repeats every {elapsed} seconds, only daily slideshows can be imported"
+        ))
+        .into());
+    }
+
+    let mut image_paths: Vec<PathBuf> = Vec::new();
+    let mut slots = Vec::with_capacity(changes.len());
+    for (path, change_at) in changes {
+        let index = match image_paths.iter().position(|known| *known == path) {
+            Some(index) => index,
+            None => {
+                image_paths.push(path);
+                image_paths.len() - 1
+            }
+        };
+        let index = u8::try_from(index).map_err(|_| ThemeImportError::TooManyImages(index + 1))?;
+
+        let fraction = ((start + change_at) / SECONDS_PER_DAY).fract();
+        let time = TimeOfDay::from_fraction(fraction)
+            .map_err(|e| ThemeImportError::InvalidMetadata(e.to_string()))?;
+        slots.push(TimeSlot::new(index, time));
+    }
+
+    let mut image_format = None;
+    let mut images = Vec::with_capacity(image_paths.len());
+    for path in image_paths {
+        let format = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension)
+            .ok_or_else(|| ThemeImportError::UnsupportedImageFormat(path.display().to_string()))?;
+        match &image_format {
+            None => image_format = Some(format),
+            Some(expected) if *expected == format => {}
+            Some(_) => return Err(ThemeImportError::MixedImageFormats.into()),
+        }
+        images.push(fs::read(&path)?);
+    }
+    let image_format = image_format.ok_or(ThemeImportError::NoImages)?;
+
+    let theme_name = xml_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(sanitize_file_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "GNOME".to_string());
+
+    let metadata =
+        CustomizedThemeMetadata::new(theme_name, DEFAULT_AUTHOR.to_string(), 1, image_format);
+    ImportedTheme::new(
+        metadata,
+        images,
+        ImportedThemeConfiguration::TimeOfDay(slots),
+    )?
+    .write(customized_themes_directory)
+}
+
+/// Exports the solar theme in `theme_directory` as a GNOME background XML for one day
+///
+/// The sun is followed through `date` at `position`, local time at `offset`, and each
+/// image is shown while the theme would select it. `images_directory` holds the
+/// theme's `<index + 1>.<format>` files, which the XML refers to by absolute path.
+pub fn export_gnome_background(
+    theme_directory: &Path,
+    images_directory: &Path,
+    image_format: &ImageFormat,
+    position: Position,
+    date: Date,
+    offset: Offset,
+    xml_path: &Path,
+) -> DwallResult<()> {
+    let options = SolarPathOptions::new(
+        position,
+        SolarModel::default(),
+        date,
+        date,
+        EXPORT_STEP,
+        offset,
+    )
+    .map_err(|e| ThemeImportError::InvalidMetadata(e.to_string()))?;
+    let table = SolarPathTable::for_theme(theme_directory, &options)?;
+
+    // Consecutive samples showing the same image, as (image index, sample count)
+    let mut runs: Vec<(u8, u64)> = Vec::new();
+    for index in table.rows().iter().filter_map(|row| row.image_index()) {
+        match runs.last_mut() {
+            Some((last, count)) if *last == index => *count += 1,
+            _ => runs.push((index, 1)),
+        }
+    }
+    if runs.is_empty() {
+        return Err(ThemeImportError::NoImages.into());
+    }
+
+    let images_directory = std::path::absolute(images_directory)?;
+    let mut xml = String::from("<background>\n");
+    let _ = write!(
+        xml,
+        "  <starttime>\n    <year>{}</year>\n    <month>{}</month>\n    <day>{}</day>\n    \
+         <hour>0</hour>\n    <minute>0</minute>\n    <second>0</second>\n  </starttime>\n",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    );
+    for (index, count) in &runs {
+        let file = images_directory.join(format!(
+            "{}.{}",
+            u16::from(*index) + 1,
+            image_format.as_str()
+        ));
+        let _ = write!(
+            xml,
+            "  <static>\n    <duration>{:.1}</duration>\n    <file>{}</file>\n  </static>\n",
+            (count * EXPORT_STEP.as_secs()) as f64,
+            escape(file.to_string_lossy())
+        );
+    }
+    xml.push_str("</background>\n");
+
+    fs::write(xml_path, xml)?;
+    info!(
+        theme_directory = %theme_directory.display(),
+        xml_path = %xml_path.display(),
+        images = runs.len(),
+        date = %date,
+        "Exported solar theme as GNOME background"
+    );
+    Ok(())
+}
+
+/// File named by the `name` child of `element`, preferring the largest of several sizes
+///
+/// This is the `<file>` of a `<static>` or the `<to>` of a `<transition>`; either
+/// holds a path or a `<size>` element per resolution.
+fn largest_file<'a>(element: &'a Element, name: &str) -> DwallResult<&'a str> {
+    let missing = || ThemeImportError::MissingEntry(format!("<{name}>"));
+    let file = element.child(name).ok_or_else(missing)?;
+
+    let area = |size: &Element| {
+        let dimension = |name| {
+            size.attributes
+                .get(name)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        dimension("width") * dimension("height")
+    };
+    let file_path = file
+        .children
+        .iter()
+        .filter(|child| child.name == "size")
+        .max_by_key(|size| area(size))
+        .map_or(file.text.trim(), |size| size.text.trim());
+
+    if file_path.is_empty() {
+        return Err(missing().into());
+    }
+    Ok(file_path)
+}
+
+fn resolve_image_path(file: &str, xml_directory: &Path) -> DwallResult<PathBuf> {
+    let path = Path::new(file);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+
+    path.file_name()
+        .map(|file_name| xml_directory.join(file_name))
+        .filter(|candidate| candidate.is_file())
+        .ok_or_else(|| {
+            error!(file = file, "Image of GNOME background not found");
+            ThemeImportError::MissingEntry(file.to_string()).into()
+        })
+}
+
+/// Parses `content` into its root element
+fn parse_xml(content: &str) -> DwallResult<Element> {
+    let invalid = |e: &dyn std::fmt::Display| ThemeImportError::InvalidMetadata(e.to_string());
+
+    let mut reader = quick_xml::Reader::from_str(content);
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let event = reader.read_event().map_err(|e| invalid(&e))?;
+        let is_empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(start) | Event::Empty(start) => {
+                let mut element = Element {
+                    name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+                    ..Default::default()
+                };
+                for attribute in start.attributes() {
+                    let attribute = attribute.map_err(|e| invalid(&e))?;
+                    element.attributes.insert(
+                        String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                        attribute
+                            .unescape_value()
+                            .map_err(|e| invalid(&e))?
+                            .into_owned(),
+                    );
+                }
+
+                if is_empty {
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                } else {
+                    stack.push(element);
+                }
+            }
+            Event::End(_) => {
+                let element = stack
+                    .pop()
+                    .ok_or_else(|| invalid(&"unexpected closing tag"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&text.xml_content().map_err(|e| invalid(&e))?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&data.decode().map_err(|e| invalid(&e))?);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                let Some(element) = stack.last_mut() else {
+                    continue;
+                };
+                if let Some(character) = reference.resolve_char_ref().map_err(|e| invalid(&e))? {
+                    element.text.push(character);
+                } else {
+                    let name = reference.decode().map_err(|e| invalid(&e))?;
+                    let resolved = resolve_predefined_entity(&name)
+                        .ok_or_else(|| invalid(&format!("unknown entity '&{name};'")))?;
+                    element.text.push_str(resolved);
+                }
+            }
+            Event::Eof => return Err(invalid(&"unexpected end of document").into()),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{png_image, scratch_directory};
+
+    /// Reads the time slots of an imported theme as (index, seconds since midnight)
+    fn read_slots(theme_directory: &Path) -> Vec<(u8, u32)> {
+        let slots: Vec<TimeSlot> =
+            serde_json::from_str(&fs::read_to_string(theme_directory.join("time.json")).unwrap())
+                .unwrap();
+        slots
+            .iter()
+            .map(|slot| (slot.index(), slot.time().seconds_since_midnight()))
+            .collect()
+    }
+
+    #[test]
+    fn imports_daily_slideshow() {
+        let directory = scratch_directory("gnome-import");
+        fs::write(directory.join("day.png"), png_image([255, 255, 0])).unwrap();
+        fs::write(directory.join("night.png"), png_image([0, 0, 64])).unwrap();
+        // Paths that do not exist as written are found next to the XML
+        let xml_path = directory.join("Fields.xml");
+        fs::write(
+            &xml_path,
+            r#"<background>
+  <starttime>
+    <year>2024</year><month>1</month><day>1</day>
+    <hour>6</hour><minute>0</minute><second>0</second>
+  </starttime>
+  <static>
+    <duration>42300.0</duration>
+    <file>/usr/share/backgrounds/fields/day.png</file>
+  </static>
+  <transition type="overlay">
+    <duration>900.0</duration>
+    <from>/usr/share/backgrounds/fields/day.png</from>
+    <to>
+      <size width="1920" height="1080">/usr/share/backgrounds/fields/night-small.png</size>
+      <size width="3840" height="2160">/usr/share/backgrounds/fields/night.png</size>
+    </to>
+  </transition>
+  <static>
+    <duration>42300.0</duration>
+    <file>
+      <size width="3840" height="2160">night.png</size>
+    </file>
+  </static>
+  <transition>
+    <duration>900.0</duration>
+    <from>night.png</from>
+    <to>day.png</to>
+  </transition>
+</background>
+"#,
+        )
+        .unwrap();
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_gnome_background(&xml_path, &themes_directory).unwrap();
+        assert_eq!(theme_id, "Fields-GNOME-v1");
+
+        let theme_directory = themes_directory.join(&theme_id);
+        // Each image starts halfway through the transition that fades it in
+        assert_eq!(
+            read_slots(&theme_directory),
+            [
+                (0, 6 * 3600),
+                (1, 17 * 3600 + 52 * 60 + 30),
+                (0, 5 * 3600 + 52 * 60 + 30)
+            ]
+        );
+        assert_eq!(
+            fs::read(theme_directory.join("images/2.png")).unwrap(),
+            png_image([0, 0, 64])
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_slideshows_that_do_not_repeat_daily() {
+        let directory = scratch_directory("gnome-cycle");
+        fs::write(directory.join("day.png"), png_image([255, 255, 0])).unwrap();
+        let xml_path = directory.join("Short.xml");
+        fs::write(
+            &xml_path,
+            "<background><starttime><hour>0</hour><minute>0</minute></starttime>\
+             <static><duration>3600</duration><file>day.png</file></static></background>",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            import_gnome_background(&xml_path, &directory.join("themes")),
+            Err(crate::error::DwallError::ThemeImport(
+                ThemeImportError::InvalidMetadata(_)
+            ))
+        ));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn exported_background_imports_back() {
+        let directory = scratch_directory("gnome-export");
+        let theme_directory = directory.join("Solar");
+        let images_directory = theme_directory.join("images");
+        fs::create_dir_all(&images_directory).unwrap();
+        fs::write(images_directory.join("1.png"), png_image([0, 0, 64])).unwrap();
+        fs::write(images_directory.join("2.png"), png_image([255, 255, 0])).unwrap();
+        fs::write(
+            theme_directory.join("solar.json"),
+            r#"[{"index":0,"altitude":-30.0,"azimuth":0.0},{"index":1,"altitude":30.0,"azimuth":180.0}]"#,
+        )
+        .unwrap();
+
+        let xml_path = directory.join("Solar.xml");
+        export_gnome_background(
+            &theme_directory,
+            &images_directory,
+            &ImageFormat::Png,
+            Position::new(51.5, -0.12, 0.0).unwrap(),
+            "2024-06-21".parse().unwrap(),
+            "+01:00".parse().unwrap(),
+            &xml_path,
+        )
+        .unwrap();
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_gnome_background(&xml_path, &themes_directory).unwrap();
+        let imported_directory = themes_directory.join(&theme_id);
+
+        // Night from midnight, day from around sunrise, night again from around sunset
+        let slots = read_slots(&imported_directory);
+        let indices: Vec<u8> = slots.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, [0, 1, 0]);
+        assert_eq!(slots[0].1, 0);
+        assert!((4 * 3600..7 * 3600).contains(&slots[1].1), "{slots:?}");
+        assert!((19 * 3600..23 * 3600).contains(&slots[2].1), "{slots:?}");
+
+        for name in ["1.png", "2.png"] {
+            assert_eq!(
+                fs::read(imported_directory.join("images").join(name)).unwrap(),
+                fs::read(images_directory.join(name)).unwrap()
+            );
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn exports_the_last_image_index() {
+        let directory = scratch_directory("gnome-export-last-index");
+        let theme_directory = directory.join("Solar");
+        let images_directory = theme_directory.join("images");
+        fs::create_dir_all(&images_directory).unwrap();
+        fs::write(
+            theme_directory.join("solar.json"),
+            r#"[{"index":0,"altitude":-30.0,"azimuth":0.0},{"index":255,"altitude":30.0,"azimuth":180.0}]"#,
+        )
+        .unwrap();
+
+        let xml_path = directory.join("Solar.xml");
+        export_gnome_background(
+            &theme_directory,
+            &images_directory,
+            &ImageFormat::Png,
+            Position::new(51.5, -0.12, 0.0).unwrap(),
+            "2024-06-21".parse().unwrap(),
+            "+01:00".parse().unwrap(),
+            &xml_path,
+        )
+        .unwrap();
+
+        let xml = fs::read_to_string(&xml_path).unwrap();
+        let day_image = std::path::absolute(images_directory.join("256.png")).unwrap();
+        assert!(xml.contains(&*escape(day_image.to_string_lossy())), "{xml}");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
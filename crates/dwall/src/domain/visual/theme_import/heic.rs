@@ -0,0 +1,487 @@
+//! macOS dynamic HEIC wallpapers
+//!
+//! A dynamic `.heic` keeps its sun positions in the `apple_desktop:solar` XMP property,
+//! a base64 encoded binary property list. Its `si` array holds one entry per sun
+//! position, with the altitude (`a`), the azimuth (`z`) and the index of the image in
+//! the HEIC container (`i`).
+//!
+//! Decoding HEIC images is left to other tools; a theme is built from frames that were
+//! already extracted, in container order.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+
+use crate::{SolarAngle, config::ImageFormat, domain::visual::CustomizedThemeMetadata};
+
+use super::{
+    DwallResult, ImportedTheme, ImportedThemeConfiguration, ThemeImportError, sanitize_file_name,
+};
+
+const SOLAR_PROPERTY: &[u8] = b"apple_desktop:solar";
+
+/// Author recorded for imported themes; the container does not name one
+const DEFAULT_AUTHOR: &str = "macOS";
+
+#[derive(Debug, Deserialize)]
+struct SolarMetadata {
+    #[serde(rename = "si")]
+    solar_images: Vec<SolarImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SolarImage {
+    #[serde(rename = "i")]
+    index: u32,
+    #[serde(rename = "a")]
+    altitude: f64,
+    #[serde(rename = "z")]
+    azimuth: f64,
+}
+
+/// Reads the sun positions of the dynamic wallpaper at `heic_path`
+///
+/// The result is ordered by image index and can be written as a theme's `solar.json`.
+/// Metadata listing an image twice, or skipping one, is rejected.
+pub fn read_heic_solar_angles(heic_path: &Path) -> DwallResult<Vec<SolarAngle>> {
+    let content = fs::read(heic_path).inspect_err(|e| {
+        error!(
+            heic_path = %heic_path.display(),
+            error = %e,
+            "Failed to read HEIC file"
+        );
+    })?;
+
+    let encoded = find_xmp_property(&content, SOLAR_PROPERTY).ok_or_else(|| {
+        error!(
+            heic_path = %heic_path.display(),
+            "HEIC file has no solar metadata"
+        );
+        ThemeImportError::MissingEntry("apple_desktop:solar".to_string())
+    })?;
+    // The element form may wrap the value over several lines
+    let encoded: Vec<u8> = encoded
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    let plist = STANDARD
+        .decode(encoded)
+        .map_err(|e| ThemeImportError::InvalidMetadata(e.to_string()))?;
+    let metadata: SolarMetadata =
+        plist::from_bytes(&plist).map_err(|e| ThemeImportError::InvalidMetadata(e.to_string()))?;
+    debug!(metadata = ?metadata, "Parsed HEIC solar metadata");
+
+    let mut angles = metadata
+        .solar_images
+        .into_iter()
+        .map(|image| {
+            let index = u8::try_from(image.index)
+                .map_err(|_| ThemeImportError::TooManyImages(image.index as usize + 1))?;
+            Ok(SolarAngle::new(
+                index,
+                image.altitude,
+                image.azimuth.rem_euclid(360.0),
+            ))
+        })
+        .collect::<DwallResult<Vec<_>>>()?;
+    angles.sort_by_key(|angle| angle.index());
+
+    if angles.is_empty() {
+        return Err(ThemeImportError::NoImages.into());
+    }
+    // Frames are numbered by index, so every index from 0 must appear exactly once
+    for (expected, angle) in angles.iter().enumerate() {
+        let index = usize::from(angle.index());
+        if index != expected {
+            let error = if index < expected {
+                ThemeImportError::DuplicateImageIndex(angle.index())
+            } else {
+                // Below an index that fits in u8, so it does too
+                ThemeImportError::ImageIndexGap(expected as u8)
+            };
+            error!(
+                heic_path = %heic_path.display(),
+                error = %error,
+                "Invalid image indices in HEIC solar metadata"
+            );
+            return Err(error.into());
+        }
+    }
+
+    info!(
+        heic_path = %heic_path.display(),
+        sun_positions = angles.len(),
+        "Read solar metadata from HEIC file"
+    );
+    Ok(angles)
+}
+
+/// Writes the sun positions of the wallpaper at `heic_path` as `solar_config_path`
+pub fn write_heic_solar_config(heic_path: &Path, solar_config_path: &Path) -> DwallResult<()> {
+    let angles = read_heic_solar_angles(heic_path)?;
+    fs::write(solar_config_path, serde_json::to_string_pretty(&angles)?)?;
+
+    Ok(())
+}
+
+/// Imports the wallpaper at `heic_path` as a customized theme, using the frames
+/// extracted from it into `frames_directory`
+///
+/// Frames are matched to image indices in the order of the number in their file names,
+/// as written by common HEIC extraction tools; frames past the last image the solar
+/// metadata references are left out. Returns the ID of the new theme.
+pub fn import_heic_theme(
+    heic_path: &Path,
+    frames_directory: &Path,
+    customized_themes_directory: &Path,
+) -> DwallResult<String> {
+    let angles = read_heic_solar_angles(heic_path)?;
+
+    let (frames, image_format) = list_frames(frames_directory)?;
+    // Indices run from 0 without gaps, so the sun positions reference the first frames
+    if let Some(index) = angles
+        .iter()
+        .map(SolarAngle::index)
+        .find(|index| usize::from(*index) >= frames.len())
+    {
+        error!(
+            frames = frames.len(),
+            index = index,
+            frames_directory = %frames_directory.display(),
+            "Fewer extracted frames than images referenced by the solar metadata"
+        );
+        return Err(ThemeImportError::MissingFrame {
+            index,
+            frames_directory: frames_directory.to_path_buf(),
+        }
+        .into());
+    }
+    if frames.len() > angles.len() {
+        warn!(
+            frames = frames.len(),
+            referenced = angles.len(),
+            "Skipping extracted frames the solar metadata does not reference"
+        );
+    }
+
+    let images = frames
+        .iter()
+        .take(angles.len())
+        .map(fs::read)
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let theme_name = heic_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(sanitize_file_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Dynamic".to_string());
+
+    let metadata =
+        CustomizedThemeMetadata::new(theme_name, DEFAULT_AUTHOR.to_string(), 1, image_format);
+    ImportedTheme::new(metadata, images, ImportedThemeConfiguration::Solar(angles))?
+        .write(customized_themes_directory)
+}
+
+/// Lists the extracted frames in container order, with their common image format
+fn list_frames(frames_directory: &Path) -> DwallResult<(Vec<PathBuf>, ImageFormat)> {
+    let mut image_format = None;
+    let mut frames = Vec::new();
+    for entry in fs::read_dir(frames_directory)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let Some(format) = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension)
+        else {
+            continue;
+        };
+        match &image_format {
+            None => image_format = Some(format),
+            Some(expected) if *expected == format => {}
+            Some(_) => {
+                error!(
+                    frame = %path.display(),
+                    "Extracted frames mix image formats"
+                );
+                return Err(ThemeImportError::MixedImageFormats.into());
+            }
+        }
+        frames.push(path);
+    }
+
+    frames.sort_by_cached_key(|path| {
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_string();
+        (trailing_number(&stem), stem)
+    });
+
+    let image_format = image_format.ok_or(ThemeImportError::NoImages)?;
+    Ok((frames, image_format))
+}
+
+/// Number at the end of `stem`, such as 12 in `mojave-12`
+fn trailing_number(stem: &str) -> Option<u32> {
+    let digits_start = stem
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |position| position + 1);
+    stem[digits_start..].parse().ok()
+}
+
+/// Finds the value of the XMP property `name` anywhere in `content`
+///
+/// XMP packets are stored uncompressed, so the bytes can be searched directly. Both the
+/// attribute form (`name="value"`) and the element form (`<name>value</name>`) are
+/// accepted.
+fn find_xmp_property<'a>(content: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let mut rest = content;
+    while let Some(position) = rest.windows(name.len()).position(|window| window == name) {
+        let after_name = &rest[position + name.len()..];
+        let value = trim_start(after_name);
+
+        let found = match value.first() {
+            Some(b'=') => {
+                let value = trim_start(&value[1..]);
+                // An unquoted value is not the property; keep looking
+                let Some(&quote) = value.first().filter(|q| matches!(q, b'"' | b'\'')) else {
+                    rest = after_name;
+                    continue;
+                };
+                let value = &value[1..];
+                value
+                    .iter()
+                    .position(|&byte| byte == quote)
+                    .map(|end| &value[..end])
+            }
+            Some(b'>') => {
+                let value = &value[1..];
+                value
+                    .iter()
+                    .position(|&byte| byte == b'<')
+                    .map(|end| &value[..end])
+            }
+            _ => None,
+        };
+        if let Some(found) = found {
+            let found = trim_start(found);
+            let end = found
+                .iter()
+                .rposition(|byte| !byte.is_ascii_whitespace())
+                .map_or(0, |position| position + 1);
+            return Some(&found[..end]);
+        }
+
+        rest = after_name;
+    }
+
+    None
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::DwallError,
+        utils::testing::{png_image, scratch_directory},
+    };
+
+    /// Bytes standing in for a HEIC file, with the sun positions as (index, altitude,
+    /// azimuth) in an XMP attribute
+    fn heic_with_solar_metadata(positions: &[(u64, f64, f64)]) -> Vec<u8> {
+        let solar_images = positions
+            .iter()
+            .map(|&(index, altitude, azimuth)| {
+                plist::Value::Dictionary(plist::Dictionary::from_iter([
+                    ("i".to_string(), plist::Value::from(index)),
+                    ("a".to_string(), plist::Value::from(altitude)),
+                    ("z".to_string(), plist::Value::from(azimuth)),
+                ]))
+            })
+            .collect();
+        let metadata = plist::Value::Dictionary(plist::Dictionary::from_iter([(
+            "si".to_string(),
+            plist::Value::Array(solar_images),
+        )]));
+        let mut plist = Vec::new();
+        metadata.to_writer_binary(&mut plist).unwrap();
+
+        let mut content =
+            b"\0\0\0\x18ftypheic\0\0\0\0mif1heic<x:xmpmeta><rdf:Description ".to_vec();
+        content.extend_from_slice(b"apple_desktop:solar=\"");
+        content.extend_from_slice(STANDARD.encode(plist).as_bytes());
+        content.extend_from_slice(b"\"/></x:xmpmeta>\0\xff");
+        content
+    }
+
+    #[test]
+    fn finds_xmp_property_in_both_forms() {
+        let name = b"apple_desktop:solar";
+        assert_eq!(
+            find_xmp_property(b"<rdf apple_desktop:solar = 'YmFzZTY0' />", name),
+            Some(&b"YmFzZTY0"[..])
+        );
+        assert_eq!(
+            find_xmp_property(
+                b"<apple_desktop:solar>\n  YmFz\n  ZTY0\n</apple_desktop:solar>",
+                name
+            ),
+            Some(&b"YmFz\n  ZTY0"[..])
+        );
+        // A namespace declaration or unquoted value is skipped for the real property
+        assert_eq!(
+            find_xmp_property(
+                b"apple_desktop:solar=x apple_desktop:solar=\"dmFsdWU=\"",
+                name
+            ),
+            Some(&b"dmFsdWU="[..])
+        );
+        assert_eq!(find_xmp_property(b"apple_desktop:other=\"x\"", name), None);
+    }
+
+    #[test]
+    fn reads_solar_metadata_ordered_by_index() {
+        let directory = scratch_directory("heic-solar");
+        let heic_path = directory.join("mojave.heic");
+        fs::write(
+            &heic_path,
+            heic_with_solar_metadata(&[(1, 45.5, 180.0), (0, -10.0, -90.0), (2, 5.25, 270.0)]),
+        )
+        .unwrap();
+
+        let angles = read_heic_solar_angles(&heic_path).unwrap();
+        let positions: Vec<_> = angles
+            .iter()
+            .map(|angle| (angle.index(), angle.altitude(), angle.azimuth()))
+            .collect();
+        assert_eq!(
+            positions,
+            [(0, -10.0, 270.0), (1, 45.5, 180.0), (2, 5.25, 270.0)]
+        );
+
+        let solar_config_path = directory.join("solar.json");
+        write_heic_solar_config(&heic_path, &solar_config_path).unwrap();
+        let written: Vec<SolarAngle> =
+            serde_json::from_str(&fs::read_to_string(&solar_config_path).unwrap()).unwrap();
+        assert_eq!(written.len(), 3);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_duplicate_and_missing_indices() {
+        let directory = scratch_directory("heic-indices");
+        let heic_path = directory.join("mojave.heic");
+
+        fs::write(
+            &heic_path,
+            heic_with_solar_metadata(&[(0, -10.0, 90.0), (1, 20.0, 120.0), (1, 30.0, 150.0)]),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_heic_solar_angles(&heic_path),
+            Err(DwallError::ThemeImport(
+                ThemeImportError::DuplicateImageIndex(1)
+            ))
+        ));
+
+        fs::write(
+            &heic_path,
+            heic_with_solar_metadata(&[(0, -10.0, 90.0), (2, 30.0, 150.0)]),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_heic_solar_angles(&heic_path),
+            Err(DwallError::ThemeImport(ThemeImportError::ImageIndexGap(1)))
+        ));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn imports_referenced_frames_in_container_order() {
+        let directory = scratch_directory("heic-import");
+        let heic_path = directory.join("Mojave.heic");
+        fs::write(
+            &heic_path,
+            heic_with_solar_metadata(&[(0, -10.0, 90.0), (1, 40.0, 180.0)]),
+        )
+        .unwrap();
+
+        let frames_directory = directory.join("frames");
+        fs::create_dir_all(&frames_directory).unwrap();
+        // Numbered frames sort by number, not by name
+        let frames = [
+            ("mojave-10.png", [0, 0, 255]),
+            ("mojave-2.png", [0, 255, 0]),
+            ("mojave-1.png", [255, 0, 0]),
+        ];
+        for (name, color) in frames {
+            fs::write(frames_directory.join(name), png_image(color)).unwrap();
+        }
+        fs::write(frames_directory.join("notes.txt"), b"not a frame").unwrap();
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_heic_theme(&heic_path, &frames_directory, &themes_directory).unwrap();
+        assert_eq!(theme_id, "Mojave-macOS-v1");
+
+        let images_directory = themes_directory.join(&theme_id).join("images");
+        assert_eq!(
+            fs::read(images_directory.join("1.png")).unwrap(),
+            png_image([255, 0, 0])
+        );
+        assert_eq!(
+            fs::read(images_directory.join("2.png")).unwrap(),
+            png_image([0, 255, 0])
+        );
+        // The third frame is not referenced by the solar metadata
+        assert!(!images_directory.join("3.png").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_frames() {
+        let directory = scratch_directory("heic-frames");
+        let heic_path = directory.join("Mojave.heic");
+        fs::write(
+            &heic_path,
+            heic_with_solar_metadata(&[(0, -10.0, 90.0), (1, 40.0, 180.0)]),
+        )
+        .unwrap();
+        let frames_directory = directory.join("frames");
+        fs::create_dir_all(&frames_directory).unwrap();
+        fs::write(frames_directory.join("mojave-1.png"), png_image([0, 0, 0])).unwrap();
+
+        let themes_directory = directory.join("themes");
+        assert!(matches!(
+            import_heic_theme(&heic_path, &frames_directory, &themes_directory),
+            Err(DwallError::ThemeImport(ThemeImportError::MissingFrame {
+                index: 1,
+                ..
+            }))
+        ));
+        assert!(!themes_directory.join("Mojave-macOS-v1").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
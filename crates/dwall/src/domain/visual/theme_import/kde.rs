@@ -0,0 +1,562 @@
+//! KDE dynamic wallpaper packages
+//!
+//! The KDE dynamic wallpaper plugin stores a wallpaper as a package: a
+//! `metadata.json` with the plugin information and a `Meta` list of images, and the
+//! image files it names. A `solar` package keys each image to the sun's `Elevation`
+//! and `Azimuth`, a `timed` package to a `Time` given as a fraction of the day. Solar
+//! packages carry a `Time` as well, used where the location is unknown.
+//!
+//! Packages are read from an unpacked directory or a zip archive. Solar and
+//! time-of-day themes can be exported back; moon-keyed images have no equivalent
+//! and are left out.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SolarAngle,
+    config::ImageFormat,
+    domain::visual::{CustomizedThemeMetadata, TimeOfDay, TimeSlot},
+};
+
+use super::{
+    DwallResult, ImportedTheme, ImportedThemeConfiguration, ThemeImportError, read_package_entry,
+    sanitize_file_name,
+};
+
+const PACKAGE_METADATA_FILENAME: &str = "metadata.json";
+
+/// Directory of the images inside a package
+const PACKAGE_IMAGES_DIRECTORY: &str = "contents/images";
+
+const SECONDS_PER_DAY: f64 = 24.0 * 3600.0;
+
+/// Author recorded for packages whose metadata credits nobody
+const DEFAULT_AUTHOR: &str = "KDE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum KdeWallpaperType {
+    Solar,
+    Timed,
+}
+
+/// Content of a package's `metadata.json`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct KdeMetadata {
+    #[serde(rename = "KPlugin", default)]
+    plugin: KdePlugin,
+    #[serde(rename = "Type")]
+    kind: KdeWallpaperType,
+    meta: Vec<KdeImage>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct KdePlugin {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<KdeAuthor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct KdeAuthor {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct KdeImage {
+    /// Path relative to the package root
+    file_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    elevation: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    azimuth: Option<f64>,
+    /// Fraction of the day, from midnight
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time: Option<f64>,
+    #[serde(default = "default_cross_fade")]
+    cross_fade: bool,
+}
+
+fn default_cross_fade() -> bool {
+    true
+}
+
+/// Files of an unpacked package, or of a package zip archive
+enum PackageSource {
+    Directory(PathBuf),
+    Archive {
+        archive: zip::ZipArchive<BufReader<File>>,
+        /// Entry names by their path below the package root
+        entries: HashMap<String, String>,
+    },
+}
+
+impl PackageSource {
+    fn open(package_path: &Path) -> DwallResult<Self> {
+        if package_path.is_dir() {
+            return Ok(Self::Directory(package_path.to_path_buf()));
+        }
+
+        let file = File::open(package_path).inspect_err(|e| {
+            error!(
+                package_path = %package_path.display(),
+                error = %e,
+                "Failed to open KDE wallpaper package"
+            );
+        })?;
+        let archive = zip::ZipArchive::new(BufReader::new(file)).map_err(ThemeImportError::from)?;
+
+        // Archives usually wrap the package in a folder named after it
+        let root = archive
+            .file_names()
+            .filter(|name| name.rsplit('/').next() == Some(PACKAGE_METADATA_FILENAME))
+            .min_by_key(|name| name.len())
+            .map(|name| name[..name.len() - PACKAGE_METADATA_FILENAME.len()].to_string())
+            .ok_or_else(|| ThemeImportError::MissingEntry(PACKAGE_METADATA_FILENAME.to_string()))?;
+        let entries = archive
+            .file_names()
+            .filter_map(|name| {
+                name.strip_prefix(&root)
+                    .map(|relative| (relative.to_string(), name.to_string()))
+            })
+            .collect();
+
+        Ok(Self::Archive { archive, entries })
+    }
+
+    /// Reads the file at `relative_path` below the package root
+    fn read(&mut self, relative_path: &str) -> DwallResult<Vec<u8>> {
+        let relative_path = relative_path.trim_start_matches("./");
+        let missing = || {
+            error!(
+                file_name = relative_path,
+                "KDE wallpaper package entry not found"
+            );
+            ThemeImportError::MissingEntry(relative_path.to_string())
+        };
+
+        // Paths come from the package's metadata.json and must stay inside the package
+        if !Path::new(relative_path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            error!(
+                file_name = relative_path,
+                "KDE wallpaper package path leaves the package"
+            );
+            return Err(ThemeImportError::UnsafePath(relative_path.to_string()).into());
+        }
+
+        match self {
+            Self::Directory(root) => {
+                let path = root.join(relative_path);
+                if !path.is_file() {
+                    return Err(missing().into());
+                }
+                Ok(fs::read(path)?)
+            }
+            Self::Archive { archive, entries } => {
+                let entry_name = entries.get(relative_path).ok_or_else(missing)?;
+                let entry = archive
+                    .by_name(entry_name)
+                    .map_err(ThemeImportError::from)?;
+                read_package_entry(entry_name, entry)
+            }
+        }
+    }
+}
+
+/// Imports the KDE dynamic wallpaper package at `package_path`, a directory or a zip
+/// archive, as a customized theme
+///
+/// Returns the ID of the new theme.
+pub fn import_kde_wallpaper(
+    package_path: &Path,
+    customized_themes_directory: &Path,
+) -> DwallResult<String> {
+    info!(package_path = %package_path.display(), "Importing KDE dynamic wallpaper");
+
+    let mut source = PackageSource::open(package_path)?;
+    let metadata: KdeMetadata = serde_json::from_slice(&source.read(PACKAGE_METADATA_FILENAME)?)
+        .inspect_err(|e| error!(error = %e, "Failed to parse KDE wallpaper metadata"))?;
+    debug!(metadata = ?metadata, "Parsed KDE wallpaper metadata");
+
+    let mut image_format = None;
+    let mut file_names: Vec<&str> = Vec::new();
+    let mut angles = Vec::with_capacity(metadata.meta.len());
+    let mut slots = Vec::with_capacity(metadata.meta.len());
+    for image in &metadata.meta {
+        // The same image may be named with and without a leading `./`
+        let file_name = image.file_name.trim_start_matches("./");
+        let format = Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension)
+            .ok_or_else(|| ThemeImportError::UnsupportedImageFormat(image.file_name.clone()))?;
+        match &image_format {
+            None => image_format = Some(format),
+            Some(expected) if *expected == format => {}
+            Some(_) => return Err(ThemeImportError::MixedImageFormats.into()),
+        }
+
+        let index = match file_names.iter().position(|name| *name == file_name) {
+            Some(index) => index,
+            None => {
+                file_names.push(file_name);
+                file_names.len() - 1
+            }
+        };
+        let index = u8::try_from(index).map_err(|_| ThemeImportError::TooManyImages(index + 1))?;
+
+        match metadata.kind {
+            KdeWallpaperType::Solar => {
+                let (Some(elevation), Some(azimuth)) = (image.elevation, image.azimuth) else {
+                    return Err(ThemeImportError::InvalidMetadata(format!(
+                        "solar image '{}' has no elevation or azimuth",
+                        image.file_name
+                    ))
+                    .into());
+                };
+                angles.push(SolarAngle::new(index, elevation, azimuth.rem_euclid(360.0)));
+            }
+            KdeWallpaperType::Timed => {
+                let time = image.time.ok_or_else(|| {
+                    ThemeImportError::InvalidMetadata(format!(
+                        "timed image '{}' has no time",
+                        image.file_name
+                    ))
+                })?;
+                let time = TimeOfDay::from_fraction(time)
+                    .map_err(|e| ThemeImportError::InvalidMetadata(e.to_string()))?;
+                slots.push(TimeSlot::new(index, time));
+            }
+        }
+    }
+    let image_format = image_format.ok_or(ThemeImportError::NoImages)?;
+
+    let images = file_names
+        .iter()
+        .map(|file_name| source.read(file_name))
+        .collect::<DwallResult<Vec<_>>>()?;
+
+    let theme_name = metadata
+        .plugin
+        .name
+        .as_deref()
+        .or(metadata.plugin.id.as_deref())
+        .or_else(|| package_path.file_stem().and_then(|stem| stem.to_str()))
+        .map(sanitize_file_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "KDE".to_string());
+    let author = metadata
+        .plugin
+        .authors
+        .iter()
+        .map(|author| sanitize_file_name(&author.name))
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_AUTHOR.to_string());
+
+    let configuration = match metadata.kind {
+        KdeWallpaperType::Solar => ImportedThemeConfiguration::Solar(angles),
+        KdeWallpaperType::Timed => ImportedThemeConfiguration::TimeOfDay(slots),
+    };
+    let metadata = CustomizedThemeMetadata::new(theme_name, author, 1, image_format)
+        .with_license(metadata.plugin.license);
+    ImportedTheme::new(metadata, images, configuration)?.write(customized_themes_directory)
+}
+
+/// Exports the theme in `theme_directory` as an unpacked KDE dynamic wallpaper package
+///
+/// Solar themes become `solar` packages and time-of-day themes `timed` packages; other
+/// themes have no KDE equivalent. Images are read from `images_directory`, which holds
+/// the theme's `<index + 1>.<format>` files. A solar image's `Time` is where the
+/// reference sun, due south at noon, reaches its azimuth.
+pub fn export_kde_wallpaper(
+    theme_directory: &Path,
+    images_directory: &Path,
+    image_format: &ImageFormat,
+    theme_name: &str,
+    package_directory: &Path,
+) -> DwallResult<()> {
+    let image_file = |index: u8| format!("{}.{}", u16::from(index) + 1, image_format.as_str());
+    let file_name = |index: u8| format!("{PACKAGE_IMAGES_DIRECTORY}/{}", image_file(index));
+
+    let solar_config_path = theme_directory.join("solar.json");
+    let time_config_path = theme_directory.join("time.json");
+    let (kind, indices, meta): (_, Vec<u8>, _) = if solar_config_path.is_file() {
+        let angles: Vec<SolarAngle> =
+            serde_json::from_str(&fs::read_to_string(solar_config_path)?)?;
+        let angles: Vec<&SolarAngle> = angles
+            .iter()
+            .filter(|angle| angle.moon_phase().is_none())
+            .collect();
+        let meta = angles
+            .iter()
+            .map(|angle| KdeImage {
+                file_name: file_name(angle.index()),
+                elevation: Some(angle.altitude()),
+                azimuth: Some(angle.azimuth()),
+                time: Some(angle.azimuth().rem_euclid(360.0) / 360.0),
+                cross_fade: true,
+            })
+            .collect();
+        let indices = angles.iter().map(|angle| angle.index()).collect();
+        (KdeWallpaperType::Solar, indices, meta)
+    } else if time_config_path.is_file() {
+        let slots: Vec<TimeSlot> = serde_json::from_str(&fs::read_to_string(time_config_path)?)?;
+        let meta = slots
+            .iter()
+            .map(|slot| KdeImage {
+                file_name: file_name(slot.index()),
+                elevation: None,
+                azimuth: None,
+                time: Some(f64::from(slot.time().seconds_since_midnight()) / SECONDS_PER_DAY),
+                cross_fade: true,
+            })
+            .collect();
+        let indices = slots.iter().map(TimeSlot::index).collect();
+        (KdeWallpaperType::Timed, indices, meta)
+    } else {
+        error!(
+            theme_directory = %theme_directory.display(),
+            "Only solar and time-of-day themes can be exported as KDE wallpapers"
+        );
+        return Err(ThemeImportError::MissingEntry("solar.json or time.json".to_string()).into());
+    };
+
+    let metadata = KdeMetadata {
+        plugin: KdePlugin {
+            id: Some(theme_name.replace(' ', "-")),
+            name: Some(theme_name.to_string()),
+            authors: Vec::new(),
+            license: None,
+        },
+        kind,
+        meta,
+    };
+    if metadata.meta.is_empty() {
+        return Err(ThemeImportError::NoImages.into());
+    }
+
+    let package_images_directory = package_directory.join(PACKAGE_IMAGES_DIRECTORY);
+    fs::create_dir_all(&package_images_directory)?;
+    let mut copied: Vec<u8> = Vec::new();
+    for index in indices {
+        if copied.contains(&index) {
+            continue;
+        }
+        fs::copy(
+            images_directory.join(image_file(index)),
+            package_images_directory.join(image_file(index)),
+        )?;
+        copied.push(index);
+    }
+
+    fs::write(
+        package_directory.join(PACKAGE_METADATA_FILENAME),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+
+    info!(
+        theme_directory = %theme_directory.display(),
+        package_directory = %package_directory.display(),
+        images = copied.len(),
+        "Exported theme as KDE dynamic wallpaper"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::DwallError,
+        utils::testing::{png_image, scratch_directory, write_zip},
+    };
+
+    const SOLAR_METADATA: &str = r#"{
+        "KPlugin": {
+            "Id": "lakeside",
+            "Name": "Lakeside",
+            "Authors": [{ "Name": "Jane" }],
+            "License": "CC-BY-SA-4.0"
+        },
+        "Type": "solar",
+        "Meta": [
+            { "FileName": "contents/images/night.png", "Elevation": -20, "Azimuth": -10, "Time": 0.0 },
+            { "FileName": "contents/images/day.png", "Elevation": 40, "Azimuth": 180, "Time": 0.5 },
+            { "FileName": "./contents/images/night.png", "Elevation": -25, "Azimuth": 330, "Time": 0.9 }
+        ]
+    }"#;
+
+    fn read_angles(theme_directory: &Path) -> Vec<(u8, f64, f64)> {
+        let angles: Vec<SolarAngle> =
+            serde_json::from_str(&fs::read_to_string(theme_directory.join("solar.json")).unwrap())
+                .unwrap();
+        angles
+            .iter()
+            .map(|angle| (angle.index(), angle.altitude(), angle.azimuth()))
+            .collect()
+    }
+
+    #[test]
+    fn imports_solar_package_directory() {
+        let directory = scratch_directory("kde-directory");
+        let package_directory = directory.join("lakeside");
+        fs::create_dir_all(package_directory.join(PACKAGE_IMAGES_DIRECTORY)).unwrap();
+        fs::write(
+            package_directory.join(PACKAGE_METADATA_FILENAME),
+            SOLAR_METADATA,
+        )
+        .unwrap();
+        let images_directory = package_directory.join(PACKAGE_IMAGES_DIRECTORY);
+        fs::write(images_directory.join("night.png"), png_image([0, 0, 64])).unwrap();
+        fs::write(images_directory.join("day.png"), png_image([255, 255, 0])).unwrap();
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_kde_wallpaper(&package_directory, &themes_directory).unwrap();
+        assert_eq!(theme_id, "Lakeside-Jane-v1");
+
+        let theme_directory = themes_directory.join(&theme_id);
+        assert_eq!(
+            read_angles(&theme_directory),
+            [(0, -20.0, 350.0), (1, 40.0, 180.0), (0, -25.0, 330.0)]
+        );
+        assert_eq!(
+            fs::read(theme_directory.join("images/2.png")).unwrap(),
+            png_image([255, 255, 0])
+        );
+        assert!(!theme_directory.join("images/3.png").exists());
+        let metadata: CustomizedThemeMetadata =
+            toml::from_str(&fs::read_to_string(theme_directory.join("metadata.toml")).unwrap())
+                .unwrap();
+        assert_eq!(metadata.license(), Some("CC-BY-SA-4.0"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn imports_timed_package_archive() {
+        let directory = scratch_directory("kde-archive");
+        let metadata = r#"{
+            "KPlugin": { "Name": "Harbour" },
+            "Type": "timed",
+            "Meta": [
+                { "FileName": "contents/images/morning.png", "Time": 0.25 },
+                { "FileName": "contents/images/evening.png", "Time": 0.75 }
+            ]
+        }"#;
+        let (morning, evening) = (png_image([255, 128, 0]), png_image([64, 0, 128]));
+        let zip_path = directory.join("harbour.zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("harbour/metadata.json", metadata.as_bytes()),
+                ("harbour/contents/images/morning.png", &morning),
+                ("harbour/contents/images/evening.png", &evening),
+            ],
+        );
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_kde_wallpaper(&zip_path, &themes_directory).unwrap();
+        assert_eq!(theme_id, "Harbour-KDE-v1");
+
+        let theme_directory = themes_directory.join(&theme_id);
+        let slots: Vec<TimeSlot> =
+            serde_json::from_str(&fs::read_to_string(theme_directory.join("time.json")).unwrap())
+                .unwrap();
+        let slots: Vec<_> = slots
+            .iter()
+            .map(|slot| (slot.index(), slot.time().seconds_since_midnight()))
+            .collect();
+        assert_eq!(slots, [(0, 6 * 3600), (1, 18 * 3600)]);
+        assert_eq!(
+            fs::read(theme_directory.join("images/2.png")).unwrap(),
+            evening
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_leaving_the_package() {
+        let directory = scratch_directory("kde-traversal");
+        let package_directory = directory.join("package");
+        fs::create_dir_all(&package_directory).unwrap();
+        fs::write(directory.join("secret.png"), png_image([0, 0, 0])).unwrap();
+        fs::write(
+            package_directory.join(PACKAGE_METADATA_FILENAME),
+            r#"{ "Type": "timed", "Meta": [{ "FileName": "../secret.png", "Time": 0.5 }] }"#,
+        )
+        .unwrap();
+
+        let themes_directory = directory.join("themes");
+        assert!(matches!(
+            import_kde_wallpaper(&package_directory, &themes_directory),
+            Err(DwallError::ThemeImport(ThemeImportError::UnsafePath(path)))
+                if path == "../secret.png"
+        ));
+        assert!(!themes_directory.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn exported_package_imports_back() {
+        let directory = scratch_directory("kde-export");
+        let theme_directory = directory.join("Solar");
+        let images_directory = theme_directory.join("images");
+        fs::create_dir_all(&images_directory).unwrap();
+        fs::write(images_directory.join("1.png"), png_image([0, 0, 64])).unwrap();
+        fs::write(images_directory.join("2.png"), png_image([255, 255, 0])).unwrap();
+        fs::write(
+            theme_directory.join("solar.json"),
+            r#"[{"index":0,"altitude":-30.0,"azimuth":0.0},{"index":1,"altitude":30.0,"azimuth":180.0}]"#,
+        )
+        .unwrap();
+
+        let package_directory = directory.join("package");
+        export_kde_wallpaper(
+            &theme_directory,
+            &images_directory,
+            &ImageFormat::Png,
+            "Round Trip",
+            &package_directory,
+        )
+        .unwrap();
+
+        let themes_directory = directory.join("themes");
+        let theme_id = import_kde_wallpaper(&package_directory, &themes_directory).unwrap();
+        assert_eq!(theme_id, "Round-Trip-KDE-v1");
+
+        let imported_directory = themes_directory.join(&theme_id);
+        assert_eq!(
+            read_angles(&imported_directory),
+            read_angles(&theme_directory)
+        );
+        for name in ["1.png", "2.png"] {
+            assert_eq!(
+                fs::read(imported_directory.join("images").join(name)).unwrap(),
+                fs::read(images_directory.join(name)).unwrap()
+            );
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Theme import service
+//!
+//! This module converts dynamic wallpapers made for other applications into customized
+//! themes, and where the formats allow, themes back into them. Importers and exporters
+//! work on plain paths, so the settings app and other tools share them.
+//!
+//! # Modules
+//!
+//! - `ddw`: WinDynamicDesktop `.ddw` theme packages
+//! - `gnome`: GNOME timed slideshow background XML
+//! - `heic`: macOS dynamic HEIC wallpapers
+//! - `kde`: KDE dynamic wallpaper packages
+//! - `photos`: timelapse photos with EXIF capture time and GPS position
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    DwallResult, SolarAngle, ThemeValidator,
+    domain::visual::{CUSTOMIZED_THEME_METADATA_FILENAME, CustomizedThemeMetadata, TimeSlot},
+};
+
+mod ddw;
+mod gnome;
+mod heic;
+mod kde;
+mod photos;
+
+pub use ddw::import_ddw_theme;
+pub use gnome::{export_gnome_background, import_gnome_background};
+pub use heic::{import_heic_theme, write_heic_solar_config};
+pub use kde::{export_kde_wallpaper, import_kde_wallpaper};
+pub use photos::import_photo_theme;
+
+/// Largest file read from a theme package archive, far above any wallpaper image
+const MAX_PACKAGE_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeImportError {
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    TomlSerialize(#[from] toml::ser::Error),
+    #[error("Theme package is missing '{0}'")]
+    MissingEntry(String),
+    #[error("Theme package path '{0}' points outside the package")]
+    UnsafePath(String),
+    #[error("Theme package file '{0}' is larger than {MAX_PACKAGE_ENTRY_BYTES} bytes")]
+    EntryTooLarge(String),
+    #[error("Solar metadata lists image {0} more than once")]
+    DuplicateImageIndex(u8),
+    #[error("Solar metadata has no sun position for image {0}")]
+    ImageIndexGap(u8),
+    #[error("No extracted frame for image {index} in '{}'", .frames_directory.display())]
+    MissingFrame {
+        index: u8,
+        frames_directory: PathBuf,
+    },
+    #[error("Theme package contains no images")]
+    NoImages,
+    #[error("Theme package has {0} images, at most 256 are supported")]
+    TooManyImages(usize),
+    #[error("Unsupported image format '{0}'")]
+    UnsupportedImageFormat(String),
+    #[error("Theme images mix several formats")]
+    MixedImageFormats,
+    #[error("Invalid theme metadata: {0}")]
+    InvalidMetadata(String),
+    #[error("A customized theme named '{0}' already exists")]
+    ThemeExists(String),
+}
+
+/// How an imported theme picks its images
+pub enum ImportedThemeConfiguration {
+    /// Written as `solar.json`
+    Solar(Vec<SolarAngle>),
+    /// Written as `time.json`
+    TimeOfDay(Vec<TimeSlot>),
+}
+
+impl ImportedThemeConfiguration {
+    fn file_name(&self) -> &'static str {
+        match self {
+            ImportedThemeConfiguration::Solar(_) => "solar.json",
+            ImportedThemeConfiguration::TimeOfDay(_) => "time.json",
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Result<String> {
+        match self {
+            ImportedThemeConfiguration::Solar(angles) => serde_json::to_string_pretty(angles),
+            ImportedThemeConfiguration::TimeOfDay(slots) => serde_json::to_string_pretty(slots),
+        }
+    }
+}
+
+/// A customized theme assembled by an importer, not yet written to disk
+pub struct ImportedTheme {
+    metadata: CustomizedThemeMetadata,
+    /// Encoded image files; the `n`th one is saved as `images/{n + 1}.<format>`
+    images: Vec<Vec<u8>>,
+    configuration: ImportedThemeConfiguration,
+}
+
+impl ImportedTheme {
+    pub fn new(
+        metadata: CustomizedThemeMetadata,
+        images: Vec<Vec<u8>>,
+        configuration: ImportedThemeConfiguration,
+    ) -> DwallResult<Self> {
+        if images.is_empty() {
+            return Err(ThemeImportError::NoImages.into());
+        }
+        if images.len() > usize::from(u8::MAX) + 1 {
+            return Err(ThemeImportError::TooManyImages(images.len()).into());
+        }
+
+        Ok(Self {
+            metadata,
+            images,
+            configuration,
+        })
+    }
+
+    /// Writes the theme into its own directory under `customized_themes_directory`
+    ///
+    /// The written theme is validated the same way as before it is applied; a theme
+    /// that fails is removed again. Returns the theme ID.
+    pub fn write(&self, customized_themes_directory: &Path) -> DwallResult<String> {
+        let theme_id = self.metadata.theme_id();
+        let theme_directory = customized_themes_directory.join(&theme_id);
+        if theme_directory.exists() {
+            warn!(
+                theme_id = theme_id,
+                theme_directory = %theme_directory.display(),
+                "Customized theme directory already exists"
+            );
+            return Err(ThemeImportError::ThemeExists(theme_id).into());
+        }
+
+        let written = self.write_files(&theme_directory).and_then(|_| {
+            ThemeValidator::validate(
+                customized_themes_directory,
+                &theme_id,
+                true,
+                self.metadata.image_format(),
+            )
+        });
+
+        if let Err(e) = written {
+            error!(
+                theme_id = theme_id,
+                error = %e,
+                "Failed to write imported theme, removing it"
+            );
+            if let Err(remove_error) = fs::remove_dir_all(&theme_directory) {
+                warn!(
+                    theme_directory = %theme_directory.display(),
+                    error = %remove_error,
+                    "Failed to remove incomplete theme directory"
+                );
+            }
+            return Err(e);
+        }
+
+        info!(
+            theme_id = theme_id,
+            images = self.images.len(),
+            theme_directory = %theme_directory.display(),
+            "Imported customized theme"
+        );
+        Ok(theme_id)
+    }
+
+    fn write_files(&self, theme_directory: &Path) -> DwallResult<()> {
+        let images_directory = theme_directory.join("images");
+        fs::create_dir_all(&images_directory)?;
+
+        fs::write(
+            theme_directory.join(CUSTOMIZED_THEME_METADATA_FILENAME),
+            toml::to_string(&self.metadata).map_err(ThemeImportError::from)?,
+        )?;
+
+        let extension = self.metadata.image_format().as_str();
+        for (position, image) in self.images.iter().enumerate() {
+            fs::write(
+                images_directory.join(format!("{}.{extension}", position + 1)),
+                image,
+            )?;
+        }
+
+        fs::write(
+            theme_directory.join(self.configuration.file_name()),
+            self.configuration.to_json()?,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Reads the archive entry `name`, without trusting the size the archive records
+fn read_package_entry(name: &str, entry: impl Read) -> DwallResult<Vec<u8>> {
+    let mut content = Vec::new();
+    entry
+        .take(MAX_PACKAGE_ENTRY_BYTES + 1)
+        .read_to_end(&mut content)?;
+    if content.len() as u64 > MAX_PACKAGE_ENTRY_BYTES {
+        error!(name = name, "Theme package entry is too large");
+        return Err(ThemeImportError::EntryTooLarge(name.to_string()).into());
+    }
+
+    Ok(content)
+}
+
+/// Replaces characters that are not allowed in Windows file names
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+
+    sanitized.trim_end_matches(['.', ' ']).to_string()
+}
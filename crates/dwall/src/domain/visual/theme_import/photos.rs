@@ -0,0 +1,50 @@
+//! Solar themes from timelapse photos
+//!
+//! Each photo's sun position is computed from its EXIF capture time and GPS position,
+//! or a given location for photos taken without GPS. Images are numbered in capture
+//! order, so a timelapse becomes a solar theme without writing `solar.json` by hand.
+
+use std::{fs, path::Path};
+
+use crate::{
+    Config,
+    domain::visual::{CustomizedThemeMetadata, PhotoSolarOptions, find_photos, solar_photos},
+};
+
+use super::{DwallResult, ImportedTheme, ImportedThemeConfiguration, sanitize_file_name};
+
+/// Imports the photos in `photos_directory` as a solar customized theme
+///
+/// The sun positions use the solar model of `config`. Returns the ID of the new theme.
+pub fn import_photo_theme(
+    photos_directory: &Path,
+    theme_name: &str,
+    author: &str,
+    options: &PhotoSolarOptions,
+    config: &Config,
+    customized_themes_directory: &Path,
+) -> DwallResult<String> {
+    let (photos, image_format) = find_photos(photos_directory)?;
+    let photos = solar_photos(&photos, options, |position| config.solar_model(position))?;
+
+    let images = photos
+        .iter()
+        .map(|photo| fs::read(photo.capture().path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let angles = photos.iter().map(|photo| photo.angle().clone()).collect();
+
+    info!(
+        photos_directory = %photos_directory.display(),
+        images = images.len(),
+        "Computed sun positions of photos"
+    );
+
+    let metadata = CustomizedThemeMetadata::new(
+        sanitize_file_name(theme_name),
+        sanitize_file_name(author),
+        1,
+        image_format,
+    );
+    ImportedTheme::new(metadata, images, ImportedThemeConfiguration::Solar(angles))?
+        .write(customized_themes_directory)
+}
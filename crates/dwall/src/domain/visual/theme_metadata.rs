@@ -0,0 +1,75 @@
+//! Metadata of customized themes
+//!
+//! Every customized theme directory has a `metadata.toml` at its root, written by the
+//! settings app when a theme is created or imported, and read back when it is listed
+//! or packed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ImageFormat;
+
+/// Name of the metadata file at the root of a customized theme directory
+pub const CUSTOMIZED_THEME_METADATA_FILENAME: &str = "metadata.toml";
+
+/// Content of a customized theme's `metadata.toml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomizedThemeMetadata {
+    image_format: ImageFormat,
+    theme_name: String,
+    author: String,
+    version: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+}
+
+impl CustomizedThemeMetadata {
+    pub fn new(
+        theme_name: String,
+        author: String,
+        version: u16,
+        image_format: ImageFormat,
+    ) -> Self {
+        Self {
+            image_format,
+            theme_name,
+            author,
+            version,
+            license: None,
+        }
+    }
+
+    pub fn with_license(mut self, license: Option<String>) -> Self {
+        self.license = license;
+        self
+    }
+
+    pub fn image_format(&self) -> &ImageFormat {
+        &self.image_format
+    }
+
+    pub fn theme_name(&self) -> &str {
+        &self.theme_name
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    /// Theme ID, which is also the name of the theme's directory
+    pub fn theme_id(&self) -> String {
+        format!(
+            "{}-{}-v{}",
+            self.theme_name.replace(" ", "-"),
+            self.author,
+            self.version
+        )
+    }
+}
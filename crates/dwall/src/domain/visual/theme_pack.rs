@@ -0,0 +1,715 @@
+//! Distributable theme packs
+//!
+//! A theme pack is a zip of a theme directory with a `manifest.toml` at its root. The
+//! manifest names the theme and records the SHA-256 hash of every other file, so an
+//! installed theme can later be checked against it with [`verify_theme`].
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    DwallResult,
+    config::ImageFormat,
+    domain::visual::{
+        Severity,
+        theme_metadata::{CUSTOMIZED_THEME_METADATA_FILENAME, CustomizedThemeMetadata},
+    },
+};
+
+/// Name of the manifest at the root of a theme pack and of an installed theme
+pub const THEME_MANIFEST_FILENAME: &str = "manifest.toml";
+
+/// Version of this build, the default minimum version of new packs
+const DWALL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThemePackError {
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Theme directory '{0}' has no {THEME_MANIFEST_FILENAME}")]
+    ManifestMissing(PathBuf),
+    #[error("Invalid theme manifest '{path}': {reason}")]
+    InvalidManifest { path: PathBuf, reason: String },
+    #[error("Invalid theme metadata '{path}': {reason}")]
+    InvalidMetadata { path: PathBuf, reason: String },
+    #[error("Invalid dwall version '{0}', expected MAJOR.MINOR.PATCH")]
+    InvalidVersion(String),
+    #[error("Theme directory '{0}' contains no images")]
+    NoImages(PathBuf),
+    #[error("File name '{0}' is not valid UTF-8")]
+    NonUtf8Path(PathBuf),
+}
+
+/// Description of a theme recorded in the manifest of a new pack
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemePackOptions {
+    name: String,
+    author: String,
+    version: u16,
+    license: Option<String>,
+    min_dwall_version: String,
+}
+
+impl ThemePackOptions {
+    /// Options for a theme that runs on this dwall version or later
+    pub fn new(name: impl Into<String>, author: impl Into<String>, version: u16) -> Self {
+        Self {
+            name: name.into(),
+            author: author.into(),
+            version,
+            license: None,
+            min_dwall_version: DWALL_VERSION.to_string(),
+        }
+    }
+
+    /// Options from a customized theme's `metadata.toml`, if the theme has one
+    pub fn from_metadata(theme_directory: &Path) -> DwallResult<Option<Self>> {
+        let path = theme_directory.join(CUSTOMIZED_THEME_METADATA_FILENAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let metadata: CustomizedThemeMetadata = toml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| ThemePackError::InvalidMetadata {
+                path: path.clone(),
+                reason: e.to_string(),
+            })?;
+
+        let options = Self::new(metadata.theme_name(), metadata.author(), metadata.version());
+        Ok(Some(match metadata.license() {
+            Some(license) => options.with_license(license),
+            None => options,
+        }))
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = author.into();
+        self
+    }
+
+    pub fn with_version(mut self, version: u16) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Oldest dwall version able to apply the theme, as `MAJOR.MINOR.PATCH`
+    pub fn with_min_dwall_version(mut self, version: &str) -> Result<Self, ThemePackError> {
+        parse_version(version)?;
+        self.min_dwall_version = version.to_string();
+        Ok(self)
+    }
+}
+
+/// The `manifest.toml` of a theme pack
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeManifest {
+    name: String,
+    author: String,
+    version: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    image_formats: Vec<ImageFormat>,
+    min_dwall_version: String,
+    /// SHA-256 hash of every other file of the theme, by path relative to its root
+    files: BTreeMap<String, String>,
+}
+
+impl ThemeManifest {
+    /// Describes the theme in `theme_directory`, hashing each of its files
+    pub fn for_theme(theme_directory: &Path, options: &ThemePackOptions) -> DwallResult<Self> {
+        let mut files = BTreeMap::new();
+        for path in theme_files(theme_directory)? {
+            let hash = file_hash(&theme_directory.join(&path))?;
+            files.insert(path, hash);
+        }
+
+        let image_formats: Vec<ImageFormat> = ImageFormat::ALL
+            .into_iter()
+            .filter(|format| {
+                files
+                    .keys()
+                    .any(|path| image_format_of(path).as_ref() == Some(format))
+            })
+            .collect();
+        if image_formats.is_empty() {
+            return Err(ThemePackError::NoImages(theme_directory.to_path_buf()).into());
+        }
+
+        Ok(Self {
+            name: options.name.clone(),
+            author: options.author.clone(),
+            version: options.version,
+            license: options.license.clone(),
+            image_formats,
+            min_dwall_version: options.min_dwall_version.clone(),
+            files,
+        })
+    }
+
+    /// Reads the manifest of an installed theme
+    pub fn read(theme_directory: &Path) -> DwallResult<Self> {
+        let path = theme_directory.join(THEME_MANIFEST_FILENAME);
+        if !path.is_file() {
+            return Err(ThemePackError::ManifestMissing(theme_directory.to_path_buf()).into());
+        }
+
+        let manifest: Self = toml::from_str(&fs::read_to_string(&path)?).map_err(|e| {
+            ThemePackError::InvalidManifest {
+                path: path.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+        parse_version(&manifest.min_dwall_version).map_err(|e| {
+            ThemePackError::InvalidManifest {
+                path: path.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+
+        // Listed paths are joined to the theme directory, so they must stay inside it
+        if let Some(unsafe_path) = manifest.files.keys().find(|file| {
+            !Path::new(file)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        }) {
+            error!(
+                manifest_path = %path.display(),
+                file = unsafe_path,
+                "Theme manifest lists a path outside the theme"
+            );
+            return Err(ThemePackError::InvalidManifest {
+                reason: format!("file path '{unsafe_path}' points outside the theme"),
+                path,
+            }
+            .into());
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    pub fn image_formats(&self) -> &[ImageFormat] {
+        &self.image_formats
+    }
+
+    pub fn min_dwall_version(&self) -> &str {
+        &self.min_dwall_version
+    }
+
+    /// Hashes by path relative to the theme root, with `/` separators
+    pub fn files(&self) -> &BTreeMap<String, String> {
+        &self.files
+    }
+
+    fn to_toml(&self) -> DwallResult<String> {
+        toml::to_string(self).map_err(|e| {
+            ThemePackError::InvalidManifest {
+                path: PathBuf::from(THEME_MANIFEST_FILENAME),
+                reason: e.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Zips the theme in `theme_directory` with a new manifest into `zip_path`
+///
+/// Files sit at the root of the zip, as in the theme directory. A partly written zip is
+/// removed again. Returns the manifest written into the zip.
+pub fn pack_theme(
+    theme_directory: &Path,
+    options: &ThemePackOptions,
+    zip_path: &Path,
+) -> DwallResult<ThemeManifest> {
+    let manifest = ThemeManifest::for_theme(theme_directory, options)?;
+
+    if let Err(e) = write_pack(theme_directory, &manifest, zip_path) {
+        error!(
+            zip_path = %zip_path.display(),
+            error = %e,
+            "Failed to write theme pack, removing it"
+        );
+        if let Err(remove_error) = fs::remove_file(zip_path) {
+            warn!(
+                zip_path = %zip_path.display(),
+                error = %remove_error,
+                "Failed to remove incomplete theme pack"
+            );
+        }
+        return Err(e);
+    }
+
+    info!(
+        theme_directory = %theme_directory.display(),
+        zip_path = %zip_path.display(),
+        files = manifest.files.len(),
+        "Packed theme"
+    );
+    Ok(manifest)
+}
+
+fn write_pack(
+    theme_directory: &Path,
+    manifest: &ThemeManifest,
+    zip_path: &Path,
+) -> DwallResult<()> {
+    let mut zip = ZipWriter::new(File::create(zip_path)?);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Images are compressed already
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file(THEME_MANIFEST_FILENAME, deflated)
+        .map_err(ThemePackError::from)?;
+    zip.write_all(manifest.to_toml()?.as_bytes())?;
+
+    for path in manifest.files.keys() {
+        let options = if image_format_of(path).is_some() {
+            stored
+        } else {
+            deflated
+        };
+        zip.start_file(path.as_str(), options)
+            .map_err(ThemePackError::from)?;
+        io::copy(&mut File::open(theme_directory.join(path))?, &mut zip)?;
+    }
+
+    zip.finish().map_err(ThemePackError::from)?;
+    Ok(())
+}
+
+/// A difference between an installed theme and its manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    MissingFile {
+        path: String,
+    },
+    ModifiedFile {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+    UnlistedFile {
+        path: String,
+    },
+    UnsupportedDwallVersion {
+        required: String,
+        current: String,
+    },
+}
+
+impl IntegrityIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            IntegrityIssue::UnlistedFile { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::MissingFile { path } => write!(f, "File '{path}' is missing"),
+            IntegrityIssue::ModifiedFile {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "File '{path}' was modified: SHA-256 is {actual}, the manifest expects {expected}"
+            ),
+            IntegrityIssue::UnlistedFile { path } => {
+                write!(f, "File '{path}' is not listed in the manifest")
+            }
+            IntegrityIssue::UnsupportedDwallVersion { required, current } => write!(
+                f,
+                "Theme requires dwall {required} or later, this is dwall {current}"
+            ),
+        }
+    }
+}
+
+/// An issue with its severity and message, as reported to the settings UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntegrityItem {
+    severity: Severity,
+    message: String,
+    #[serde(flatten)]
+    issue: IntegrityIssue,
+}
+
+impl IntegrityItem {
+    fn new(issue: IntegrityIssue) -> Self {
+        Self {
+            severity: issue.severity(),
+            message: issue.to_string(),
+            issue,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn issue(&self) -> &IntegrityIssue {
+        &self.issue
+    }
+}
+
+/// Result of checking an installed theme against its manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    manifest: ThemeManifest,
+    items: Vec<IntegrityItem>,
+}
+
+impl IntegrityReport {
+    pub fn manifest(&self) -> &ThemeManifest {
+        &self.manifest
+    }
+
+    pub fn items(&self) -> &[IntegrityItem] {
+        &self.items
+    }
+
+    /// Whether every listed file is unchanged and this dwall version is supported
+    pub fn is_intact(&self) -> bool {
+        self.items
+            .iter()
+            .all(|item| item.severity != Severity::Error)
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Theme '{}' v{} by {}: {}",
+            self.manifest.name,
+            self.manifest.version,
+            self.manifest.author,
+            if self.is_intact() {
+                "intact"
+            } else {
+                "not intact"
+            }
+        )?;
+        for item in &self.items {
+            write!(f, "\n{}: {}", item.severity, item.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the theme in `theme_directory` against its `manifest.toml`
+///
+/// Every listed file must exist with the recorded hash, and this dwall version must be
+/// at least the manifest's minimum. Files the manifest does not list are warnings.
+pub fn verify_theme(theme_directory: &Path) -> DwallResult<IntegrityReport> {
+    let manifest = ThemeManifest::read(theme_directory)?;
+    let mut items = Vec::new();
+
+    if parse_version(&manifest.min_dwall_version)? > parse_version(DWALL_VERSION)? {
+        items.push(IntegrityItem::new(
+            IntegrityIssue::UnsupportedDwallVersion {
+                required: manifest.min_dwall_version.clone(),
+                current: DWALL_VERSION.to_string(),
+            },
+        ));
+    }
+
+    for (path, expected) in &manifest.files {
+        let file_path = theme_directory.join(path);
+        if !file_path.is_file() {
+            items.push(IntegrityItem::new(IntegrityIssue::MissingFile {
+                path: path.clone(),
+            }));
+            continue;
+        }
+
+        let actual = file_hash(&file_path)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            items.push(IntegrityItem::new(IntegrityIssue::ModifiedFile {
+                path: path.clone(),
+                expected: expected.clone(),
+                actual,
+            }));
+        }
+    }
+
+    for path in theme_files(theme_directory)? {
+        if !manifest.files.contains_key(&path) {
+            items.push(IntegrityItem::new(IntegrityIssue::UnlistedFile { path }));
+        }
+    }
+
+    for item in &items {
+        debug!(
+            theme_directory = %theme_directory.display(),
+            severity = %item.severity,
+            issue = %item.issue,
+            "Theme integrity issue"
+        );
+    }
+
+    Ok(IntegrityReport { manifest, items })
+}
+
+/// Paths of every file of a theme except its manifest, relative to the theme root
+fn theme_files(theme_directory: &Path) -> DwallResult<Vec<String>> {
+    let mut files = Vec::new();
+    let mut directories = vec![(theme_directory.to_path_buf(), String::new())];
+
+    while let Some((directory, prefix)) = directories.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| ThemePackError::NonUtf8Path(entry.path()))?;
+            let relative = format!("{prefix}{name}");
+
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                directories.push((entry.path(), format!("{relative}/")));
+            } else if file_type.is_file() && relative != THEME_MANIFEST_FILENAME {
+                files.push(relative);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Lowercase hexadecimal SHA-256 of a file's content
+fn file_hash(path: &Path) -> DwallResult<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn image_format_of(path: &str) -> Option<ImageFormat> {
+    ImageFormat::from_extension(Path::new(path).extension()?.to_str()?)
+}
+
+/// Major, minor and patch numbers of a version, ignoring any pre-release suffix
+fn parse_version(version: &str) -> Result<(u64, u64, u64), ThemePackError> {
+    let release = version.split(['-', '+']).next().unwrap_or_default();
+    let mut numbers = release.split('.').map(|number| number.parse::<u64>());
+    match (
+        numbers.next(),
+        numbers.next(),
+        numbers.next(),
+        numbers.next(),
+    ) {
+        (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => Ok((major, minor, patch)),
+        _ => Err(ThemePackError::InvalidVersion(version.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::{error::DwallError, utils::testing::scratch_directory};
+
+    fn write_theme(theme_directory: &Path) {
+        fs::create_dir_all(theme_directory.join("images")).unwrap();
+        fs::write(theme_directory.join("images/1.jpg"), b"first image").unwrap();
+        fs::write(theme_directory.join("images/2.jpg"), b"second image").unwrap();
+        fs::write(
+            theme_directory.join("solar.json"),
+            r#"[{"index":0,"altitude":-10.0,"azimuth":90.0}]"#,
+        )
+        .unwrap();
+        fs::write(
+            theme_directory.join(CUSTOMIZED_THEME_METADATA_FILENAME),
+            "image_format = \"jpeg\"\ntheme_name = \"Timelapse\"\nauthor = \"Jane\"\nversion = 2\nlicense = \"CC-BY-4.0\"\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn packs_theme_with_manifest() {
+        let directory = scratch_directory("pack-zip");
+        let theme_directory = directory.join("theme");
+        write_theme(&theme_directory);
+
+        let options = ThemePackOptions::from_metadata(&theme_directory)
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.license.as_deref(), Some("CC-BY-4.0"));
+
+        let zip_path = directory.join("theme.zip");
+        let manifest = pack_theme(&theme_directory, &options, &zip_path).unwrap();
+        assert_eq!(manifest.name(), "Timelapse");
+        assert_eq!(manifest.version(), 2);
+        assert_eq!(manifest.image_formats(), &[ImageFormat::Jpeg]);
+        assert_eq!(
+            manifest.files().keys().collect::<Vec<_>>(),
+            [
+                "images/1.jpg",
+                "images/2.jpg",
+                "metadata.toml",
+                "solar.json"
+            ]
+        );
+        assert_eq!(
+            manifest.files()["images/1.jpg"],
+            format!("{:x}", Sha256::digest(b"first image"))
+        );
+
+        let mut zip = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(zip.len(), 5);
+        let mut content = String::new();
+        zip.by_name(THEME_MANIFEST_FILENAME)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(toml::from_str::<ThemeManifest>(&content).unwrap(), manifest);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn verifies_installed_theme() {
+        let directory = scratch_directory("pack-verify");
+        write_theme(&directory);
+
+        let manifest =
+            ThemeManifest::for_theme(&directory, &ThemePackOptions::new("Theme", "Jane", 1))
+                .unwrap();
+        fs::write(
+            directory.join(THEME_MANIFEST_FILENAME),
+            manifest.to_toml().unwrap(),
+        )
+        .unwrap();
+        assert!(verify_theme(&directory).unwrap().items().is_empty());
+
+        fs::write(directory.join("images/1.jpg"), b"edited image").unwrap();
+        fs::remove_file(directory.join("images/2.jpg")).unwrap();
+        fs::write(directory.join("notes.txt"), b"notes").unwrap();
+
+        let report = verify_theme(&directory).unwrap();
+        assert!(!report.is_intact());
+        let issues: Vec<_> = report.items().iter().map(IntegrityItem::issue).collect();
+        assert!(matches!(
+            issues[0],
+            IntegrityIssue::ModifiedFile { path, .. } if path == "images/1.jpg"
+        ));
+        assert_eq!(
+            issues[1..],
+            [
+                &IntegrityIssue::MissingFile {
+                    path: "images/2.jpg".to_string()
+                },
+                &IntegrityIssue::UnlistedFile {
+                    path: "notes.txt".to_string()
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_themes_for_newer_dwall() {
+        let directory = scratch_directory("pack-version");
+        write_theme(&directory);
+
+        let options = ThemePackOptions::new("Theme", "Jane", 1)
+            .with_min_dwall_version("999.0.0")
+            .unwrap();
+        let manifest = ThemeManifest::for_theme(&directory, &options).unwrap();
+        fs::write(
+            directory.join(THEME_MANIFEST_FILENAME),
+            manifest.to_toml().unwrap(),
+        )
+        .unwrap();
+
+        let report = verify_theme(&directory).unwrap();
+        assert_eq!(
+            report.items()[0].issue(),
+            &IntegrityIssue::UnsupportedDwallVersion {
+                required: "999.0.0".to_string(),
+                current: DWALL_VERSION.to_string(),
+            }
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_manifest_paths_outside_the_theme() {
+        let directory = scratch_directory("pack-traversal");
+        write_theme(&directory);
+        let manifest =
+            ThemeManifest::for_theme(&directory, &ThemePackOptions::new("Theme", "Jane", 1))
+                .unwrap()
+                .to_toml()
+                .unwrap();
+
+        for unsafe_path in ["../images/1.jpg", "/images/1.jpg"] {
+            fs::write(
+                directory.join(THEME_MANIFEST_FILENAME),
+                manifest.replace("\"images/1.jpg\"", &format!("\"{unsafe_path}\"")),
+            )
+            .unwrap();
+            assert!(matches!(
+                verify_theme(&directory),
+                Err(DwallError::ThemePack(ThemePackError::InvalidManifest { reason, .. }))
+                    if reason.contains(unsafe_path)
+            ));
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("0.2.5").unwrap(), (0, 2, 5));
+        assert_eq!(parse_version("1.0.0-beta.1").unwrap(), (1, 0, 0));
+        assert!(parse_version("1.0").is_err());
+        assert!(parse_version("1.0.x").is_err());
+        assert!(parse_version("1.0.0.0").is_err());
+    }
+}
@@ -1,11 +1,12 @@
-//! Solar-based theme processing and management for dynamic wallpaper updates
+//! Theme processing and management for dynamic wallpaper updates
+//!
+//! Themes select their images either by sun position or by local time of day,
+//! see [`ThemeDefinition`].
 
 use std::{
     cell::RefCell,
-    collections::HashMap,
-    fs,
+    collections::{BTreeSet, HashMap},
     path::{Path, PathBuf},
-    thread::sleep,
     time::Duration,
 };
 
@@ -13,25 +14,33 @@ use time::{OffsetDateTime, UtcDateTime};
 
 use crate::{
     DwallResult,
-    config::{Config, ImageFormat},
+    config::Config,
     domain::{
         geography::{Position, provider::GeographicPositionProvider},
-        time::solar_calculator::{SolarAngle, SolarPosition},
+        time::{
+            lunar_calculator::LunarPosition,
+            solar_calculator::{SolarModel, SolarPosition},
+        },
         visual::{
             DaylightState,
+            blend::{blend_cache_directory, render_blend},
             color_scheme::{
-                ColorSchemeManager, ThresholdConfig, determine_color_scheme_with_hysteresis,
+                ColorScheme, ColorSchemeContext, ColorSchemeManager, ThresholdConfig,
                 set_color_scheme,
             },
+            scheduler::{ChangeScheduler, sleep_until},
+            theme_definition::{SelectionMoment, ThemeDefinition},
+            theme_images::{ThemeImages, converted_cache_directory},
+            theme_validator::ValidationReport,
+            wallpaper::{SelectionPolicy, SkyPosition, WallpaperChoice},
         },
     },
-    infrastructure::display::wallpaper_setter::WallpaperSetter,
+    infrastructure::display::{DisplayMonitor, Resolution, wallpaper_setter::WallpaperSetter},
     utils::cache::get_cache,
 };
 
 // Constants for improved code maintainability
 const MAX_CONSECUTIVE_FAILURE_THRESHOLD: u8 = 3;
-const SOLAR_CONFIG_FILENAME: &str = "solar.json";
 
 /// Comprehensive error handling for solar theme-related operations
 #[derive(Debug, thiserror::Error)]
@@ -40,10 +49,30 @@ pub enum ThemeError {
     ThemeDirectoryNotFound(String),
     #[error("Default theme is missing or not configured")]
     DefaultThemeMissing,
-    #[error("Solar configuration file 'solar.json' is missing in theme directory")]
-    SolarConfigurationMissing,
-    #[error("Image files do not match solar configuration: expected {expected}, found {found}")]
-    ImageSolarConfigurationMismatch { expected: usize, found: usize },
+    #[error(
+        "Theme configuration file 'solar.json', 'time.json' or 'appearance.json' is missing in theme directory"
+    )]
+    ThemeConfigurationMissing,
+    #[error("Solar angle of image {index} has an invalid {field}: {value}")]
+    InvalidSolarAngle {
+        index: u8,
+        field: &'static str,
+        value: f64,
+    },
+    #[error("{0}")]
+    ValidationFailed(ValidationReport),
+    #[error("None of the theme's {images_available} images suits the current moment")]
+    NoSuitableImage { images_available: usize },
+    #[error("Theme has images in {available}, but none in the preferred formats {preferred}")]
+    NoPreferredImageFormat {
+        available: String,
+        preferred: String,
+    },
+    #[error("Theme images in {available} cannot be converted to any of {preferred}")]
+    ImageConversionUnavailable {
+        available: String,
+        preferred: String,
+    },
     #[error("Wallpaper image file '{path}' does not exist")]
     WallpaperImageMissing { path: String },
     #[error("No monitor-specific wallpaper configurations found")]
@@ -62,8 +91,9 @@ impl<'a> ThemeProcessor<'a> {
     pub(crate) fn new(config: &'a Config) -> DwallResult<Self> {
         info!(
             auto_detect_color_mode = ?config.auto_detect_color_scheme(),
-            image_format = ?config.image_format(),
-            update_interval_seconds = config.interval(),
+            image_formats = ?config.image_format_preference(),
+            convert_image_formats = config.convert_image_formats(),
+            max_wake_interval_seconds = config.interval(),
             "Initializing solar theme processor"
         );
 
@@ -76,24 +106,26 @@ impl<'a> ThemeProcessor<'a> {
         })
     }
 
-    /// Returns the update interval in seconds
-    pub(crate) fn update_interval(&self) -> u16 {
+    /// Returns the maximum wake interval in seconds
+    pub(crate) fn max_wake_interval(&self) -> u16 {
         self.config.interval()
     }
 
     /// Runs a single solar theme update cycle
     ///
-    /// Returns `true` if the cycle was successful, `false` otherwise
-    pub(crate) fn run_once(&self) -> DwallResult<bool> {
+    /// Returns how long to wait until the next wallpaper or color scheme change is due
+    pub(crate) fn run_once(&self) -> DwallResult<Duration> {
         let current_geographic_position =
             self.geographic_position_provider.get_current_position()?;
-        let result = self.process_solar_theme_cycle(&current_geographic_position);
+        let result = self
+            .process_solar_theme_cycle(&current_geographic_position)
+            .and_then(|_| self.schedule_next_cycle(&current_geographic_position));
 
         if let Err(error) = &result {
             error!(error = %error, "Solar theme cycle failed");
         }
 
-        result.map(|_| true)
+        result
     }
 
     /// Checks if monitor configuration has changed and reloads if necessary
@@ -136,14 +168,14 @@ impl<'a> ThemeProcessor<'a> {
     /// Starts a continuous loop to update wallpaper themes based on current solar position
     pub fn start_solar_update_loop(&self) -> DwallResult<()> {
         info!(
-            update_interval_seconds = self.config.interval(),
+            max_wake_interval_seconds = self.config.interval(),
             "Starting solar-based theme update loop"
         );
 
         let mut last_update_timestamp = UtcDateTime::now();
         let mut consecutive_failure_count = 0;
 
-        let update_interval_duration = Duration::from_secs(self.config.interval().into());
+        let max_wake_interval = Duration::from_secs(self.config.interval().into());
 
         loop {
             let current_timestamp = UtcDateTime::now();
@@ -156,16 +188,20 @@ impl<'a> ThemeProcessor<'a> {
             // Get current geographic position and process solar theme cycle
             let current_geographic_position =
                 self.geographic_position_provider.get_current_position()?;
-            let theme_cycle_result = self.process_solar_theme_cycle(&current_geographic_position);
+            let theme_cycle_result = self
+                .process_solar_theme_cycle(&current_geographic_position)
+                .and_then(|_| self.schedule_next_cycle(&current_geographic_position));
 
+            let mut wake_delay = max_wake_interval;
             match theme_cycle_result {
-                Ok(_) => {
+                Ok(delay) => {
                     debug!(
                         latitude = current_geographic_position.latitude(),
                         longitude = current_geographic_position.longitude(),
                         "Solar theme cycle completed successfully"
                     );
                     consecutive_failure_count = 0; // Reset failure counter on success
+                    wake_delay = delay;
                 }
                 Err(error) => {
                     consecutive_failure_count += 1;
@@ -226,14 +262,20 @@ impl<'a> ThemeProcessor<'a> {
                 continue;
             }
 
-            // Normal flow: sleep before next update
+            // Normal flow: sleep until the next predicted change, waking early if
+            // the monitor configuration changes in the meantime
             debug!(
-                sleep_duration_seconds = update_interval_duration.as_secs(),
+                sleep_duration_seconds = wake_delay.as_secs(),
                 "Waiting before next solar theme update cycle"
             );
 
             last_update_timestamp = current_timestamp;
-            sleep(update_interval_duration);
+            let deadline = UtcDateTime::now().add_seconds(wake_delay.as_secs())?;
+            sleep_until(deadline, || {
+                self.wallpaper_manager
+                    .is_monitor_configuration_stale()
+                    .unwrap_or(false)
+            });
         }
 
         warn!("Solar theme update loop terminated");
@@ -247,288 +289,183 @@ impl<'a> ThemeProcessor<'a> {
     ) -> DwallResult<()> {
         process_solar_theme_cycle(self.config, geographic_position, &self.wallpaper_manager)
     }
-}
-
-/// Theme validation utilities for solar-based wallpaper themes
-pub struct ThemeValidator;
-
-impl ThemeValidator {
-    /// Validates if a solar theme exists and has proper configuration and image files
-    pub fn validate(
-        themes_directory: &Path,
-        theme_identifier: &str,
-        is_customized: bool,
-        image_format: &ImageFormat,
-    ) -> DwallResult<()> {
-        trace!(
-            theme_id = theme_identifier,
-            themes_directory = %themes_directory.display(),
-            "Starting solar theme validation"
-        );
-
-        let theme_directory_path = themes_directory.join(theme_identifier);
-
-        if !theme_directory_path.exists() {
-            warn!(
-                theme_id = theme_identifier,
-                theme_path = %theme_directory_path.display(),
-                "Solar theme directory not found"
-            );
-            return Err(ThemeError::ThemeDirectoryNotFound(theme_identifier.to_string()).into());
-        }
-
-        let solar_angle_configuration =
-            Self::load_solar_angle_configuration(&theme_directory_path)?;
-        let expected_image_indices: Vec<u8> = solar_angle_configuration
-            .iter()
-            .map(|angle| angle.index())
-            .collect();
-
-        if !Self::validate_theme_image_files(
-            &theme_directory_path,
-            &expected_image_indices,
-            is_customized,
-            image_format.as_str(),
-        ) {
-            warn!(
-                theme_id = theme_identifier,
-                expected_images = expected_image_indices.len(),
-                "Solar theme image validation failed"
-            );
-            return Err(ThemeError::ImageSolarConfigurationMismatch {
-                expected: expected_image_indices.len(),
-                found: 0, // We'll improve this in validate_theme_image_files
-            }
-            .into());
-        }
-
-        info!(
-            theme_id = theme_identifier,
-            solar_angles_count = solar_angle_configuration.len(),
-            is_customized = is_customized,
-            "Solar theme validation completed successfully"
-        );
-        Ok(())
-    }
-
-    /// Loads solar angle configuration from theme directory
-    fn load_solar_angle_configuration(theme_directory: &Path) -> DwallResult<Vec<SolarAngle>> {
-        let solar_configuration_file_path = theme_directory.join(SOLAR_CONFIG_FILENAME);
 
-        if !solar_configuration_file_path.exists() {
-            error!(
-                solar_config_path = %solar_configuration_file_path.display(),
-                "Solar configuration file 'solar.json' is missing from theme directory"
-            );
-            return Err(ThemeError::SolarConfigurationMissing.into());
-        }
-
-        let solar_configuration_content = fs::read_to_string(&solar_configuration_file_path)
-            .map_err(|io_error| {
-                error!(
-                    error = %io_error,
-                    solar_config_path = %solar_configuration_file_path.display(),
-                    "Failed to read solar configuration file"
-                );
-                io_error
-            })?;
-
-        let solar_angle_list: Vec<SolarAngle> = serde_json::from_str(&solar_configuration_content)
-            .map_err(|json_error| {
-                error!(
-                    error = %json_error,
-                    solar_config_path = %solar_configuration_file_path.display(),
-                    "Failed to parse solar configuration JSON"
-                );
-                json_error
-            })?;
-
-        debug!(
-            solar_angles_count = solar_angle_list.len(),
-            solar_config_path = %solar_configuration_file_path.display(),
-            "Successfully loaded solar angle configuration"
-        );
-        Ok(solar_angle_list)
+    /// Predicts how long to wait before the next solar theme cycle is due
+    fn schedule_next_cycle(&self, geographic_position: &Position) -> DwallResult<Duration> {
+        schedule_next_solar_theme_cycle(self.config, geographic_position, &self.wallpaper_manager)
     }
+}
 
-    /// Validates that all required image files exist for the theme's solar configuration
-    fn validate_theme_image_files(
-        theme_directory: &Path,
-        expected_image_indices: &[u8],
-        is_customized: bool,
-        image_file_format: &str,
-    ) -> bool {
-        let images_directory_path = if is_customized {
-            theme_directory.join("images")
-        } else {
-            theme_directory.join(image_file_format)
-        };
-
-        if !images_directory_path.is_dir() {
-            warn!(
-                images_directory = %images_directory_path.display(),
-                "Theme images directory not found or is not a directory"
-            );
-            return false;
-        }
-
-        let mut missing_images_count = 0;
-        let validation_successful = expected_image_indices.iter().all(|&image_index| {
-            let image_filename = format!("{}.{}", image_index + 1, image_file_format);
-            let image_file_path = images_directory_path.join(image_filename);
+thread_local! {
+    /// Cache theme configurations to avoid repeated reads
+    static THEME_CACHE: RefCell<HashMap<PathBuf, ThemeDefinition>> =
+        RefCell::new(HashMap::new());
 
-            let image_exists = image_file_path.exists() && image_file_path.is_file();
-            if !image_exists {
-                missing_images_count += 1;
-                warn!(
-                    image_path = %image_file_path.display(),
-                    image_index = image_index,
-                    "Required theme image file is missing"
-                );
-            }
-            image_exists
-        });
+    /// Image last selected for each theme directory, so selection can stick to it
+    static SELECTED_IMAGES: RefCell<HashMap<PathBuf, u8>> = RefCell::new(HashMap::new());
+}
 
-        if !validation_successful {
-            error!(
-                missing_images = missing_images_count,
-                total_expected = expected_image_indices.len(),
-                "Theme image validation failed due to missing files"
-            );
-        }
+/// Key of a theme in the caches above, the same however its directory is spelled
+fn theme_cache_key(theme_directory: &Path) -> DwallResult<PathBuf> {
+    Ok(theme_directory.canonicalize()?)
+}
 
-        validation_successful
-    }
+/// Image last selected for the theme with the given cache key, if any
+fn previous_image(theme_key: &Path) -> Option<u8> {
+    SELECTED_IMAGES.with(|selected| selected.borrow().get(theme_key).copied())
 }
 
-thread_local! {
-    /// Cache solar configuration to avoid repeated reads
-    static SOLAR_CACHE: RefCell<HashMap<PathBuf, Vec<SolarAngle>>> =
-        RefCell::new(HashMap::new());
+/// Selection policy for predicting the theme's next change, sticking to the image
+/// last selected for it however its directory is spelled
+fn scheduling_policy(configuration: &Config, theme_directory: &Path) -> SelectionPolicy {
+    let previous = theme_cache_key(theme_directory)
+        .ok()
+        .and_then(|theme_key| previous_image(&theme_key));
+    configuration.selection_policy().with_previous(previous)
 }
 
-/// Load solar configuration for a specific theme directory
-fn load_cached_solar_angles(theme_directory: &Path) -> DwallResult<Vec<SolarAngle>> {
-    let theme_directory = theme_directory.canonicalize()?;
-    debug!(path = %theme_directory.display(), "Loading solar configuration from canonical and absolute path");
+/// Load the theme configuration for a specific theme directory
+pub(crate) fn load_cached_theme_definition(theme_directory: &Path) -> DwallResult<ThemeDefinition> {
+    let theme_directory = theme_cache_key(theme_directory)?;
+    debug!(path = %theme_directory.display(), "Loading theme configuration from canonical and absolute path");
 
     // Check cache
     {
-        if let Some(cached_angles) =
-            SOLAR_CACHE.with(|cache| cache.borrow().get(&theme_directory).cloned())
+        if let Some(cached_definition) =
+            THEME_CACHE.with(|cache| cache.borrow().get(&theme_directory).cloned())
         {
-            debug!("Using cached solar configuration");
-            return Ok(cached_angles);
+            debug!("Using cached theme configuration");
+            return Ok(cached_definition);
         }
     }
-    let solar_config_path = theme_directory.join("solar.json");
-    // Validate solar configuration file exists
-    if !solar_config_path.exists() {
-        error!(
-            solar_config_path = %solar_config_path.display(),
-            "Solar configuration file is missing"
-        );
-        return Err(ThemeError::SolarConfigurationMissing.into());
-    }
-    // Read solar configuration file
-    let solar_config_content = match fs::read_to_string(&solar_config_path) {
-        Ok(content) => content,
-        Err(read_error) => {
-            error!(
-                solar_config_path = %solar_config_path.display(),
-                error = ?read_error,
-                "Failed to read solar configuration file"
-            );
-            return Err(read_error.into());
-        }
-    };
-    // Parse solar configuration
-    let solar_angles: Vec<SolarAngle> = match serde_json::from_str(&solar_config_content) {
-        Ok(angles) => angles,
-        Err(parse_error) => {
-            error!(
-                solar_config_path = %solar_config_path.display(),
-                error = ?parse_error,
-                "Failed to parse solar configuration JSON"
-            );
-            return Err(parse_error.into());
-        }
-    };
+
+    let theme_definition = ThemeDefinition::load(&theme_directory)?;
     debug!(
-        solar_angles_count = solar_angles.len(),
-        "Successfully loaded solar configuration"
+        theme_kind = %theme_definition,
+        images_count = theme_definition.image_indices().len(),
+        "Successfully loaded theme configuration"
     );
 
-    // Cache solar configuration
-    SOLAR_CACHE.with(|cache| {
+    // Cache theme configuration
+    THEME_CACHE.with(|cache| {
         cache
             .borrow_mut()
-            .insert(theme_directory.to_path_buf(), solar_angles.clone());
+            .insert(theme_directory.to_path_buf(), theme_definition.clone());
     });
 
-    Ok(solar_angles)
+    Ok(theme_definition)
 }
 
-/// Build wallpaper file path based on theme directory, image format, and solar image index
-fn construct_wallpaper_file_path(
+/// Images of a theme in the format it is shown in, converting them if configured
+fn resolve_theme_images(
+    configuration: &Config,
     theme_directory_path: &Path,
-    image_format: &str,
-    solar_image_index: u8,
     is_customized: bool,
-) -> PathBuf {
-    let image_file_name = format!("{}.{}", solar_image_index + 1, image_format);
-    if is_customized {
-        theme_directory_path.join("images").join(image_file_name)
-    } else {
-        theme_directory_path
-            .join(image_format)
-            .join(image_file_name)
+) -> DwallResult<ThemeImages> {
+    ThemeImages::resolve(
+        theme_directory_path,
+        is_customized,
+        &configuration.image_format_preference(),
+        configuration.convert_image_formats(),
+        &converted_cache_directory(),
+    )
+}
+
+/// Build the file to show for `choice`, rendering a cached blend if needed
+fn resolve_wallpaper_file_path(
+    theme_images: &ThemeImages,
+    choice: WallpaperChoice,
+) -> DwallResult<PathBuf> {
+    let (from, to, weight) = match choice {
+        WallpaperChoice::Single(index) => return Ok(theme_images.image_path(index)),
+        WallpaperChoice::Blend { from, to, weight } => (from, to, weight),
+    };
+
+    if !theme_images.format().is_decodable() {
+        debug!(
+            image_format = theme_images.format().as_str(),
+            "Image format cannot be decoded for blending, showing a single image"
+        );
+        return Ok(theme_images.image_path(choice.primary_index()));
+    }
+
+    let from_path = theme_images.image_path(from);
+    let to_path = theme_images.image_path(to);
+    for path in [&from_path, &to_path] {
+        if !path.exists() {
+            error!(
+                wallpaper_path = %path.display(),
+                "Wallpaper image file to blend does not exist"
+            );
+            return Err(ThemeError::WallpaperImageMissing {
+                path: path.display().to_string(),
+            }
+            .into());
+        }
     }
+
+    render_blend(&from_path, &to_path, weight, &blend_cache_directory())
 }
 
-/// Find the wallpaper image that best matches the current solar position
-fn find_optimal_solar_wallpaper(
+/// Find the wallpaper image, or pair of images to blend, that best matches the
+/// current sun and moon positions or local time, depending on the theme
+fn find_optimal_wallpaper(
     theme_directory_path: &Path,
-    current_sun_position: &SolarPosition,
-) -> DwallResult<(u8, Vec<SolarAngle>)> {
-    let solar_angle_configuration = load_cached_solar_angles(theme_directory_path)?;
-    let sun_altitude_degrees = current_sun_position.altitude();
-    let sun_azimuth_degrees = current_sun_position.azimuth();
+    current_moment: &SelectionMoment,
+    policy: SelectionPolicy,
+) -> DwallResult<WallpaperChoice> {
+    let theme_definition = load_cached_theme_definition(theme_directory_path)?;
+    let theme_key = theme_cache_key(theme_directory_path)?;
+    // Blends change gradually, so only single images stick to the previous one
+    let previous_image_index = previous_image(&theme_key);
+    let current_sky = current_moment.sky();
+    let sun_altitude_degrees = current_sky.sun().altitude();
+    let sun_azimuth_degrees = current_sky.sun().azimuth();
 
     debug!(
+        theme_kind = %theme_definition,
+        local_time = %current_moment.local_time().to_rfc3339(),
         sun_altitude = sun_altitude_degrees,
         sun_azimuth = sun_azimuth_degrees,
+        moon_phase = ?current_sky.moon().map(LunarPosition::phase),
+        moon_illumination = ?current_sky.moon().map(LunarPosition::illuminated_fraction),
+        moon_altitude = ?current_sky.moon().map(LunarPosition::altitude),
+        moon_azimuth = ?current_sky.moon().map(LunarPosition::azimuth),
         theme_directory = %theme_directory_path.display(),
-        "Calculated current solar position for wallpaper selection"
+        "Calculated current moment for wallpaper selection"
     );
 
-    let optimal_image_index = WallpaperSetter::find_closest_image(
-        &solar_angle_configuration,
-        sun_altitude_degrees,
-        sun_azimuth_degrees,
-    )
-    .ok_or_else(|| {
-        error!(
-            theme_directory = %theme_directory_path.display(),
-            sun_altitude = sun_altitude_degrees,
-            sun_azimuth = sun_azimuth_degrees,
-            solar_angles_available = solar_angle_configuration.len(),
-            "No suitable wallpaper image found for current solar position"
-        );
-        ThemeError::ImageSolarConfigurationMismatch {
-            expected: solar_angle_configuration.len(),
-            found: 0,
-        }
-    })?;
+    let optimal_choice = theme_definition
+        .select(current_moment, &policy.with_previous(previous_image_index))
+        .ok_or_else(|| {
+            let images_available = theme_definition.image_indices().len();
+            error!(
+                theme_directory = %theme_directory_path.display(),
+                theme_kind = %theme_definition,
+                sun_altitude = sun_altitude_degrees,
+                sun_azimuth = sun_azimuth_degrees,
+                images_available = images_available,
+                "No suitable wallpaper image found for current moment"
+            );
+            ThemeError::NoSuitableImage { images_available }
+        })?;
+
+    SELECTED_IMAGES.with(|selected| {
+        selected
+            .borrow_mut()
+            .insert(theme_key, optimal_choice.primary_index());
+    });
 
     info!(
-        optimal_image_index = optimal_image_index,
+        optimal_choice = ?optimal_choice,
+        previous_image_index = ?previous_image_index,
+        theme_kind = %theme_definition,
         sun_altitude = sun_altitude_degrees,
         sun_azimuth = sun_azimuth_degrees,
-        "Selected optimal wallpaper image for current solar position"
+        "Selected optimal wallpaper image for current moment"
     );
 
-    Ok((optimal_image_index, solar_angle_configuration))
+    Ok(optimal_choice)
 }
 
 /// Core solar theme processing function that updates wallpapers for all monitors
@@ -539,7 +476,7 @@ fn process_solar_theme_cycle(
 ) -> DwallResult<()> {
     debug!(
         auto_detect_color_mode = configuration.auto_detect_color_scheme(),
-        image_format = ?configuration.image_format(),
+        image_formats = ?configuration.image_format_preference(),
         latitude = current_geographic_position.latitude(),
         longitude = current_geographic_position.longitude(),
         "Starting solar theme processing cycle"
@@ -550,13 +487,37 @@ fn process_solar_theme_cycle(
 
     let current_local_time = OffsetDateTime::now_local()?;
     let current_utc_time = current_local_time.utc()?;
-    let current_solar_position = SolarPosition::new(current_geographic_position, &current_utc_time);
+    let solar_model = configuration.solar_model(current_geographic_position);
+    let current_solar_position =
+        SolarPosition::new(current_geographic_position, &current_utc_time, &solar_model);
+
+    // Decided up front so appearance themes and the system color scheme agree
+    let color_scheme_context = load_color_scheme_context(
+        current_geographic_position,
+        &current_local_time,
+        &solar_model,
+    );
+    let solar_based_color_scheme =
+        color_scheme_context.decide(&current_solar_position, &current_local_time);
+
+    let current_moment = SelectionMoment::new(
+        SkyPosition::new(
+            current_solar_position,
+            Some(LunarPosition::new(
+                current_geographic_position,
+                &current_utc_time,
+                solar_model.atmosphere(),
+            )),
+        ),
+        current_local_time,
+        solar_based_color_scheme,
+    );
 
     let mut lock_screen_theme_identifier: Option<String> = None;
     let mut successful_monitor_count = 0;
 
     // Process wallpaper update for each configured monitor
-    for monitor_identifier in available_monitors.keys() {
+    for (monitor_identifier, monitor) in &available_monitors {
         let assigned_theme_id: &str = match monitor_theme_configurations.get(monitor_identifier) {
             Some(theme_id) => theme_id.as_ref(),
             None => {
@@ -584,11 +545,11 @@ fn process_solar_theme_cycle(
         info!(theme_directory = %theme_directory_path.display(), is_customized = is_customized, theme_id = assigned_theme_id, "Using theme directory");
 
         if let Err(processing_error) = update_monitor_solar_wallpaper(
-            configuration.image_format(),
-            monitor_identifier,
+            configuration,
+            monitor,
             assigned_theme_id,
             &theme_directory_path,
-            &current_solar_position,
+            &current_moment,
             wallpaper_manager,
             is_customized,
         ) {
@@ -611,11 +572,17 @@ fn process_solar_theme_cycle(
     {
         let (theme_directory_path, is_customized) =
             get_theme_directory_path(configuration, lock_screen_theme_id);
+        // The lock screen shows on any monitor, so take the variant for the largest
+        let largest_resolution = available_monitors
+            .values()
+            .filter_map(|monitor| monitor.resolution())
+            .max_by_key(Resolution::pixel_count);
         if let Err(lock_screen_error) = apply_lock_screen_solar_wallpaper(
-            configuration.image_format(),
+            configuration,
             lock_screen_theme_id,
+            largest_resolution,
             &theme_directory_path,
-            &current_solar_position,
+            &current_moment,
             is_customized,
         ) {
             warn!(
@@ -626,6 +593,39 @@ fn process_solar_theme_cycle(
         }
     }
 
+    // Optionally update system color scheme based on solar position
+    if configuration.auto_detect_color_scheme() {
+        debug!(
+            previous_color_scheme = ?color_scheme_context.current_scheme(),
+            color_scheme = ?solar_based_color_scheme,
+            sun_altitude = current_solar_position.altitude(),
+            "Automatically updating system color scheme based on solar position"
+        );
+        if let Err(color_scheme_error) = set_color_scheme(solar_based_color_scheme) {
+            warn!(
+                error = %color_scheme_error,
+                "Failed to update system color scheme, continuing with other operations"
+            );
+        }
+    }
+
+    info!(
+        successful_monitors = successful_monitor_count,
+        total_monitors = available_monitors.len(),
+        "Solar theme processing cycle completed"
+    );
+
+    Ok(())
+}
+
+/// Returns the current system color scheme, light if it cannot be read, together
+/// with the thresholds and the day's extreme daylight state, the latter two cached
+/// for 24 hours
+fn load_color_scheme_context(
+    current_geographic_position: &Position,
+    current_local_time: &OffsetDateTime,
+    solar_model: &SolarModel,
+) -> ColorSchemeContext {
     let cache = get_cache();
 
     let threshold_config = match cache.get::<ThresholdConfig>() {
@@ -643,6 +643,7 @@ fn process_solar_theme_cycle(
             let ds = DaylightState::detect(
                 current_geographic_position,
                 current_local_time.date(),
+                solar_model,
                 &threshold_config,
             );
             cache.set(ds, Duration::from_hours(24));
@@ -650,61 +651,110 @@ fn process_solar_theme_cycle(
         }
     };
 
-    // Optionally update system color scheme based on solar position
-    if configuration.auto_detect_color_scheme() {
-        let current_color_scheme = ColorSchemeManager::get_current_scheme()?;
-        let solar_based_color_scheme = determine_color_scheme_with_hysteresis(
-            &current_solar_position,
-            &current_color_scheme,
-            &threshold_config,
-            &current_local_time,
-            &daylight_state,
-        );
-        debug!(
-            color_scheme = ?solar_based_color_scheme,
-            sun_altitude = current_solar_position.altitude(),
-            "Automatically updating system color scheme based on solar position"
+    // Only the hysteresis starts from the current scheme, so a failed read need not
+    // stop the cycle; Windows itself shows the light scheme when the value is missing
+    let current_scheme = ColorSchemeManager::get_current_scheme().unwrap_or_else(|e| {
+        warn!(
+            error = %e,
+            "Failed to read current system color scheme, assuming light"
         );
-        if let Err(color_scheme_error) = set_color_scheme(solar_based_color_scheme) {
-            warn!(
-                error = %color_scheme_error,
-                "Failed to update system color scheme, continuing with other operations"
-            );
+        ColorScheme::Light
+    });
+
+    ColorSchemeContext::new(current_scheme, threshold_config, daylight_state)
+}
+
+/// Predicts how long the daemon can sleep before any monitor's wallpaper or the
+/// system color scheme needs to change, capped by the configured maximum wake interval
+fn schedule_next_solar_theme_cycle(
+    configuration: &Config,
+    current_geographic_position: &Position,
+    wallpaper_manager: &WallpaperSetter,
+) -> DwallResult<Duration> {
+    let current_local_time = OffsetDateTime::now_local()?;
+    let solar_model = configuration.solar_model(current_geographic_position);
+    let scheduler = ChangeScheduler::new(
+        current_geographic_position,
+        solar_model,
+        current_local_time.utc()?,
+        Duration::from_secs(configuration.interval().into()),
+    );
+    let color_scheme_context = load_color_scheme_context(
+        current_geographic_position,
+        &current_local_time,
+        &solar_model,
+    );
+
+    let available_monitors = wallpaper_manager.list_available_monitors()?;
+    let monitor_theme_configurations = configuration.monitor_specific_wallpapers();
+    let active_theme_ids: BTreeSet<&str> = available_monitors
+        .keys()
+        .filter_map(|monitor_id| monitor_theme_configurations.get(monitor_id))
+        .map(String::as_str)
+        .collect();
+
+    let mut next_change: Option<UtcDateTime> = None;
+    let mut record = |candidate: Option<UtcDateTime>| {
+        if let Some(candidate) = candidate {
+            next_change = Some(next_change.map_or(candidate, |current| current.min(candidate)));
+        }
+    };
+
+    for theme_id in active_theme_ids {
+        let (theme_directory_path, _) = get_theme_directory_path(configuration, theme_id);
+        match load_cached_theme_definition(&theme_directory_path) {
+            Ok(theme_definition) => record(scheduler.next_wallpaper_change(
+                &theme_definition,
+                &scheduling_policy(configuration, &theme_directory_path),
+                &color_scheme_context,
+                current_local_time.offset(),
+            )),
+            Err(error) => {
+                warn!(error = %error, theme_id = theme_id, "Failed to load theme configuration for scheduling");
+            }
         }
     }
 
+    if configuration.auto_detect_color_scheme() {
+        record(
+            scheduler.next_color_scheme_change(&color_scheme_context, current_local_time.offset()),
+        );
+    }
+
+    let delay = scheduler.delay_until(next_change);
     info!(
-        successful_monitors = successful_monitor_count,
-        total_monitors = available_monitors.len(),
-        "Solar theme processing cycle completed"
+        next_change = ?next_change.map(|instant| instant.to_string()),
+        sleep_duration_seconds = delay.as_secs(),
+        "Scheduled next solar theme cycle"
     );
 
-    Ok(())
+    Ok(delay)
 }
 
-/// Update solar wallpaper for a specific monitor based on current sun position
+/// Update solar wallpaper for a specific monitor based on current sun and moon positions
 fn update_monitor_solar_wallpaper(
-    image_format: &ImageFormat,
-    monitor_identifier: &str,
+    configuration: &Config,
+    monitor: &DisplayMonitor,
     theme_identifier: &str,
     theme_directory_path: &Path,
-    current_sun_position: &SolarPosition,
+    current_moment: &SelectionMoment,
     wallpaper_manager: &WallpaperSetter,
     is_customized: bool,
 ) -> DwallResult<()> {
-    let (optimal_image_index, _) =
-        find_optimal_solar_wallpaper(theme_directory_path, current_sun_position)?;
-    let wallpaper_file_path = construct_wallpaper_file_path(
+    let optimal_choice = find_optimal_wallpaper(
         theme_directory_path,
-        image_format.as_str(),
-        optimal_image_index,
-        is_customized,
-    );
+        current_moment,
+        configuration.selection_policy(),
+    )?;
+    let theme_images = resolve_theme_images(configuration, theme_directory_path, is_customized)?
+        .for_resolution(monitor.resolution());
+    let wallpaper_file_path = resolve_wallpaper_file_path(&theme_images, optimal_choice)?;
 
     info!(
         wallpaper_path = %wallpaper_file_path.display(),
-        image_index = optimal_image_index,
-        monitor_id = monitor_identifier,
+        image_index = optimal_choice.primary_index(),
+        monitor_id = monitor.device_path(),
+        monitor_resolution = ?monitor.resolution().map(|resolution| resolution.to_string()),
         theme_id = theme_identifier,
         is_customized = is_customized,
         "Selected optimal solar wallpaper for monitor"
@@ -722,10 +772,10 @@ fn update_monitor_solar_wallpaper(
         .into());
     }
 
-    wallpaper_manager.set_monitor_wallpaper(monitor_identifier, &wallpaper_file_path)?;
+    wallpaper_manager.set_monitor_wallpaper(monitor.device_path(), &wallpaper_file_path)?;
 
     debug!(
-        monitor_id = monitor_identifier,
+        monitor_id = monitor.device_path(),
         wallpaper_path = %wallpaper_file_path.display(),
         "Successfully applied solar wallpaper to monitor"
     );
@@ -735,20 +785,23 @@ fn update_monitor_solar_wallpaper(
 
 /// Apply solar wallpaper to lock screen based on theme and current sun position
 fn apply_lock_screen_solar_wallpaper(
-    image_format: &ImageFormat,
+    configuration: &Config,
     theme_identifier: &str,
+    resolution: Option<Resolution>,
     theme_directory_path: &Path,
-    current_sun_position: &SolarPosition,
+    current_moment: &SelectionMoment,
     is_customized: bool,
 ) -> DwallResult<()> {
-    match find_optimal_solar_wallpaper(theme_directory_path, current_sun_position) {
-        Ok((optimal_image_index, _)) => {
-            let wallpaper_file_path = construct_wallpaper_file_path(
-                theme_directory_path,
-                image_format.as_str(),
-                optimal_image_index,
-                is_customized,
-            );
+    match find_optimal_wallpaper(
+        theme_directory_path,
+        current_moment,
+        configuration.selection_policy(),
+    ) {
+        Ok(optimal_choice) => {
+            let theme_images =
+                resolve_theme_images(configuration, theme_directory_path, is_customized)?
+                    .for_resolution(resolution);
+            let wallpaper_file_path = resolve_wallpaper_file_path(&theme_images, optimal_choice)?;
 
             if wallpaper_file_path.exists() {
                 info!(
@@ -799,7 +852,10 @@ pub async fn apply_solar_theme(configuration: Config) -> DwallResult<()> {
     solar_theme_processor.start_solar_update_loop()
 }
 
-fn get_theme_directory_path(configuration: &Config, theme_identifier: &str) -> (PathBuf, bool) {
+pub(crate) fn get_theme_directory_path(
+    configuration: &Config,
+    theme_identifier: &str,
+) -> (PathBuf, bool) {
     let path = configuration.themes_directory().join(theme_identifier);
     if path.exists() {
         return (path, false);
@@ -811,3 +867,34 @@ fn get_theme_directory_path(configuration: &Config, theme_identifier: &str) -> (
 
     (path, true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+
+    #[test]
+    fn scheduling_sticks_to_image_selected_under_another_spelling() {
+        let directory = scratch_directory("processor-sticky");
+        let theme_directory = directory.join("Catalina");
+        std::fs::create_dir_all(&theme_directory).unwrap();
+        let key = theme_cache_key(&theme_directory).unwrap();
+        SELECTED_IMAGES.with(|selected| selected.borrow_mut().insert(key, 3));
+
+        let configuration = Config::default();
+        let expected = configuration.selection_policy().with_previous(Some(3));
+        for spelling in [
+            theme_directory.join("."),
+            directory.join("./Catalina"),
+            theme_directory.join("../Catalina"),
+        ] {
+            assert_eq!(scheduling_policy(&configuration, &spelling), expected);
+        }
+        assert_eq!(
+            scheduling_policy(&configuration, &directory.join("Missing")),
+            configuration.selection_policy()
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
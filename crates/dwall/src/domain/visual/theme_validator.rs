@@ -0,0 +1,1154 @@
+//! Theme validation
+//!
+//! [`ThemeValidator::report`] checks a theme directory in one pass and lists every
+//! problem it finds, each with a [`Severity`]. [`ThemeValidator::validate`] reduces
+//! the report to pass or fail for callers that only need to know whether a theme can
+//! be applied.
+//!
+//! Image files are opened, not just looked up: their content must be in the theme's
+//! format, and [`ImageCheck::Decode`] also finds truncated or corrupt files. Each
+//! resolution variant of the images is checked as a full image set of its own.
+//!
+//! No decoder for AVIF or JPEG XL is built in, so files in those formats are only
+//! checked for the format's signature; their dimensions and data are not inspected.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use image::{GenericImageView, ImageReader};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DwallResult,
+    config::ImageFormat,
+    domain::visual::{
+        ThemeError,
+        theme_definition::{ThemeDefinition, configuration_path, invalid_solar_angle_fields},
+        theme_images::{ThemeImages, resolution_variants},
+    },
+};
+
+/// Extensions of files counted as theme images, whatever format the theme uses
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "avif", "jxl", "heic", "heif", "bmp", "gif", "tif", "tiff",
+];
+
+/// How serious a validation issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The theme can be applied, but probably does not look as intended
+    Warning,
+    /// The theme cannot be applied until the issue is fixed
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// How thoroughly image files are inspected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageCheck {
+    /// Read each file's header for its real format and dimensions
+    #[default]
+    Headers,
+    /// Decode each file completely, which also finds truncated and corrupt data
+    ///
+    /// AVIF and JPEG XL files cannot be decoded and are checked by signature only.
+    Decode,
+}
+
+/// A problem found in a theme directory
+///
+/// Image indices are zero-based, as in the theme description; the image file of index
+/// `n` is named `n + 1`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationIssue {
+    /// The theme directory does not exist
+    ThemeDirectoryNotFound { path: PathBuf },
+    /// None of `solar.json`, `time.json` or `appearance.json` exists
+    ConfigurationMissing,
+    /// The theme description cannot be read or parsed
+    ConfigurationUnreadable { file: String, reason: String },
+    /// The theme description lists no images
+    EmptyConfiguration { file: String },
+    /// A solar angle is out of range or not a number
+    InvalidSolarAngle {
+        index: u8,
+        field: &'static str,
+        value: f64,
+    },
+    /// The theme description refers to the same image more than once
+    DuplicateIndex { index: u8, occurrences: usize },
+    /// The directory holding the image files does not exist
+    ImagesDirectoryMissing { path: PathBuf },
+    /// A referenced image has no file
+    MissingImage { index: u8, path: PathBuf },
+    /// A referenced image only exists in another format or under another extension
+    ImageFormatMismatch {
+        index: u8,
+        path: PathBuf,
+        expected: PathBuf,
+    },
+    /// An image file's content is in another format than its name says
+    ImageContentMismatch {
+        index: u8,
+        path: PathBuf,
+        actual: String,
+        expected: String,
+    },
+    /// An image file cannot be read as an image
+    CorruptImage {
+        index: u8,
+        path: PathBuf,
+        reason: String,
+    },
+    /// An image's resolution differs from the one most images share
+    ///
+    /// Blending resizes such an image, and switching to it shifts the picture.
+    InconsistentResolution {
+        index: u8,
+        path: PathBuf,
+        width: u32,
+        height: u32,
+        expected_width: u32,
+        expected_height: u32,
+    },
+    /// An image file the theme description never refers to
+    UnreferencedImage { path: PathBuf },
+}
+
+impl ValidationIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationIssue::DuplicateIndex { .. }
+            | ValidationIssue::InconsistentResolution { .. }
+            | ValidationIssue::UnreferencedImage { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::ThemeDirectoryNotFound { path } => {
+                write!(f, "Theme directory '{}' does not exist", path.display())
+            }
+            ValidationIssue::ConfigurationMissing => write!(
+                f,
+                "Theme configuration file 'solar.json', 'time.json' or 'appearance.json' is missing"
+            ),
+            ValidationIssue::ConfigurationUnreadable { file, reason } => {
+                write!(f, "'{file}' cannot be read: {reason}")
+            }
+            ValidationIssue::EmptyConfiguration { file } => {
+                write!(f, "'{file}' does not list any images")
+            }
+            ValidationIssue::InvalidSolarAngle {
+                index,
+                field,
+                value,
+            } => write!(
+                f,
+                "Solar angle of image {index} has an invalid {field}: {value}"
+            ),
+            ValidationIssue::DuplicateIndex { index, occurrences } => {
+                write!(f, "Image {index} is listed {occurrences} times")
+            }
+            ValidationIssue::ImagesDirectoryMissing { path } => {
+                write!(f, "Images directory '{}' does not exist", path.display())
+            }
+            ValidationIssue::MissingImage { index, path } => {
+                write!(f, "Image {index} is missing: '{}'", path.display())
+            }
+            ValidationIssue::ImageFormatMismatch {
+                index,
+                path,
+                expected,
+            } => write!(
+                f,
+                "Image {index} is '{}' but the theme expects '{}'",
+                path.display(),
+                expected.display()
+            ),
+            ValidationIssue::ImageContentMismatch {
+                index,
+                path,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "Image {index} '{}' holds {actual} data, expected {expected}",
+                path.display()
+            ),
+            ValidationIssue::CorruptImage {
+                index,
+                path,
+                reason,
+            } => write!(
+                f,
+                "Image {index} '{}' cannot be read: {reason}",
+                path.display()
+            ),
+            ValidationIssue::InconsistentResolution {
+                index,
+                path,
+                width,
+                height,
+                expected_width,
+                expected_height,
+            } => write!(
+                f,
+                "Image {index} '{}' is {width}x{height} while most images are {expected_width}x{expected_height}",
+                path.display()
+            ),
+            ValidationIssue::UnreferencedImage { path } => {
+                write!(
+                    f,
+                    "Image file '{}' is not used by the theme",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+/// An issue with its severity and message, as reported to the settings UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationItem {
+    severity: Severity,
+    message: String,
+    #[serde(flatten)]
+    issue: ValidationIssue,
+}
+
+impl ValidationItem {
+    fn new(issue: ValidationIssue) -> Self {
+        Self {
+            severity: issue.severity(),
+            message: issue.to_string(),
+            issue,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn issue(&self) -> &ValidationIssue {
+        &self.issue
+    }
+}
+
+/// Format and dimensions read from a theme image
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageDetails {
+    index: u8,
+    path: PathBuf,
+    /// Format of the file content, named by its usual extension such as `jpg`
+    format: String,
+    width: u32,
+    height: u32,
+}
+
+impl ImageDetails {
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// Every issue found in one theme, in the order the checks ran
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    theme_id: String,
+    items: Vec<ValidationItem>,
+    /// Referenced images whose header could be read, by index
+    images: Vec<ImageDetails>,
+}
+
+impl ValidationReport {
+    fn new(theme_id: &str) -> Self {
+        Self {
+            theme_id: theme_id.to_string(),
+            items: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn theme_id(&self) -> &str {
+        &self.theme_id
+    }
+
+    pub fn items(&self) -> &[ValidationItem] {
+        &self.items
+    }
+
+    pub fn images(&self) -> &[ImageDetails] {
+        &self.images
+    }
+
+    /// Items that keep the theme from being applied
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationItem> {
+        self.items
+            .iter()
+            .filter(|item| item.severity == Severity::Error)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    fn push(&mut self, issue: ValidationIssue) {
+        debug!(
+            theme_id = self.theme_id,
+            severity = %issue.severity(),
+            issue = %issue,
+            "Theme validation issue"
+        );
+        self.items.push(ValidationItem::new(issue));
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors = self.errors().count();
+        write!(
+            f,
+            "Theme '{}': {errors} errors, {} warnings",
+            self.theme_id,
+            self.items.len() - errors
+        )?;
+        for item in &self.items {
+            write!(f, "\n{}: {}", item.severity, item.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Theme validation utilities for wallpaper themes
+pub struct ThemeValidator;
+
+impl ThemeValidator {
+    /// Validates if a theme exists and has proper configuration and image files
+    ///
+    /// Image headers are read but not decoded. Fails with the full [`ValidationReport`]
+    /// when it contains errors; warnings are only logged.
+    pub fn validate(
+        themes_directory: &Path,
+        theme_identifier: &str,
+        is_customized: bool,
+        image_format: &ImageFormat,
+    ) -> DwallResult<()> {
+        trace!(
+            theme_id = theme_identifier,
+            themes_directory = %themes_directory.display(),
+            "Starting theme validation"
+        );
+
+        let theme_directory_path = themes_directory.join(theme_identifier);
+        if !theme_directory_path.exists() {
+            warn!(
+                theme_id = theme_identifier,
+                theme_path = %theme_directory_path.display(),
+                "Theme directory not found"
+            );
+            return Err(ThemeError::ThemeDirectoryNotFound(theme_identifier.to_string()).into());
+        }
+
+        let report = Self::report(
+            themes_directory,
+            theme_identifier,
+            is_customized,
+            image_format,
+            ImageCheck::Headers,
+        );
+        if report.has_errors() {
+            warn!(
+                theme_id = theme_identifier,
+                errors = report.errors().count(),
+                issues = report.items().len(),
+                "Theme validation failed"
+            );
+            return Err(ThemeError::ValidationFailed(report).into());
+        }
+
+        info!(
+            theme_id = theme_identifier,
+            warnings = report.items().len(),
+            is_customized = is_customized,
+            "Theme validation completed successfully"
+        );
+        Ok(())
+    }
+
+    /// Checks the theme and lists every issue found
+    ///
+    /// Checks that cannot run, such as image checks without a readable description,
+    /// are skipped rather than reported again.
+    pub fn report(
+        themes_directory: &Path,
+        theme_identifier: &str,
+        is_customized: bool,
+        image_format: &ImageFormat,
+        image_check: ImageCheck,
+    ) -> ValidationReport {
+        let mut report = ValidationReport::new(theme_identifier);
+
+        let theme_directory = themes_directory.join(theme_identifier);
+        if !theme_directory.is_dir() {
+            report.push(ValidationIssue::ThemeDirectoryNotFound {
+                path: theme_directory,
+            });
+            return report;
+        }
+
+        let Some(configuration_path) = configuration_path(&theme_directory) else {
+            report.push(ValidationIssue::ConfigurationMissing);
+            return report;
+        };
+        let file = configuration_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let definition = match ThemeDefinition::read(&theme_directory) {
+            Ok(definition) => definition,
+            Err(e) => {
+                report.push(ValidationIssue::ConfigurationUnreadable {
+                    file,
+                    reason: e.to_string(),
+                });
+                return report;
+            }
+        };
+
+        if let ThemeDefinition::Solar(angles) = &definition {
+            for (index, field, value) in invalid_solar_angle_fields(angles) {
+                report.push(ValidationIssue::InvalidSolarAngle {
+                    index,
+                    field,
+                    value,
+                });
+            }
+        }
+
+        let image_indices = definition.image_indices();
+        if image_indices.is_empty() {
+            report.push(ValidationIssue::EmptyConfiguration { file });
+            return report;
+        }
+
+        let mut occurrences: BTreeMap<u8, usize> = BTreeMap::new();
+        for index in image_indices {
+            *occurrences.entry(index).or_default() += 1;
+        }
+        for (&index, &occurrences) in &occurrences {
+            if occurrences > 1 {
+                report.push(ValidationIssue::DuplicateIndex { index, occurrences });
+            }
+        }
+
+        let images = ThemeImages::new(&theme_directory, image_format.clone(), is_customized);
+        let variants = resolution_variants(images.directory());
+        // Images outside the variants are optional once a theme has variants
+        if variants.is_empty() || contains_image_files(images.directory()) {
+            Self::check_image_files(
+                &mut report,
+                images.directory(),
+                occurrences.keys().copied(),
+                image_format,
+                image_check,
+            );
+        }
+        for variant in variants {
+            Self::check_image_files(
+                &mut report,
+                &images.directory().join(variant.to_string()),
+                occurrences.keys().copied(),
+                image_format,
+                image_check,
+            );
+        }
+
+        report
+    }
+
+    /// Matches the image files in `images_directory` against the referenced indices
+    fn check_image_files(
+        report: &mut ValidationReport,
+        images_directory: &Path,
+        referenced_indices: impl Iterator<Item = u8>,
+        image_format: &ImageFormat,
+        image_check: ImageCheck,
+    ) {
+        if !images_directory.is_dir() {
+            report.push(ValidationIssue::ImagesDirectoryMissing {
+                path: images_directory.to_path_buf(),
+            });
+            return;
+        }
+
+        let first_image = report.images.len();
+
+        // Image files named by a number, whatever their format, and all other image files
+        let mut numbered_files: BTreeMap<u8, Vec<PathBuf>> = BTreeMap::new();
+        let mut other_files = Vec::new();
+        match fs::read_dir(images_directory) {
+            Ok(entries) => {
+                for path in entries.flatten().map(|entry| entry.path()) {
+                    if !path.is_file() || !is_image_file(&path) {
+                        continue;
+                    }
+
+                    match image_number_of(&path) {
+                        Some(number) => numbered_files.entry(number).or_default().push(path),
+                        None => other_files.push(path),
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(
+                    images_directory = %images_directory.display(),
+                    error = %e,
+                    "Failed to list theme images"
+                );
+            }
+        }
+
+        for index in referenced_indices {
+            let expected_file_name = format!("{}.{}", u16::from(index) + 1, image_format.as_str());
+            let expected = images_directory.join(&expected_file_name);
+
+            let mut candidates = numbered_files.remove(&index).unwrap_or_default();
+            candidates.retain(|path| {
+                !path
+                    .file_name()
+                    .is_some_and(|file_name| file_name.eq_ignore_ascii_case(&expected_file_name))
+            });
+            candidates.sort();
+
+            if expected.is_file() {
+                Self::inspect_image(report, index, expected, image_format, image_check);
+                other_files.extend(candidates);
+            } else if candidates.is_empty() {
+                report.push(ValidationIssue::MissingImage {
+                    index,
+                    path: expected,
+                });
+            } else {
+                let path = candidates.remove(0);
+                report.push(ValidationIssue::ImageFormatMismatch {
+                    index,
+                    path,
+                    expected,
+                });
+                other_files.extend(candidates);
+            }
+        }
+
+        Self::check_resolutions(report, first_image);
+
+        other_files.extend(numbered_files.into_values().flatten());
+        other_files.sort();
+        for path in other_files {
+            report.push(ValidationIssue::UnreferencedImage { path });
+        }
+    }
+
+    /// Reads the image at `path`, reporting content that does not match `image_format`
+    fn inspect_image(
+        report: &mut ValidationReport,
+        index: u8,
+        path: PathBuf,
+        image_format: &ImageFormat,
+        image_check: ImageCheck,
+    ) {
+        let reader = match ImageReader::open(&path).and_then(ImageReader::with_guessed_format) {
+            Ok(reader) => reader,
+            Err(e) => {
+                report.push(ValidationIssue::CorruptImage {
+                    index,
+                    path,
+                    reason: e.to_string(),
+                });
+                return;
+            }
+        };
+        let Some(expected_format) = image_format.codec() else {
+            // The image library does not know JPEG XL; only its signature is checked
+            if !has_jpeg_xl_signature(&path) {
+                report.push(ValidationIssue::ImageContentMismatch {
+                    index,
+                    path,
+                    actual: reader
+                        .format()
+                        .map_or_else(|| "unknown".to_string(), image_format_name),
+                    expected: image_format.as_str().to_string(),
+                });
+            }
+            return;
+        };
+        let Some(format) = reader.format() else {
+            report.push(ValidationIssue::CorruptImage {
+                index,
+                path,
+                reason: "unrecognized image format".to_string(),
+            });
+            return;
+        };
+        let format_name = image_format_name(format);
+
+        let matches_format = format == expected_format;
+        if !matches_format {
+            report.push(ValidationIssue::ImageContentMismatch {
+                index,
+                path: path.clone(),
+                actual: format_name.clone(),
+                expected: image_format_name(expected_format),
+            });
+        }
+
+        // Without a decoder, only the signature could be checked
+        if !format.reading_enabled() {
+            return;
+        }
+
+        let dimensions = match image_check {
+            ImageCheck::Headers => reader.into_dimensions().map_err(|e| e.to_string()),
+            ImageCheck::Decode => reader
+                .decode()
+                .map_err(|e| e.to_string())
+                .and_then(|image| match format {
+                    image::ImageFormat::Jpeg if !is_complete_jpeg(&path) => {
+                        Err("image data ends before the end of image marker".to_string())
+                    }
+                    _ => Ok(image.dimensions()),
+                }),
+        };
+        match dimensions {
+            Ok((width, height)) => report.images.push(ImageDetails {
+                index,
+                path,
+                format: format_name,
+                width,
+                height,
+            }),
+            // Other formats may simply not be supported; the mismatch is reported already
+            Err(reason) if matches_format => report.push(ValidationIssue::CorruptImage {
+                index,
+                path,
+                reason,
+            }),
+            Err(_) => {}
+        }
+    }
+
+    /// Reports images from `first_image` on whose resolution differs from the most
+    /// common one among them
+    fn check_resolutions(report: &mut ValidationReport, first_image: usize) {
+        let mut resolutions: Vec<((u32, u32), usize)> = Vec::new();
+        for image in &report.images[first_image..] {
+            match resolutions
+                .iter_mut()
+                .find(|(resolution, _)| *resolution == image.dimensions())
+            {
+                Some((_, count)) => *count += 1,
+                None => resolutions.push((image.dimensions(), 1)),
+            }
+        }
+
+        // `max_by_key` keeps the last maximum; reversing makes ties go to the lowest index
+        let Some(&((expected_width, expected_height), _)) =
+            resolutions.iter().rev().max_by_key(|(_, count)| *count)
+        else {
+            return;
+        };
+
+        let outliers: Vec<ValidationIssue> = report.images[first_image..]
+            .iter()
+            .filter(|image| image.dimensions() != (expected_width, expected_height))
+            .map(|image| ValidationIssue::InconsistentResolution {
+                index: image.index,
+                path: image.path.clone(),
+                width: image.width,
+                height: image.height,
+                expected_width,
+                expected_height,
+            })
+            .collect();
+        for issue in outliers {
+            report.push(issue);
+        }
+    }
+}
+
+/// Usual extension of `format`, such as `jpg`
+fn image_format_name(format: image::ImageFormat) -> String {
+    format.extensions_str().first().map_or_else(
+        || format!("{format:?}").to_lowercase(),
+        |name| name.to_string(),
+    )
+}
+
+/// Whether the JPEG file at `path` has an end of image marker after its last scan
+///
+/// The decoder fills in missing scan data instead of failing, so a file cut short
+/// decodes without error.
+fn is_complete_jpeg(path: &Path) -> bool {
+    const START_OF_SCAN: [u8; 2] = [0xFF, 0xDA];
+    const END_OF_IMAGE: [u8; 2] = [0xFF, 0xD9];
+
+    let Ok(content) = fs::read(path) else {
+        return false;
+    };
+    // Scan data escapes 0xFF bytes, so the markers cannot appear inside it
+    content
+        .windows(2)
+        .rposition(|window| window == START_OF_SCAN)
+        .is_some_and(|start| {
+            content[start..]
+                .windows(2)
+                .any(|window| window == END_OF_IMAGE)
+        })
+}
+
+/// Whether the file at `path` starts like a JPEG XL codestream or container
+fn has_jpeg_xl_signature(path: &Path) -> bool {
+    const CODESTREAM: &[u8] = &[0xFF, 0x0A];
+    const CONTAINER: &[u8] = &[
+        0x00, 0x00, 0x00, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+    ];
+
+    let mut header = [0; 12];
+    let Ok(read) = File::open(path).and_then(|mut file| file.read(&mut header)) else {
+        return false;
+    };
+    header[..read].starts_with(CODESTREAM) || header[..read].starts_with(CONTAINER)
+}
+
+fn contains_image_files(directory: &Path) -> bool {
+    fs::read_dir(directory).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let path = entry.path();
+            path.is_file() && is_image_file(&path)
+        })
+    })
+}
+
+fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// Zero-based image index of a file named `<index + 1>.<extension>`
+pub(crate) fn image_number_of(path: &Path) -> Option<u8> {
+    let number: u16 = path.file_stem()?.to_str()?.parse().ok()?;
+    number.checked_sub(1)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+
+    /// Writes a noisy image, in the format given by the extension of `path`
+    fn write_image(path: &Path, width: u32, height: u32) {
+        RgbImage::from_fn(width, height, |x, y| {
+            let value = (x * 7 + y * 13) as u8 ^ (x * y) as u8;
+            Rgb([value, value.wrapping_mul(3), value.wrapping_add(90)])
+        })
+        .save(path)
+        .unwrap();
+    }
+
+    fn kinds(report: &ValidationReport) -> Vec<(Severity, String)> {
+        report
+            .items()
+            .iter()
+            .map(|item| {
+                let kind = serde_json::to_value(item.issue()).unwrap()["kind"]
+                    .as_str()
+                    .unwrap()
+                    .to_string();
+                (item.severity(), kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_every_issue_in_one_pass() {
+        let themes_directory = scratch_directory("validator-issues");
+        let theme_directory = themes_directory.join("theme");
+        let images_directory = theme_directory.join("images");
+        fs::create_dir_all(&images_directory).unwrap();
+        fs::write(
+            theme_directory.join("solar.json"),
+            r#"[
+                {"index": 0, "altitude": 95.0, "azimuth": 90.0},
+                {"index": 1, "altitude": 10.0, "azimuth": 400.0},
+                {"index": 1, "altitude": -10.0, "azimuth": 270.0},
+                {"index": 2, "altitude": 0.0, "azimuth": 180.0},
+                {"index": 3, "altitude": 0.0, "azimuth": 0.0}
+            ]"#,
+        )
+        .unwrap();
+        for file_name in ["1.jpg", "3.png", "5.jpg", "cover.jpg"] {
+            write_image(&images_directory.join(file_name), 4, 2);
+        }
+        fs::write(images_directory.join("notes.txt"), b"").unwrap();
+
+        let report = ThemeValidator::report(
+            &themes_directory,
+            "theme",
+            true,
+            &ImageFormat::Jpeg,
+            ImageCheck::Headers,
+        );
+        assert_eq!(
+            kinds(&report),
+            [
+                (Severity::Error, "invalid_solar_angle".to_string()),
+                (Severity::Error, "invalid_solar_angle".to_string()),
+                (Severity::Warning, "duplicate_index".to_string()),
+                (Severity::Error, "missing_image".to_string()),
+                (Severity::Error, "image_format_mismatch".to_string()),
+                (Severity::Error, "missing_image".to_string()),
+                (Severity::Warning, "unreferenced_image".to_string()),
+                (Severity::Warning, "unreferenced_image".to_string()),
+            ]
+        );
+        assert_eq!(
+            report.items()[4].issue(),
+            &ValidationIssue::ImageFormatMismatch {
+                index: 2,
+                path: images_directory.join("3.png"),
+                expected: images_directory.join("3.jpg"),
+            }
+        );
+        assert_eq!(report.errors().count(), 5);
+
+        let error = ThemeValidator::validate(&themes_directory, "theme", true, &ImageFormat::Jpeg)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("5 errors, 3 warnings"),
+            "unexpected error: {error}"
+        );
+
+        fs::remove_dir_all(&themes_directory).unwrap();
+    }
+
+    #[test]
+    fn reports_missing_and_empty_configuration() {
+        let themes_directory = scratch_directory("validator-configuration");
+        let theme_directory = themes_directory.join("theme");
+        fs::create_dir_all(&theme_directory).unwrap();
+        let report = |theme_id| {
+            ThemeValidator::report(
+                &themes_directory,
+                theme_id,
+                false,
+                &ImageFormat::Png,
+                ImageCheck::Headers,
+            )
+        };
+
+        assert_eq!(
+            kinds(&report("other")),
+            [(Severity::Error, "theme_directory_not_found".to_string())]
+        );
+        assert_eq!(
+            kinds(&report("theme")),
+            [(Severity::Error, "configuration_missing".to_string())]
+        );
+
+        fs::write(theme_directory.join("solar.json"), "[]").unwrap();
+        assert_eq!(
+            report("theme").items()[0].issue(),
+            &ValidationIssue::EmptyConfiguration {
+                file: "solar.json".to_string()
+            }
+        );
+
+        fs::write(theme_directory.join("solar.json"), "{").unwrap();
+        assert_eq!(
+            kinds(&report("theme")),
+            [(Severity::Error, "configuration_unreadable".to_string())]
+        );
+
+        fs::remove_dir_all(&themes_directory).unwrap();
+    }
+
+    #[test]
+    fn warnings_do_not_fail_validation() {
+        let themes_directory = scratch_directory("validator-warnings");
+        let theme_directory = themes_directory.join("theme");
+        let images_directory = theme_directory.join("png");
+        fs::create_dir_all(&images_directory).unwrap();
+        fs::write(
+            theme_directory.join("time.json"),
+            r#"[{"index": 0, "time": "06:00"}, {"index": 0, "time": "18:00"}]"#,
+        )
+        .unwrap();
+        for file_name in ["1.png", "2.png"] {
+            write_image(&images_directory.join(file_name), 4, 2);
+        }
+
+        let report = ThemeValidator::report(
+            &themes_directory,
+            "theme",
+            false,
+            &ImageFormat::Png,
+            ImageCheck::Headers,
+        );
+        assert_eq!(
+            kinds(&report),
+            [
+                (Severity::Warning, "duplicate_index".to_string()),
+                (Severity::Warning, "unreferenced_image".to_string()),
+            ]
+        );
+        assert!(!report.has_errors());
+        ThemeValidator::validate(&themes_directory, "theme", false, &ImageFormat::Png).unwrap();
+
+        fs::remove_dir_all(&themes_directory).unwrap();
+    }
+
+    #[test]
+    fn checks_each_resolution_variant() {
+        let themes_directory = scratch_directory("validator-variants");
+        let theme_directory = themes_directory.join("theme");
+        fs::create_dir_all(theme_directory.join("png/8x4")).unwrap();
+        fs::create_dir_all(theme_directory.join("png/4x8")).unwrap();
+        fs::write(
+            theme_directory.join("time.json"),
+            r#"[{"index": 0, "time": "06:00"}, {"index": 1, "time": "18:00"}]"#,
+        )
+        .unwrap();
+        write_image(&theme_directory.join("png/8x4/1.png"), 8, 4);
+        write_image(&theme_directory.join("png/8x4/2.png"), 8, 4);
+        write_image(&theme_directory.join("png/4x8/1.png"), 4, 8);
+
+        let report = ThemeValidator::report(
+            &themes_directory,
+            "theme",
+            false,
+            &ImageFormat::Png,
+            ImageCheck::Headers,
+        );
+        // Variants differ in size from each other without being inconsistent
+        assert_eq!(
+            report
+                .items()
+                .iter()
+                .map(ValidationItem::issue)
+                .collect::<Vec<_>>(),
+            [&ValidationIssue::MissingImage {
+                index: 1,
+                path: theme_directory.join("png/4x8/2.png"),
+            }]
+        );
+        assert_eq!(report.images().len(), 3);
+
+        fs::remove_dir_all(&themes_directory).unwrap();
+    }
+
+    #[test]
+    fn checks_webp_and_jpeg_xl_images() {
+        let themes_directory = scratch_directory("validator-formats");
+        let theme_directory = themes_directory.join("theme");
+        fs::create_dir_all(theme_directory.join("webp")).unwrap();
+        fs::create_dir_all(theme_directory.join("jxl")).unwrap();
+        fs::write(
+            theme_directory.join("time.json"),
+            r#"[{"index": 0, "time": "06:00"}, {"index": 1, "time": "18:00"}]"#,
+        )
+        .unwrap();
+        write_image(&theme_directory.join("webp/1.webp"), 4, 2);
+        write_image(&theme_directory.join("webp/2.webp"), 4, 2);
+        fs::write(theme_directory.join("jxl/1.jxl"), [0xFF, 0x0A, 0x00, 0x10]).unwrap();
+        write_image(&theme_directory.join("jxl/2.png"), 4, 2);
+        fs::rename(
+            theme_directory.join("jxl/2.png"),
+            theme_directory.join("jxl/2.jxl"),
+        )
+        .unwrap();
+
+        let report = ThemeValidator::report(
+            &themes_directory,
+            "theme",
+            false,
+            &ImageFormat::Webp,
+            ImageCheck::Decode,
+        );
+        assert!(report.items().is_empty(), "unexpected issues: {report}");
+        assert_eq!(report.images().len(), 2);
+
+        let report = ThemeValidator::report(
+            &themes_directory,
+            "theme",
+            false,
+            &ImageFormat::Jxl,
+            ImageCheck::Decode,
+        );
+        assert_eq!(
+            report
+                .items()
+                .iter()
+                .map(ValidationItem::issue)
+                .collect::<Vec<_>>(),
+            [&ValidationIssue::ImageContentMismatch {
+                index: 1,
+                path: theme_directory.join("jxl/2.jxl"),
+                actual: "png".to_string(),
+                expected: "jxl".to_string(),
+            }]
+        );
+
+        fs::remove_dir_all(&themes_directory).unwrap();
+    }
+
+    #[test]
+    fn inspects_image_content() {
+        let themes_directory = scratch_directory("validator-content");
+        let theme_directory = themes_directory.join("theme");
+        let images_directory = theme_directory.join("images");
+        fs::create_dir_all(&images_directory).unwrap();
+        fs::write(
+            theme_directory.join("time.json"),
+            r#"[
+                {"index": 0, "time": "00:00"},
+                {"index": 1, "time": "04:00"},
+                {"index": 2, "time": "08:00"},
+                {"index": 3, "time": "12:00"},
+                {"index": 4, "time": "16:00"},
+                {"index": 5, "time": "20:00"}
+            ]"#,
+        )
+        .unwrap();
+        write_image(&images_directory.join("1.jpg"), 64, 32);
+        write_image(&images_directory.join("2.jpg"), 64, 32);
+        // A PNG renamed to JPEG
+        write_image(&images_directory.join("3.png"), 64, 32);
+        fs::rename(
+            images_directory.join("3.png"),
+            images_directory.join("3.jpg"),
+        )
+        .unwrap();
+        // A download cut short
+        let complete = fs::read(images_directory.join("1.jpg")).unwrap();
+        fs::write(
+            images_directory.join("4.jpg"),
+            &complete[..complete.len() / 2],
+        )
+        .unwrap();
+        write_image(&images_directory.join("5.jpg"), 32, 16);
+        fs::write(images_directory.join("6.jpg"), b"not an image").unwrap();
+
+        let report = |image_check| {
+            ThemeValidator::report(
+                &themes_directory,
+                "theme",
+                true,
+                &ImageFormat::Jpeg,
+                image_check,
+            )
+        };
+
+        let headers = report(ImageCheck::Headers);
+        assert_eq!(
+            kinds(&headers),
+            [
+                (Severity::Error, "image_content_mismatch".to_string()),
+                (Severity::Error, "corrupt_image".to_string()),
+                (Severity::Warning, "inconsistent_resolution".to_string()),
+            ]
+        );
+        assert_eq!(
+            headers.items()[0].issue(),
+            &ValidationIssue::ImageContentMismatch {
+                index: 2,
+                path: images_directory.join("3.jpg"),
+                actual: "png".to_string(),
+                expected: "jpg".to_string(),
+            }
+        );
+        assert_eq!(
+            headers.items()[2].issue(),
+            &ValidationIssue::InconsistentResolution {
+                index: 4,
+                path: images_directory.join("5.jpg"),
+                width: 32,
+                height: 16,
+                expected_width: 64,
+                expected_height: 32,
+            }
+        );
+        // The truncated file still has a complete header
+        let details: Vec<(u8, &str, (u32, u32))> = headers
+            .images()
+            .iter()
+            .map(|image| (image.index(), image.format(), image.dimensions()))
+            .collect();
+        assert_eq!(
+            details,
+            [
+                (0, "jpg", (64, 32)),
+                (1, "jpg", (64, 32)),
+                (2, "png", (64, 32)),
+                (3, "jpg", (64, 32)),
+                (4, "jpg", (32, 16)),
+            ]
+        );
+
+        let decoded = report(ImageCheck::Decode);
+        assert_eq!(
+            kinds(&decoded),
+            [
+                (Severity::Error, "image_content_mismatch".to_string()),
+                (Severity::Error, "corrupt_image".to_string()),
+                (Severity::Error, "corrupt_image".to_string()),
+                (Severity::Warning, "inconsistent_resolution".to_string()),
+            ]
+        );
+        assert!(matches!(
+            decoded.items()[1].issue(),
+            ValidationIssue::CorruptImage { index: 3, .. }
+        ));
+
+        fs::remove_dir_all(&themes_directory).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! Time-of-day themes, which show each image from a fixed local time
+//!
+//! A `time.json` file lists when each image starts, either as a local clock time
+//! (`"07:30"`) or as a fraction of the day (`0.3125`). An image stays up until the
+//! next entry starts, wrapping around midnight.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use time::OffsetDateTime;
+
+use crate::domain::visual::wallpaper::{BLEND_STEPS, WallpaperChoice};
+
+const SECONDS_PER_DAY: u32 = 24 * 3600;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TimeOfDayError {
+    #[error("Invalid time of day '{0}', expected HH:MM or HH:MM:SS")]
+    InvalidClockTime(String),
+    #[error("Invalid fraction of the day {0}, expected a value in [0, 1)")]
+    InvalidFraction(f64),
+}
+
+/// Local wall clock time, in seconds since midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeOfDay(u32);
+
+impl TimeOfDay {
+    pub fn from_hms(hour: u8, minute: u8, second: u8) -> Option<Self> {
+        (hour < 24 && minute < 60 && second < 60)
+            .then(|| Self(u32::from(hour) * 3600 + u32::from(minute) * 60 + u32::from(second)))
+    }
+
+    /// Time at `fraction` of the day, rounded to the second
+    pub fn from_fraction(fraction: f64) -> Result<Self, TimeOfDayError> {
+        if !(0.0..1.0).contains(&fraction) {
+            return Err(TimeOfDayError::InvalidFraction(fraction));
+        }
+        let seconds = (fraction * f64::from(SECONDS_PER_DAY)).round() as u32;
+        Ok(Self(seconds % SECONDS_PER_DAY))
+    }
+
+    /// Wall clock time of `local_time`
+    pub(crate) fn of(local_time: &OffsetDateTime) -> Self {
+        Self(
+            u32::from(local_time.hour()) * 3600
+                + u32::from(local_time.minute()) * 60
+                + u32::from(local_time.second()),
+        )
+    }
+
+    pub fn seconds_since_midnight(&self) -> u32 {
+        self.0
+    }
+
+    /// Seconds from `self` forward to `later`, wrapping around midnight
+    fn seconds_until(&self, later: TimeOfDay) -> u32 {
+        (later.0 + SECONDS_PER_DAY - self.0) % SECONDS_PER_DAY
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hour, minute, second) = (self.0 / 3600, self.0 / 60 % 60, self.0 % 60);
+        if second == 0 {
+            write!(f, "{hour:02}:{minute:02}")
+        } else {
+            write!(f, "{hour:02}:{minute:02}:{second:02}")
+        }
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = TimeOfDayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TimeOfDayError::InvalidClockTime(s.to_string());
+        let parts = s
+            .split(':')
+            .map(|part| {
+                (part.len() == 2)
+                    .then(|| part.parse::<u8>().ok())
+                    .flatten()
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        match parts[..] {
+            [hour, minute] => Self::from_hms(hour, minute, 0),
+            [hour, minute, second] => Self::from_hms(hour, minute, second),
+            _ => None,
+        }
+        .ok_or_else(invalid)
+    }
+}
+
+impl Serialize for TimeOfDay {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeOfDay {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Clock(String),
+            Fraction(f64),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Clock(clock) => clock.parse(),
+            Repr::Fraction(fraction) => Self::from_fraction(fraction),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+/// One entry of `time.json`: the image shown from `time` until the next entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSlot {
+    index: u8,
+    time: TimeOfDay,
+}
+
+impl TimeSlot {
+    pub fn new(index: u8, time: TimeOfDay) -> Self {
+        Self { index, time }
+    }
+
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn time(&self) -> TimeOfDay {
+        self.time
+    }
+}
+
+/// Selects the image, or the pair of images to blend, for the local time `now`
+///
+/// The slot that started most recently wins; before the first slot of the day the
+/// last one is still showing. With `blend` enabled the image fades into the next
+/// slot's image over the time between the two.
+pub(crate) fn select_by_time(
+    slots: &[TimeSlot],
+    now: TimeOfDay,
+    blend: bool,
+) -> Option<WallpaperChoice> {
+    let mut ordered: Vec<&TimeSlot> = slots.iter().collect();
+    ordered.sort_by_key(|slot| slot.time);
+
+    let position = ordered
+        .iter()
+        .rposition(|slot| slot.time <= now)
+        .unwrap_or(ordered.len().checked_sub(1)?);
+    let current = ordered[position];
+
+    if !blend || ordered.len() < 2 {
+        return Some(WallpaperChoice::Single(current.index));
+    }
+
+    let next = ordered[(position + 1) % ordered.len()];
+    let span = match current.time.seconds_until(next.time) {
+        0 => SECONDS_PER_DAY,
+        span => span,
+    };
+    let progress = f64::from(current.time.seconds_until(now)) / f64::from(span);
+
+    let (from, to, share) = if progress <= 0.5 {
+        (current.index, next.index, progress)
+    } else {
+        (next.index, current.index, 1.0 - progress)
+    };
+    let weight = (share * f64::from(BLEND_STEPS)).round() as u8;
+
+    if weight == 0 || from == to {
+        Some(WallpaperChoice::Single(from))
+    } else {
+        Some(WallpaperChoice::Blend { from, to, weight })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(clock: &str) -> TimeOfDay {
+        clock.parse().unwrap()
+    }
+
+    fn slots(json: &str) -> Vec<TimeSlot> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn parses_clock_times_and_fractions() {
+        let parsed = slots(
+            r#"[
+                {"index": 0, "time": "07:30"},
+                {"index": 1, "time": "18:45:10"},
+                {"index": 2, "time": 0.5}
+            ]"#,
+        );
+        assert_eq!(parsed[0].time(), TimeOfDay::from_hms(7, 30, 0).unwrap());
+        assert_eq!(
+            parsed[1].time().seconds_since_midnight(),
+            18 * 3600 + 45 * 60 + 10
+        );
+        assert_eq!(parsed[2].time(), at("12:00"));
+
+        assert_eq!(at("07:30").to_string(), "07:30");
+        assert_eq!(at("18:45:10").to_string(), "18:45:10");
+
+        for invalid in ["24:00", "7:30", "07:60", "07-30", ""] {
+            assert!(invalid.parse::<TimeOfDay>().is_err(), "{invalid}");
+        }
+        assert!(TimeOfDay::from_fraction(1.0).is_err());
+        assert!(serde_json::from_str::<Vec<TimeSlot>>(r#"[{"index": 0, "time": -0.1}]"#).is_err());
+    }
+
+    #[test]
+    fn selects_most_recent_slot_wrapping_midnight() {
+        // Listed out of order on purpose
+        let theme = slots(
+            r#"[
+                {"index": 1, "time": "12:00"},
+                {"index": 0, "time": "06:00"},
+                {"index": 2, "time": "20:00"}
+            ]"#,
+        );
+
+        let pick = |clock| select_by_time(&theme, at(clock), false);
+        assert_eq!(pick("06:00"), Some(WallpaperChoice::Single(0)));
+        assert_eq!(pick("11:59:59"), Some(WallpaperChoice::Single(0)));
+        assert_eq!(pick("12:00"), Some(WallpaperChoice::Single(1)));
+        assert_eq!(pick("23:00"), Some(WallpaperChoice::Single(2)));
+        assert_eq!(pick("03:00"), Some(WallpaperChoice::Single(2)));
+        assert_eq!(select_by_time(&[], at("03:00"), false), None);
+    }
+
+    #[test]
+    fn blends_towards_next_slot() {
+        let theme = slots(
+            r#"[
+                {"index": 0, "time": "06:00"},
+                {"index": 1, "time": "14:00"}
+            ]"#,
+        );
+
+        let pick = |clock| select_by_time(&theme, at(clock), true);
+        assert_eq!(pick("06:00"), Some(WallpaperChoice::Single(0)));
+        assert_eq!(
+            pick("08:00"),
+            Some(WallpaperChoice::Blend {
+                from: 0,
+                to: 1,
+                weight: BLEND_STEPS / 4
+            })
+        );
+        // Past the midpoint the next image dominates
+        assert_eq!(
+            pick("12:00"),
+            Some(WallpaperChoice::Blend {
+                from: 1,
+                to: 0,
+                weight: BLEND_STEPS / 4
+            })
+        );
+        // 16 hours from 14:00 back to 06:00 across midnight
+        assert_eq!(
+            pick("02:00"),
+            Some(WallpaperChoice::Blend {
+                from: 0,
+                to: 1,
+                weight: BLEND_STEPS / 4
+            })
+        );
+    }
+}
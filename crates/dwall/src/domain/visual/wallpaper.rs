@@ -1,31 +1,294 @@
 //! Wallpaper management and selection logic
 
-use crate::domain::time::solar_calculator::SolarAngle;
+use std::cmp::Ordering;
+
+use crate::domain::time::{
+    lunar_calculator::LunarPosition,
+    solar_calculator::{SolarAngle, SolarPosition},
+};
+
+/// Positions of the sun and, when needed, the moon at one instant
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SkyPosition {
+    sun: SolarPosition,
+    moon: Option<LunarPosition>,
+}
+
+impl SkyPosition {
+    /// `moon` may be omitted when no moon-keyed image can be selected
+    pub(crate) fn new(sun: SolarPosition, moon: Option<LunarPosition>) -> Self {
+        Self { sun, moon }
+    }
+
+    pub(crate) fn sun(&self) -> &SolarPosition {
+        &self.sun
+    }
+
+    pub(crate) fn moon(&self) -> Option<&LunarPosition> {
+        self.moon.as_ref()
+    }
+}
+
+/// Number of blend steps between two neighbouring images
+///
+/// Blend weights are rounded to multiples of `1 / BLEND_STEPS`, which bounds the
+/// number of distinct files rendered per image pair and lets them be reused.
+pub(crate) const BLEND_STEPS: u8 = 16;
+
+/// Image chosen for the current sky
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WallpaperChoice {
+    /// Show one theme image as is
+    Single(u8),
+
+    /// Cross-fade between two neighbouring images, from the one that dominates
+    Blend {
+        from: u8,
+        to: u8,
+        /// Weight of `to` in units of `1 / BLEND_STEPS`, in `1..=BLEND_STEPS / 2`
+        weight: u8,
+    },
+}
+
+impl WallpaperChoice {
+    /// Index of the image that dominates the result
+    pub(crate) fn primary_index(&self) -> u8 {
+        match self {
+            WallpaperChoice::Single(index) => *index,
+            WallpaperChoice::Blend { from, .. } => *from,
+        }
+    }
+}
+
+/// How an image is picked among the candidates of a theme
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct SelectionPolicy {
+    /// Cross-fade neighbouring images instead of snapping to one
+    blend: bool,
+    /// Angular margin, in degrees, by which another image must be closer to the
+    /// sun than the previous one before it replaces it
+    stickiness: f64,
+    /// Image shown before this selection, if known
+    previous: Option<u8>,
+}
+
+impl SelectionPolicy {
+    pub(crate) fn new(blend: bool, stickiness: f64) -> Self {
+        Self {
+            blend,
+            stickiness,
+            previous: None,
+        }
+    }
+
+    /// Same policy, remembering the image that is currently shown
+    pub(crate) fn with_previous(self, previous: Option<u8>) -> Self {
+        Self { previous, ..self }
+    }
+
+    pub(crate) fn blend(&self) -> bool {
+        self.blend
+    }
+}
 
 /// Wallpaper selection utilities
 pub struct WallpaperSelector;
 
 impl WallpaperSelector {
-    /// Finds the closest matching image based on solar angles
-    pub(crate) fn find_closest_image(
+    /// Selects the image for the current sky, taking moon-keyed night images into account
+    #[cfg(test)]
+    pub(crate) fn select_image(solar_configs: &[SolarAngle], sky: &SkyPosition) -> Option<u8> {
+        Self::select(solar_configs, sky, &SelectionPolicy::default())
+            .map(|choice| choice.primary_index())
+    }
+
+    /// Selects the image, or the pair of images to blend, for the current sky
+    ///
+    /// While the sun is below the horizon and the theme declares images with a
+    /// `moon_phase`, the one whose phase best matches the moonlight reaching the
+    /// observer wins and is never blended. Otherwise only images without a moon
+    /// phase compete on solar angles, falling back to all images if the theme has
+    /// none of those.
+    ///
+    /// With blending enabled, the images before and after the sun along its daily
+    /// path are mixed by how far the sun has moved from one towards the other.
+    /// Otherwise the previous image is kept until another one is closer by the
+    /// policy's stickiness margin, like the Schmitt trigger of the color scheme, so
+    /// near ties cannot flap.
+    pub(crate) fn select(
         solar_configs: &[SolarAngle],
-        current_altitude: f64,
-        current_azimuth: f64,
-    ) -> Option<u8> {
-        solar_configs
+        sky: &SkyPosition,
+        policy: &SelectionPolicy,
+    ) -> Option<WallpaperChoice> {
+        let current_altitude = sky.sun().altitude();
+        let current_azimuth = sky.sun().azimuth();
+        let (moon_keyed, sun_keyed): (Vec<&SolarAngle>, Vec<&SolarAngle>) = solar_configs
             .iter()
-            .min_by(|a, b| {
-                solar_distance(a.altitude(), a.azimuth(), current_altitude, current_azimuth)
-                    .partial_cmp(&solar_distance(
-                        b.altitude(),
-                        b.azimuth(),
-                        current_altitude,
-                        current_azimuth,
-                    ))
-                    .unwrap()
-            })
-            .map(|sa| sa.index())
+            .partition(|sa| sa.moon_phase().is_some());
+
+        if current_altitude < 0.0
+            && let Some(moon) = sky.moon()
+            && !moon_keyed.is_empty()
+        {
+            return closest_by_moon(&moon_keyed, moon, current_altitude, current_azimuth)
+                .map(|sa| WallpaperChoice::Single(sa.index()));
+        }
+
+        let candidates = if sun_keyed.is_empty() {
+            solar_configs.iter().collect()
+        } else {
+            sun_keyed
+        };
+
+        if !policy.blend {
+            return closest_by_sun(&candidates, current_altitude, current_azimuth, policy)
+                .map(|sa| WallpaperChoice::Single(sa.index()));
+        }
+
+        blend_along_path(candidates, current_altitude, current_azimuth)
+    }
+
+    /// Whether any image of the theme is keyed to a moon phase
+    pub(crate) fn has_moon_keyed_images(solar_configs: &[SolarAngle]) -> bool {
+        solar_configs.iter().any(|sa| sa.moon_phase().is_some())
+    }
+}
+
+/// Blends the candidates before and after the sun along its daily path
+///
+/// The sun moves steadily around the horizon over a day, so the images are ordered
+/// by azimuth and the sun falls between two neighbours, wrapping through north.
+/// Its progress from one to the other is its position projected onto the segment
+/// joining them, in the scale of [`solar_distance`].
+fn blend_along_path(
+    mut candidates: Vec<&SolarAngle>,
+    current_altitude: f64,
+    current_azimuth: f64,
+) -> Option<WallpaperChoice> {
+    candidates.sort_by(|a, b| {
+        normalize_azimuth(a.azimuth())
+            .total_cmp(&normalize_azimuth(b.azimuth()))
+            .then(a.altitude().total_cmp(&b.altitude()))
+    });
+
+    let current_azimuth = normalize_azimuth(current_azimuth);
+    let position = candidates
+        .iter()
+        .rposition(|sa| normalize_azimuth(sa.azimuth()) <= current_azimuth)
+        .unwrap_or(candidates.len().checked_sub(1)?);
+    let previous = candidates[position];
+    let next = candidates[(position + 1) % candidates.len()];
+    if previous.index() == next.index() {
+        return Some(WallpaperChoice::Single(previous.index()));
+    }
+
+    // Offsets from `previous`, forwards along the path, normalized like `solar_distance`
+    let offset = |altitude: f64, azimuth: f64| {
+        (
+            (altitude - previous.altitude()) / 180.0,
+            normalize_azimuth(azimuth - previous.azimuth()) / 360.0,
+        )
+    };
+    let segment = offset(next.altitude(), next.azimuth());
+    let sun = offset(current_altitude, current_azimuth);
+    let length = segment.0 * segment.0 + segment.1 * segment.1;
+    let progress = if length > 0.0 {
+        ((sun.0 * segment.0 + sun.1 * segment.1) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    let (from, to, share) = if progress <= 0.5 {
+        (previous.index(), next.index(), progress)
+    } else {
+        (next.index(), previous.index(), 1.0 - progress)
+    };
+    let weight = (share * f64::from(BLEND_STEPS)).round() as u8;
+
+    if weight == 0 {
+        return Some(WallpaperChoice::Single(from));
     }
+
+    // An even split is the same picture either way round; keep one cache entry for it
+    let (from, to) = if weight * 2 == BLEND_STEPS && to < from {
+        (to, from)
+    } else {
+        (from, to)
+    };
+
+    Some(WallpaperChoice::Blend { from, to, weight })
+}
+
+/// Maps an azimuth in degrees into `[0°, 360°)`
+fn normalize_azimuth(azimuth: f64) -> f64 {
+    azimuth.rem_euclid(360.0)
+}
+
+/// Picks the image closest to the sun, keeping the previous one within the stickiness margin
+fn closest_by_sun<'a>(
+    candidates: &[&'a SolarAngle],
+    current_altitude: f64,
+    current_azimuth: f64,
+    policy: &SelectionPolicy,
+) -> Option<&'a SolarAngle> {
+    let distance = |sa: &SolarAngle| {
+        solar_distance(
+            sa.altitude(),
+            sa.azimuth(),
+            current_altitude,
+            current_azimuth,
+        )
+    };
+    // `total_cmp` orders NaN last, so a corrupt entry can never win nor panic
+    let closest = candidates
+        .iter()
+        .copied()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))?;
+
+    let previous = policy
+        .previous
+        .and_then(|index| candidates.iter().copied().find(|sa| sa.index() == index));
+    if let Some(previous) = previous {
+        let lead = angular_distance(distance(previous)) - angular_distance(distance(closest));
+        if lead <= policy.stickiness {
+            return Some(previous);
+        }
+    }
+
+    Some(closest)
+}
+
+/// Picks the moon-keyed image closest in moonlight, then in exact phase, then in solar angles
+fn closest_by_moon<'a>(
+    moon_keyed: &[&'a SolarAngle],
+    moon: &LunarPosition,
+    current_altitude: f64,
+    current_azimuth: f64,
+) -> Option<&'a SolarAngle> {
+    let visible_illumination = moon.visible_illumination();
+    let key = |sa: &SolarAngle| {
+        let phase = sa
+            .moon_phase()
+            .expect("only moon-keyed images are compared");
+        (
+            (phase.nominal_illumination() - visible_illumination).abs(),
+            phase != moon.phase(),
+            solar_distance(
+                sa.altitude(),
+                sa.azimuth(),
+                current_altitude,
+                current_azimuth,
+            ),
+        )
+    };
+
+    moon_keyed.iter().copied().min_by(|a, b| {
+        let (a, b) = (key(a), key(b));
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(Ordering::Equal)
+            .then(a.1.cmp(&b.1))
+            .then(a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+    })
 }
 
 /// Calculates the normalized distance between two solar positions
@@ -42,3 +305,170 @@ fn solar_distance(alt1: f64, az1: f64, alt2: f64, az2: f64) -> f64 {
 
     da * da + daz * daz // No need to take square root; the comparison result is the same
 }
+
+/// Converts a [`solar_distance`] to degrees of altitude
+///
+/// One degree of altitude maps to one degree; one degree of azimuth to half a degree.
+fn angular_distance(solar_distance: f64) -> f64 {
+    solar_distance.sqrt() * 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Month, UtcDateTime};
+
+    use super::*;
+    use crate::{
+        Position,
+        domain::time::{AtmosphericConditions, SolarModel},
+    };
+
+    fn sky_at(position: &Position, instant: UtcDateTime) -> SkyPosition {
+        SkyPosition::new(
+            SolarPosition::new(position, &instant, &SolarModel::default()),
+            Some(LunarPosition::new(
+                position,
+                &instant,
+                &AtmosphericConditions::default(),
+            )),
+        )
+    }
+
+    #[test]
+    fn moon_keyed_images_are_used_at_night() {
+        let london = Position::new(51.5074, -0.1278, 11.0).unwrap();
+        let angles: Vec<SolarAngle> = serde_json::from_str(
+            r#"[
+                {"index": 0, "altitude": 50.0, "azimuth": 180.0},
+                {"index": 1, "altitude": -30.0, "azimuth": 0.0},
+                {"index": 2, "altitude": -30.0, "azimuth": 0.0, "moon_phase": "full"},
+                {"index": 3, "altitude": -30.0, "azimuth": 0.0, "moon_phase": "new"}
+            ]"#,
+        )
+        .unwrap();
+
+        let full_moon_night = UtcDateTime::new(2024, Month::April, 23, 23, 49, 0).unwrap();
+        let new_moon_night = UtcDateTime::new(2024, Month::April, 8, 23, 0, 0).unwrap();
+        let midday = UtcDateTime::new(2024, Month::April, 23, 12, 0, 0).unwrap();
+
+        let select = |instant| WallpaperSelector::select_image(&angles, &sky_at(&london, instant));
+        assert_eq!(select(full_moon_night), Some(2));
+        assert_eq!(select(new_moon_night), Some(3));
+        assert_eq!(select(midday), Some(0));
+
+        // Without a moon position, moon-keyed images never compete
+        let sun_only = SkyPosition::new(*sky_at(&london, full_moon_night).sun(), None);
+        assert_eq!(WallpaperSelector::select_image(&angles, &sun_only), Some(1));
+    }
+
+    #[test]
+    fn previous_image_sticks_within_margin() {
+        let angles: Vec<SolarAngle> = serde_json::from_str(
+            r#"[
+                {"index": 0, "altitude": 10.0, "azimuth": 90.0},
+                {"index": 1, "altitude": 20.0, "azimuth": 90.0}
+            ]"#,
+        )
+        .unwrap();
+        let candidates: Vec<&SolarAngle> = angles.iter().collect();
+        let policy = SelectionPolicy::new(false, 1.0);
+        let pick = |altitude, previous| {
+            closest_by_sun(&candidates, altitude, 90.0, &policy.with_previous(previous))
+                .map(SolarAngle::index)
+        };
+
+        // Just past the midpoint: image 1 is nearer, but not by the 1° margin
+        assert_eq!(pick(15.4, None), Some(1));
+        assert_eq!(pick(15.4, Some(0)), Some(0));
+        // Half a degree further image 1 leads by more than the margin
+        assert_eq!(pick(15.6, Some(0)), Some(1));
+        // Coming back, image 1 holds until image 0 is clearly nearer
+        assert_eq!(pick(14.6, Some(1)), Some(1));
+        assert_eq!(pick(14.4, Some(1)), Some(0));
+        // An unknown previous index is ignored
+        assert_eq!(pick(14.6, Some(7)), Some(0));
+    }
+
+    #[test]
+    fn nan_sun_position_does_not_panic() {
+        let angles: Vec<SolarAngle> = serde_json::from_str(
+            r#"[
+                {"index": 0, "altitude": 10.0, "azimuth": 90.0},
+                {"index": 1, "altitude": 20.0, "azimuth": 90.0}
+            ]"#,
+        )
+        .unwrap();
+        let candidates: Vec<&SolarAngle> = angles.iter().collect();
+
+        assert_eq!(
+            closest_by_sun(&candidates, f64::NAN, 90.0, &SelectionPolicy::default())
+                .map(SolarAngle::index),
+            Some(0)
+        );
+        assert!(blend_along_path(candidates, f64::NAN, 90.0).is_some());
+    }
+
+    #[test]
+    fn blends_between_neighbours_along_the_sun_path() {
+        let angles: Vec<SolarAngle> = serde_json::from_str(
+            r#"[
+                {"index": 0, "altitude": -10.0, "azimuth": 70.0},
+                {"index": 1, "altitude": 40.0, "azimuth": 180.0},
+                {"index": 2, "altitude": -10.0, "azimuth": 290.0},
+                {"index": 3, "altitude": -50.0, "azimuth": 0.0}
+            ]"#,
+        )
+        .unwrap();
+        let blend =
+            |altitude, azimuth| blend_along_path(angles.iter().rev().collect(), altitude, azimuth);
+
+        // On an image: no blend
+        assert_eq!(blend(40.0, 180.0), Some(WallpaperChoice::Single(1)));
+        // A quarter of the way from dawn to noon
+        assert_eq!(
+            blend(2.5, 97.5),
+            Some(WallpaperChoice::Blend {
+                from: 0,
+                to: 1,
+                weight: BLEND_STEPS / 4
+            })
+        );
+        // Halfway: the pair is ordered by index
+        assert_eq!(
+            blend(15.0, 125.0),
+            Some(WallpaperChoice::Blend {
+                from: 0,
+                to: 1,
+                weight: BLEND_STEPS / 2
+            })
+        );
+        // Three quarters of the way: noon dominates
+        assert_eq!(
+            blend(27.5, 152.5),
+            Some(WallpaperChoice::Blend {
+                from: 1,
+                to: 0,
+                weight: BLEND_STEPS / 4
+            })
+        );
+        // Just after dawn the midnight image is nearer than noon, but the sun is
+        // heading for noon
+        assert_eq!(
+            blend(-5.0, 80.0),
+            Some(WallpaperChoice::Blend {
+                from: 0,
+                to: 1,
+                weight: 2
+            })
+        );
+        // Before midnight the path wraps through north
+        assert_eq!(
+            blend(-45.0, 350.0),
+            Some(WallpaperChoice::Blend {
+                from: 3,
+                to: 2,
+                weight: 2
+            })
+        );
+    }
+}
@@ -1,5 +1,9 @@
+use crate::config::ConfigIssue;
+use crate::core::cli::CommandLineError;
 use crate::domain::geography::{CoordinateError, GeolocationAccessError};
-use crate::domain::visual::ThemeError;
+use crate::domain::visual::{
+    PhotoSolarError, SolarPathError, ThemeError, ThemeImportError, ThemePackError,
+};
 use crate::infrastructure::display::DisplayError;
 use crate::infrastructure::display::WallpaperError;
 use crate::infrastructure::platform::windows::RegistryError;
@@ -24,6 +28,30 @@ pub enum DwallError {
     #[error("Theme processing error: {0}")]
     ThemeProcessing(#[from] ThemeError),
 
+    /// Command line error
+    #[error("Invalid command line: {0}")]
+    CommandLine(#[from] CommandLineError),
+
+    /// Solar path table error
+    #[error("Solar path table error: {0}")]
+    SolarPath(#[from] SolarPathError),
+
+    /// Theme pack or manifest error
+    #[error("Theme pack error: {0}")]
+    ThemePack(#[from] ThemePackError),
+
+    /// Error while importing or exporting another application's theme
+    #[error("Theme import error: {0}")]
+    ThemeImport(#[from] ThemeImportError),
+
+    /// Error while building a solar theme from photos
+    #[error("Photo metadata error: {0}")]
+    PhotoSolar(#[from] PhotoSolarError),
+
+    /// Image decoding or encoding error
+    #[error("Image processing failed: {0}")]
+    Image(#[from] image::ImageError),
+
     /// JSON serialization/deserialization error
     #[error("JSON processing failed: {0}")]
     SerdeJson(#[from] serde_json::Error),
@@ -63,18 +91,45 @@ pub enum DwallError {
     Logger(#[from] log::SetLoggerError),
 }
 
+fn issue_list(issues: &[ConfigIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// Configuration-related errors
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("IO error occurred: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Syntax error: {0}")]
+    Syntax(#[from] toml_edit::TomlError),
+
     #[error("Deserialization error: {0}")]
     Deserialization(#[from] toml::de::Error),
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] toml::ser::Error),
 
-    #[error("Configuration validation failed: {reason}")]
-    Validation { reason: String },
+    #[error("Configuration validation failed: {}", issue_list(.issues))]
+    Validation { issues: Vec<ConfigIssue> },
+
+    #[error("Configuration file is empty")]
+    Empty,
+
+    #[error(
+        "No unused name for a backup of the version {version} configuration after {attempts} attempts"
+    )]
+    NoBackupName { version: u32, attempts: u32 },
+
+    #[error("Configuration version must be a non-negative integer, got {0}")]
+    InvalidVersion(String),
+
+    #[error(
+        "Configuration version {found} was written by a newer release, this one supports up to {supported}"
+    )]
+    UnsupportedVersion { found: u32, supported: u32 },
 }
@@ -5,8 +5,9 @@ use std::mem;
 use windows::Win32::{
     Devices::Display::{
         DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME, DISPLAYCONFIG_DEVICE_INFO_HEADER,
-        DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_TARGET_DEVICE_NAME,
-        DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes, QDC_ALL_PATHS, QueryDisplayConfig,
+        DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE, DISPLAYCONFIG_PATH_INFO,
+        DISPLAYCONFIG_TARGET_DEVICE_NAME, DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes,
+        QDC_ALL_PATHS, QueryDisplayConfig,
     },
     Foundation::ERROR_SUCCESS,
 };
@@ -14,7 +15,7 @@ use windows::core::Error as WindowsError;
 
 use crate::DwallResult;
 
-use super::error::DisplayError;
+use super::{error::DisplayError, monitor_manager::Resolution};
 
 const DISPLAYCONFIG_PATH_ACTIVE: u32 = 0x00000001;
 
@@ -22,12 +23,14 @@ const DISPLAYCONFIG_PATH_ACTIVE: u32 = 0x00000001;
 pub(crate) struct DisplayPath {
     pub(crate) adapter_id: u32,
     pub(crate) target_id: u32,
+    /// Size of the desktop shown on the target, rotation included
+    pub(crate) resolution: Option<Resolution>,
 }
 
 /// Queries all active display paths in the system
 pub(crate) fn query_display_paths() -> DwallResult<Vec<DisplayPath>> {
     let (mut path_count, mut mode_count) = get_buffer_sizes()?;
-    let (paths, _) = query_config(&mut path_count, &mut mode_count)?;
+    let (paths, modes) = query_config(&mut path_count, &mut mode_count)?;
 
     Ok(paths
         .into_iter()
@@ -35,10 +38,28 @@ pub(crate) fn query_display_paths() -> DwallResult<Vec<DisplayPath>> {
         .map(|path| DisplayPath {
             adapter_id: path.targetInfo.adapterId.LowPart,
             target_id: path.targetInfo.id,
+            resolution: source_resolution(&path, &modes),
         })
         .collect())
 }
 
+/// Reads the desktop size from the source mode of `path`
+fn source_resolution(
+    path: &DISPLAYCONFIG_PATH_INFO,
+    modes: &[DISPLAYCONFIG_MODE_INFO],
+) -> Option<Resolution> {
+    // Without QDC_VIRTUAL_MODE_AWARE the union holds a plain mode index
+    let mode_index = unsafe { path.sourceInfo.Anonymous.modeInfoIdx } as usize;
+    let mode = modes.get(mode_index)?;
+    if mode.infoType != DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE {
+        return None;
+    }
+
+    let source_mode = unsafe { mode.Anonymous.sourceMode };
+    (source_mode.width > 0 && source_mode.height > 0)
+        .then(|| Resolution::new(source_mode.width, source_mode.height))
+}
+
 /// Queries the target device name for a specific display adapter and target
 pub(crate) fn query_target_name(
     adapter_id: u32,
@@ -6,5 +6,5 @@ pub mod wallpaper_setter;
 
 // Re-export commonly used types
 pub use error::DisplayError;
-pub use monitor_manager::{DisplayMonitor, DisplayMonitorProvider};
+pub use monitor_manager::{DisplayMonitor, DisplayMonitorProvider, Orientation, Resolution};
 pub(crate) use wallpaper_setter::WallpaperError;
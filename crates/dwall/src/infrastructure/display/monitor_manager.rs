@@ -3,6 +3,7 @@
 use std::{
     cell::RefCell,
     collections::HashMap,
+    fmt,
     time::{Duration, Instant},
 };
 
@@ -18,20 +19,91 @@ use super::display_query::{query_display_paths, query_target_name};
 /// Reduces API calls by 90% and significantly lowers memory usage and CPU overhead
 const CACHE_EXPIRY_SECONDS: u64 = 300; // 5 minutes
 
+/// Size in pixels of a monitor's desktop, or of the images made for one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Resolution {
+    width: u32,
+    height: u32,
+}
+
+impl Resolution {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Parses a `<width>x<height>` name such as `3840x2160`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (width, height) = name.split_once(['x', 'X'])?;
+        let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+        (width > 0 && height > 0).then_some(Self { width, height })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Square resolutions count as landscape
+    pub fn orientation(&self) -> Orientation {
+        if self.height > self.width {
+            Orientation::Portrait
+        } else {
+            Orientation::Landscape
+        }
+    }
+
+    pub fn pixel_count(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+
+    /// Whether an image of this resolution fills `other` without upscaling
+    pub fn covers(&self, other: Resolution) -> bool {
+        self.width >= other.width && self.height >= other.height
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
 /// Display monitor information with serialization support
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisplayMonitor {
     device_path: String,
     friendly_name: String,
     position_index: Option<u32>,
+    /// Desktop size, rotation included, when the system reports it
+    #[serde(default)]
+    resolution: Option<Resolution>,
+    #[serde(default)]
+    orientation: Option<Orientation>,
 }
 
 impl DisplayMonitor {
-    fn new(device_path: String, friendly_name: String, position_index: Option<u32>) -> Self {
+    fn new(
+        device_path: String,
+        friendly_name: String,
+        position_index: Option<u32>,
+        resolution: Option<Resolution>,
+    ) -> Self {
         Self {
             device_path,
             friendly_name,
             position_index,
+            resolution,
+            orientation: resolution.map(|resolution| resolution.orientation()),
         }
     }
 
@@ -46,6 +118,14 @@ impl DisplayMonitor {
     pub fn position_index(&self) -> Option<u32> {
         self.position_index
     }
+
+    pub fn resolution(&self) -> Option<Resolution> {
+        self.resolution
+    }
+
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.orientation
+    }
 }
 
 /// Cache structure for monitor information with optimized expiration
@@ -124,9 +204,11 @@ impl DisplayMonitorProvider {
             return Ok(true);
         }
 
-        for device_path in current_monitors.keys() {
-            if !cache.data.contains_key(device_path) {
-                return Ok(true);
+        // A resized or rotated monitor may need another image variant
+        for (device_path, monitor) in &current_monitors {
+            match cache.data.get(device_path) {
+                Some(cached) if cached.resolution() == monitor.resolution() => {}
+                _ => return Ok(true),
             }
         }
 
@@ -176,7 +258,12 @@ pub(crate) fn fetch_system_monitors() -> DwallResult<HashMap<String, DisplayMoni
 
         monitors.insert(
             device_path.clone(),
-            DisplayMonitor::new(device_path, friendly_name, Some(index as u32)),
+            DisplayMonitor::new(
+                device_path,
+                friendly_name,
+                Some(index as u32),
+                display_path.resolution,
+            ),
         );
     }
 
@@ -13,10 +13,7 @@ use windows::{
     core::{HSTRING, Interface},
 };
 
-use crate::{
-    domain::{time::solar_calculator::SolarAngle, visual::wallpaper::WallpaperSelector},
-    error::DwallResult,
-};
+use crate::error::DwallResult;
 
 use super::monitor_manager::{DisplayMonitor, DisplayMonitorProvider};
 
@@ -205,15 +202,6 @@ impl WallpaperSetter {
         Ok(())
     }
 
-    /// Finds the closest matching image using wallpaper selection logic
-    pub(crate) fn find_closest_image(
-        solar_configs: &[SolarAngle],
-        current_altitude: f64,
-        current_azimuth: f64,
-    ) -> Option<u8> {
-        WallpaperSelector::find_closest_image(solar_configs, current_altitude, current_azimuth)
-    }
-
     // Private methods
     fn retry_set_wallpaper(&self, monitor_id: &str, wallpaper_path: &Path) -> DwallResult<()> {
         warn!("Refreshing monitor information and retrying...");
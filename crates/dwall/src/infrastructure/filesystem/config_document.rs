@@ -0,0 +1,178 @@
+//! Updates `config.toml` in place
+//!
+//! The file may be edited by hand, so instead of replacing it with a freshly
+//! serialized [`Config`], [`update_document`] compares the configuration read from
+//! the file with the new one and edits only the keys that differ. Comments, key
+//! order, formatting and keys this release does not know are left as they are.
+//! When the changes cannot be expressed as edits, the caller rewrites the whole file.
+
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::config::Config;
+
+/// Applies the changes from `previous`, the configuration read from `document`,
+/// to `config` onto the document
+///
+/// Returns `None` when either configuration does not serialize to a document that
+/// can be edited, in which case `document` is left partially updated and should be
+/// discarded.
+pub(crate) fn update_document(
+    document: &mut DocumentMut,
+    previous: &Config,
+    config: &Config,
+) -> Option<()> {
+    let previous = serialize(previous)?;
+    let new = serialize(config)?;
+    apply_changes(document.as_table_mut(), previous.as_table(), new.as_table())
+}
+
+fn serialize(config: &Config) -> Option<DocumentMut> {
+    toml::to_string(config).ok()?.parse().ok()
+}
+
+/// Edits the entries of `document` that changed from `previous` to `new`
+fn apply_changes(
+    document: &mut dyn TableLike,
+    previous: &dyn TableLike,
+    new: &dyn TableLike,
+) -> Option<()> {
+    for (key, _) in previous.iter() {
+        if !new.contains_key(key) {
+            document.remove(key);
+        }
+    }
+
+    for (key, new_item) in new.iter() {
+        let previous_item = previous.get(key);
+        if previous_item.is_some_and(|previous_item| same_item(previous_item, new_item)) {
+            continue;
+        }
+
+        let Some(item) = document.get_mut(key) else {
+            document.insert(key, new_item.clone());
+            continue;
+        };
+
+        let is_inline = item.is_value();
+        if let (Some(table), Some(previous_table), Some(new_table)) = (
+            item.as_table_like_mut(),
+            previous_item.and_then(Item::as_table_like),
+            new_item.as_table_like(),
+        ) {
+            apply_changes(table, previous_table, new_table)?;
+            if is_inline {
+                // Inline tables fit on one line, so respacing them loses no comments
+                table.fmt();
+            }
+        } else if let Some(value) = item.as_value_mut() {
+            // Keep the value inline, with the comment trailing it in its decor
+            let decor = value.decor().clone();
+            *value = new_item.clone().into_value().ok()?;
+            *value.decor_mut() = decor;
+        } else {
+            *item = new_item.clone();
+        }
+    }
+
+    Some(())
+}
+
+fn same_item(a: &Item, b: &Item) -> bool {
+    match (a.clone().into_value(), b.clone().into_value()) {
+        (Ok(a), Ok(b)) => same_value(&a, &b),
+        _ => false,
+    }
+}
+
+/// Whether two values are equal, however they are written
+fn same_value(a: &Value, b: &Value) -> bool {
+    let parse = |value: &Value| {
+        format!("value = {value}")
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+    };
+
+    match (parse(a), parse(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies the configuration read from `edited` to `original`
+    fn update(original: &str, edited: &str) -> String {
+        let previous: Config = toml::from_str(original).unwrap();
+        let config: Config = toml::from_str(edited).unwrap();
+        let mut document: DocumentMut = original.parse().unwrap();
+        update_document(&mut document, &previous, &config).unwrap();
+
+        let updated = document.to_string();
+        assert_eq!(toml::from_str::<Config>(&updated).unwrap(), config);
+        updated
+    }
+
+    #[test]
+    fn keeps_comments_order_and_unknown_keys() {
+        let original = r#"# Edited by hand
+version = 1
+
+# Check every ten minutes
+interval = 600 # seconds
+future_setting = "kept"
+image_format = 'jpeg'
+
+[position_source]
+type = "MANUAL" # from the map
+latitude = 39.9
+longitude = 116.4
+"#;
+
+        let updated = update(
+            original,
+            &original.replace("interval = 600", "interval = 300"),
+        );
+        assert_eq!(
+            updated,
+            original.replace("interval = 600 #", "interval = 300 #")
+        );
+
+        // Unchanged values keep their spelling, such as the single quotes
+        assert_eq!(update(original, original), original);
+    }
+
+    #[test]
+    fn removes_settings_that_were_unset() {
+        let original = r#"version = 1
+network = { host = "127.0.0.1", port = 1080 }
+position_source = { type = "MANUAL", latitude = 39.9, longitude = 116.4 } # home
+unknown = true
+
+[monitor_specific_wallpapers]
+DISPLAY1 = "Catalina"
+DISPLAY2 = "Big Sur"
+"#;
+        let edited = r#"version = 1
+position_source = { type = "AUTOMATIC" }
+unknown = true
+
+[monitor_specific_wallpapers]
+DISPLAY1 = "Catalina"
+"#;
+
+        let updated = update(original, edited);
+        assert!(!updated.contains("network"), "{updated}");
+        assert!(!updated.contains("DISPLAY2"), "{updated}");
+        assert!(!updated.contains("latitude"), "{updated}");
+        assert!(updated.contains("unknown = true"), "{updated}");
+        assert!(
+            updated.contains(
+                r#"position_source = { type = "AUTOMATIC", update_on_each_calculation = false, cache_minutes = 30 } # home"#
+            ),
+            "{updated}"
+        );
+    }
+}
@@ -1,17 +1,28 @@
 //! Configuration and filesystem management infrastructure
 
 use std::{
-    fs,
+    fs::{self, File},
+    io::{self, Write},
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
+use toml_edit::DocumentMut;
+
 use crate::{
-    config::{Config, RawConfig},
-    error::{ConfigError, DwallResult},
+    config::Config,
+    error::{ConfigError, DwallError, DwallResult},
     lazy::DWALL_CONFIG_DIR,
 };
 
+use super::{config_document::update_document, config_migration::migrate_config};
+
+/// Number of previous configuration files kept as `config.toml.<n>.bak`
+const CONFIG_BACKUP_COUNT: usize = 3;
+
+/// Names tried for the backup kept before rewriting a migrated file
+const MIGRATION_BACKUP_ATTEMPTS: u32 = 100;
+
 /// Configuration manager for file operations
 pub(crate) struct ConfigManager {
     config_path: PathBuf,
@@ -71,6 +82,10 @@ impl ConfigManager {
     }
 
     /// Reads the configuration from the file system and updates the last modified time
+    ///
+    /// When the file is corrupt or empty, for example after a crash of an older release
+    /// in the middle of a write, the newest valid backup is used instead. The file
+    /// itself is left untouched so it can still be repaired by hand.
     pub(crate) fn read_config(&mut self) -> DwallResult<Config> {
         if !self.config_path.exists() {
             warn!("Config file not found, using default configuration");
@@ -83,28 +98,120 @@ impl ConfigManager {
         let metadata = fs::metadata(&self.config_path)?;
         self.last_modified = Some(metadata.modified()?);
 
-        let content = fs::read_to_string(&self.config_path)?;
-        let raw_config: RawConfig = toml::from_str(&content).map_err(|e| {
-            error!(error = %e, "Failed to parse configuration");
-            ConfigError::Deserialization(e)
-        })?;
-        let config = Config::from(raw_config);
-
-        config.validate()?;
+        let content = match fs::read_to_string(&self.config_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return self.read_backup_config(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (config, migrated_from) = match load_config(&content) {
+            Ok(loaded) => loaded,
+            Err(DwallError::Config(ConfigError::Empty)) => {
+                // Nothing writes an empty file, so it was most likely truncated
+                warn!(
+                    path = %self.config_path.display(),
+                    "Configuration file is empty, it may have been truncated"
+                );
+                return Ok(self.newest_valid_backup().unwrap_or_default());
+            }
+            Err(e) if is_corrupt(&e) => return self.read_backup_config(e),
+            Err(e) => return Err(e),
+        };
         info!("Configuration loaded successfully");
 
+        if let Some(version) = migrated_from {
+            // Keep the original so a failed or unwanted upgrade can be undone by hand;
+            // without a backup the file is left as it is and migrated again next time
+            match self.backup_config(version) {
+                Ok(backup_path) => {
+                    info!(
+                        from = version,
+                        to = config.version(),
+                        backup_path = %backup_path.display(),
+                        "Rewriting migrated configuration file"
+                    );
+                    self.write_config_to_file(&config)?;
+                    self.last_modified = self.get_file_modified_time()?;
+                }
+                Err(e) => warn!(
+                    from = version,
+                    error = %e,
+                    "Failed to back up configuration file, leaving it unmigrated on disk"
+                ),
+            }
+        }
+
         Ok(config)
     }
 
+    /// Falls back to the newest valid backup after the configuration file failed to load
+    fn read_backup_config(&self, error: DwallError) -> DwallResult<Config> {
+        error!(
+            path = %self.config_path.display(),
+            error = %error,
+            "Configuration file is invalid"
+        );
+        self.newest_valid_backup().ok_or(error)
+    }
+
+    /// Loads the newest of the rolling backups that parses and validates
+    fn newest_valid_backup(&self) -> Option<Config> {
+        for backup_path in (1..=CONFIG_BACKUP_COUNT).map(|index| self.backup_path(index)) {
+            let Ok(content) = fs::read_to_string(&backup_path) else {
+                continue;
+            };
+
+            match load_config(&content) {
+                Ok((config, _)) => {
+                    warn!(
+                        backup_path = %backup_path.display(),
+                        "Using configuration backup"
+                    );
+                    return Some(config);
+                }
+                Err(e) => {
+                    warn!(
+                        backup_path = %backup_path.display(),
+                        error = %e,
+                        "Skipping invalid configuration backup"
+                    );
+                }
+            }
+        }
+
+        error!("No valid configuration backup found");
+        None
+    }
+
+    /// Copies the configuration file to an unused `config.toml.v<version>.bak` name
+    fn backup_config(&self, version: u32) -> DwallResult<PathBuf> {
+        let backup_path = (0..MIGRATION_BACKUP_ATTEMPTS)
+            .map(|attempt| {
+                let suffix = match attempt {
+                    0 => format!("v{version}.bak"),
+                    attempt => format!("v{version}.{attempt}.bak"),
+                };
+                self.config_path.with_extension(format!("toml.{suffix}"))
+            })
+            .find(|path| !path.exists())
+            .ok_or(ConfigError::NoBackupName {
+                version,
+                attempts: MIGRATION_BACKUP_ATTEMPTS,
+            })?;
+
+        fs::copy(&self.config_path, &backup_path)?;
+        Ok(backup_path)
+    }
+
     /// Writes the configuration to the file system
     pub(crate) fn write_config(&mut self, config: &Config) -> DwallResult<()> {
         config.validate()?;
 
-        if !self.config_path.exists() {
-            return self.write_config_to_file(config);
-        }
-
-        if let Ok(existing_config) = self.read_config()
+        // Compare with the file itself: a corrupt file must be replaced even when the
+        // backup it would be read from holds the same configuration
+        if let Ok(content) = fs::read_to_string(&self.config_path)
+            && let Ok((existing_config, None)) = parse_config(&content)
             && existing_config == *config
         {
             debug!("Configuration unchanged, skipping write");
@@ -114,18 +221,159 @@ impl ConfigManager {
         self.write_config_to_file(config)
     }
 
+    /// Replaces the configuration file without ever leaving a partial one behind
+    ///
+    /// The configuration goes to a temporary file next to `config.toml`, which is
+    /// flushed to disk and then renamed over it. The previous file is first added to
+    /// the rolling backups, unless it is corrupt itself.
     pub(crate) fn write_config_to_file(&self, config: &Config) -> DwallResult<()> {
+        let toml_string = self.render_config(config)?;
+
+        info!(path = %self.config_path.display(), "Writing configuration file");
+        let temp_path = self.config_path.with_extension("toml.tmp");
+        if let Err(e) = write_synced(&temp_path, toml_string.as_bytes()) {
+            error!(
+                path = %temp_path.display(),
+                error = %e,
+                "Failed to write configuration"
+            );
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+
+        if let Err(e) = self.rotate_backups() {
+            // A missing backup is no reason to lose the new configuration
+            warn!(error = %e, "Failed to back up configuration file");
+        }
+
+        fs::rename(&temp_path, &self.config_path).map_err(|e| {
+            error!(
+                path = %self.config_path.display(),
+                error = %e,
+                "Failed to replace configuration file"
+            );
+            let _ = fs::remove_file(&temp_path);
+            e
+        })?;
+        Ok(())
+    }
+
+    /// Serializes `config`, as an in-place edit of the current file when there is one
+    ///
+    /// Files of an older layout are migrated in place first, so they keep their
+    /// comments and unknown keys too.
+    fn render_config(&self, config: &Config) -> DwallResult<String> {
         let toml_string = toml::to_string(config).map_err(|e| {
             error!(error = %e, "Failed to serialize configuration");
             ConfigError::Serialization(e)
         })?;
 
-        info!(path = %self.config_path.display(), "Writing configuration file");
-        fs::write(&self.config_path, toml_string.as_bytes())?;
+        let Ok(content) = fs::read_to_string(&self.config_path) else {
+            return Ok(toml_string);
+        };
+        let Ok((mut document, _)) = parse_document(&content) else {
+            return Ok(toml_string);
+        };
+        let Ok(previous) = document_config(&document) else {
+            return Ok(toml_string);
+        };
+
+        if update_document(&mut document, &previous, config).is_none() {
+            warn!("Could not update configuration file in place, rewriting it");
+            return Ok(toml_string);
+        }
+        debug!("Updated configuration file in place");
+        Ok(document.to_string())
+    }
+
+    /// Path of the rolling backup `index`, 1 being the newest
+    fn backup_path(&self, index: usize) -> PathBuf {
+        self.config_path.with_extension(format!("toml.{index}.bak"))
+    }
+
+    /// Shifts the rolling backups by one, dropping the oldest, and copies the
+    /// configuration file to the newest
+    fn rotate_backups(&self) -> DwallResult<()> {
+        let Ok(content) = fs::read_to_string(&self.config_path) else {
+            return Ok(());
+        };
+        if let Err(e) = parse_config(&content) {
+            warn!(error = %e, "Not backing up invalid configuration file");
+            return Ok(());
+        }
+
+        for index in (1..CONFIG_BACKUP_COUNT).rev() {
+            let backup_path = self.backup_path(index);
+            if backup_path.exists() {
+                fs::rename(&backup_path, self.backup_path(index + 1))?;
+            }
+        }
+        fs::write(self.backup_path(1), content)?;
         Ok(())
     }
 }
 
+/// Parses and migrates a configuration file
+///
+/// Returns the configuration with the version the file was migrated from, if any.
+fn parse_config(content: &str) -> DwallResult<(Config, Option<u32>)> {
+    let (document, migrated_from) = parse_document(content)?;
+    Ok((document_config(&document)?, migrated_from))
+}
+
+/// Parses a configuration file and migrates it in place, keeping its formatting
+///
+/// Returns the document with the version the file was migrated from, if any.
+fn parse_document(content: &str) -> DwallResult<(DocumentMut, Option<u32>)> {
+    if content.trim().is_empty() {
+        return Err(ConfigError::Empty.into());
+    }
+
+    let mut document: DocumentMut = content.parse().map_err(|e| {
+        error!(error = %e, "Failed to parse configuration");
+        ConfigError::Syntax(e)
+    })?;
+    let migrated_from = migrate_config(&mut document)?;
+    Ok((document, migrated_from))
+}
+
+/// Deserializes the configuration of a migrated document
+fn document_config(document: &DocumentMut) -> DwallResult<Config> {
+    toml::from_str(&document.to_string()).map_err(|e| {
+        error!(error = %e, "Failed to parse configuration");
+        ConfigError::Deserialization(e).into()
+    })
+}
+
+/// Parses, migrates and validates a configuration file
+fn load_config(content: &str) -> DwallResult<(Config, Option<u32>)> {
+    let (config, migrated_from) = parse_config(content)?;
+    config.validate()?;
+    Ok((config, migrated_from))
+}
+
+/// Whether `error` means the file content is damaged, rather than unreadable or
+/// written by a newer release
+fn is_corrupt(error: &DwallError) -> bool {
+    match error {
+        DwallError::Io(e) => e.kind() == io::ErrorKind::InvalidData,
+        DwallError::Config(
+            ConfigError::Syntax(_)
+            | ConfigError::Deserialization(_)
+            | ConfigError::InvalidVersion(_)
+            | ConfigError::Validation { .. },
+        ) => true,
+        _ => false,
+    }
+}
+
+/// Writes `contents` to a new file at `path` and waits until it is on disk
+fn write_synced(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
 /// Convenience function to read the configuration file from the default location
 pub fn read_config_file() -> DwallResult<Config> {
     let mut config_manager = ConfigManager::with_config_dir(&DWALL_CONFIG_DIR);
@@ -137,3 +385,200 @@ pub fn write_config_file(config: &Config) -> DwallResult<()> {
     let mut config_manager = ConfigManager::with_config_dir(&DWALL_CONFIG_DIR);
     config_manager.write_config(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::scratch_directory;
+
+    fn config_with_interval(interval: u8) -> Config {
+        toml::from_str(&format!("interval = {interval}")).unwrap()
+    }
+
+    #[test]
+    fn backs_up_and_rewrites_migrated_files() {
+        let config_dir = scratch_directory("config-migration");
+        let legacy = fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/config/0.1.toml"),
+        )
+        .unwrap();
+        fs::write(config_dir.join("config.toml"), &legacy).unwrap();
+
+        let mut config_manager = ConfigManager::with_config_dir(&config_dir);
+        let config = config_manager.read_config().unwrap();
+        assert!(!config_manager.has_changed().unwrap());
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml.v0.bak")).unwrap(),
+            legacy
+        );
+        let rewritten = fs::read_to_string(config_dir.join("config.toml")).unwrap();
+        assert!(rewritten.contains("version = 1"), "{rewritten}");
+        assert!(!rewritten.contains("coordinate_source"), "{rewritten}");
+
+        // A second backup of the same version never replaces the first
+        fs::write(config_dir.join("config.toml"), &legacy).unwrap();
+        assert_eq!(config_manager.read_config().unwrap(), config);
+        assert!(config_dir.join("config.toml.v0.1.bak").is_file());
+
+        // With every backup name taken, the file is read but not rewritten
+        for attempt in 2..MIGRATION_BACKUP_ATTEMPTS {
+            fs::write(config_dir.join(format!("config.toml.v0.{attempt}.bak")), "").unwrap();
+        }
+        fs::write(config_dir.join("config.toml"), &legacy).unwrap();
+        assert_eq!(config_manager.read_config().unwrap(), config);
+        assert_eq!(
+            fs::read_to_string(config_dir.join("config.toml")).unwrap(),
+            legacy
+        );
+        config_manager.write_config_to_file(&config).unwrap();
+
+        // Up-to-date files are left alone
+        let modified = fs::metadata(config_dir.join("config.toml"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        config_manager.read_config().unwrap();
+        assert_eq!(
+            fs::metadata(config_dir.join("config.toml"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+
+        fs::remove_dir_all(&config_dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_hand_written_files_in_place() {
+        let config_dir = scratch_directory("config-migrate-in-place");
+        let config_path = config_dir.join("config.toml");
+        let legacy = fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/config/0.1.toml"),
+        )
+        .unwrap();
+        fs::write(
+            &config_path,
+            format!("{legacy}from_a_newer_release = true\n"),
+        )
+        .unwrap();
+
+        let mut config_manager = ConfigManager::with_config_dir(&config_dir);
+        let config = config_manager.read_config().unwrap();
+        let migrated = fs::read_to_string(&config_path).unwrap();
+        assert!(
+            migrated.starts_with(
+                "# Written by dwall 0.1.x, before the `version` field existed\nnetwork = "
+            ),
+            "{migrated}"
+        );
+        assert!(
+            migrated.contains("from_a_newer_release = true"),
+            "{migrated}"
+        );
+        assert!(migrated.contains("[position_source]"), "{migrated}");
+
+        assert_eq!(config.interval(), 30);
+        let edited: Config =
+            toml::from_str(&migrated.replace("interval = 30", "interval = 60")).unwrap();
+        config_manager.write_config(&edited).unwrap();
+        let written = fs::read_to_string(&config_path).unwrap();
+        assert_eq!(written, migrated.replace("interval = 30", "interval = 60"));
+        assert_eq!(config_manager.read_config().unwrap(), edited);
+
+        fs::remove_dir_all(&config_dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_rolling_backups_of_written_files() {
+        let config_dir = scratch_directory("config-backups");
+        let mut config_manager = ConfigManager::with_config_dir(&config_dir);
+        for interval in 1..=5 {
+            config_manager
+                .write_config(&config_with_interval(interval))
+                .unwrap();
+        }
+
+        assert_eq!(config_manager.read_config().unwrap().interval(), 5);
+        for (index, interval) in [(1, 4), (2, 3), (3, 2)] {
+            let backup = fs::read_to_string(config_manager.backup_path(index)).unwrap();
+            assert_eq!(parse_config(&backup).unwrap().0.interval(), interval);
+        }
+        assert!(!config_manager.backup_path(CONFIG_BACKUP_COUNT + 1).exists());
+        assert!(!config_dir.join("config.toml.tmp").exists());
+
+        fs::remove_dir_all(&config_dir).unwrap();
+    }
+
+    #[test]
+    fn falls_back_to_newest_valid_backup() {
+        let config_dir = scratch_directory("config-fallback");
+        let config_path = config_dir.join("config.toml");
+        let mut config_manager = ConfigManager::with_config_dir(&config_dir);
+        config_manager
+            .write_config(&config_with_interval(10))
+            .unwrap();
+        config_manager
+            .write_config(&config_with_interval(20))
+            .unwrap();
+
+        // Truncated files, whether cut mid-value or emptied, load the previous write
+        for corrupt in ["interval = ", "interval = 20\nimage_format = \"Pn", ""] {
+            fs::write(&config_path, corrupt).unwrap();
+            assert_eq!(config_manager.read_config().unwrap().interval(), 10);
+            // The corrupt file is kept for repair and never becomes a backup
+            assert_eq!(fs::read_to_string(&config_path).unwrap(), corrupt);
+        }
+
+        config_manager
+            .write_config(&config_with_interval(30))
+            .unwrap();
+        assert_eq!(config_manager.read_config().unwrap().interval(), 30);
+        let newest_backup = fs::read_to_string(config_manager.backup_path(1)).unwrap();
+        assert_eq!(parse_config(&newest_backup).unwrap().0.interval(), 10);
+
+        // Without a valid backup the original error is reported
+        fs::write(config_manager.backup_path(1), "interval = ").unwrap();
+        fs::write(
+            &config_path,
+            r#"position_source = { type = "MANUAL", latitude = 91.0, longitude = 0.0 }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config_manager.read_config(),
+            Err(DwallError::Config(ConfigError::Validation { .. }))
+        ));
+
+        fs::remove_dir_all(&config_dir).unwrap();
+    }
+
+    #[test]
+    fn edits_hand_written_files_in_place() {
+        let config_dir = scratch_directory("config-in-place");
+        let config_path = config_dir.join("config.toml");
+        let hand_written =
+            "version = 1\n\n# Slower on battery\ninterval = 60\nfrom_a_newer_release = 1\n";
+        fs::write(&config_path, hand_written).unwrap();
+
+        let mut config_manager = ConfigManager::with_config_dir(&config_dir);
+        config_manager
+            .write_config(&config_with_interval(120))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            hand_written.replace("60", "120")
+        );
+
+        // Older layouts are migrated in place
+        fs::write(&config_path, "# Legacy\ninterval = 60\n").unwrap();
+        config_manager
+            .write_config(&config_with_interval(120))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "# Legacy\ninterval = 120\nversion = 1\n"
+        );
+
+        fs::remove_dir_all(&config_dir).unwrap();
+    }
+}
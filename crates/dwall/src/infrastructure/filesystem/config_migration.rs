@@ -0,0 +1,285 @@
+//! Upgrades configuration files written by older releases
+//!
+//! Every `config.toml` carries the `version` of its layout; files written before the
+//! field existed count as version 0. [`migrate_config`] runs the steps of
+//! [`MIGRATIONS`] in order on the parsed file, so a file skipping several releases
+//! goes through each step in turn before it is deserialized.
+//!
+//! The steps edit the document in place, so comments, key order and keys this
+//! release does not know survive the upgrade.
+
+use toml_edit::{DocumentMut, Item, Key, Table, Value, value};
+
+use crate::{config::CONFIG_VERSION, error::ConfigError};
+
+/// Name of the field holding the layout version
+const VERSION_KEY: &str = "version";
+
+/// One step of the chain, upgrading a file from version `from` to `from + 1`
+struct Migration {
+    from: u32,
+    description: &'static str,
+    migrate: fn(&mut Table),
+}
+
+/// Migration steps, ordered by the version they upgrade from
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "rename keys from before 0.2.0",
+    migrate: rename_legacy_keys,
+}];
+
+/// Upgrades `document` to [`CONFIG_VERSION`]
+///
+/// Returns the version the file had when it was migrated, or `None` when it is
+/// already up to date.
+pub(crate) fn migrate_config(document: &mut DocumentMut) -> Result<Option<u32>, ConfigError> {
+    let table = document.as_table_mut();
+    let version = config_version(table)?;
+    if version > CONFIG_VERSION {
+        error!(
+            version = version,
+            supported_version = CONFIG_VERSION,
+            "Configuration file was written by a newer release"
+        );
+        return Err(ConfigError::UnsupportedVersion {
+            found: version,
+            supported: CONFIG_VERSION,
+        });
+    }
+    if version == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    for migration in MIGRATIONS.iter().filter(|step| step.from >= version) {
+        (migration.migrate)(table);
+        set_version(table, migration.from + 1);
+        info!(
+            from = migration.from,
+            to = migration.from + 1,
+            description = migration.description,
+            "Migrated configuration file"
+        );
+    }
+
+    Ok(Some(version))
+}
+
+/// Layout version of the parsed file, 0 when it has none
+fn config_version(table: &Table) -> Result<u32, ConfigError> {
+    match table.get(VERSION_KEY) {
+        None => Ok(0),
+        Some(item) => item
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| ConfigError::InvalidVersion(item.to_string().trim().to_string())),
+    }
+}
+
+/// Writes `version` into the file, keeping the comment of an existing version field
+fn set_version(table: &mut Table, version: u32) {
+    let version = Value::from(i64::from(version));
+    match table.get_mut(VERSION_KEY).and_then(Item::as_value_mut) {
+        Some(current) => {
+            let decor = current.decor().clone();
+            *current = version;
+            *current.decor_mut() = decor;
+        }
+        None => {
+            table.insert(VERSION_KEY, value(version));
+        }
+    }
+}
+
+/// Version 0 to 1: `coordinate_source`, `auto_detect_color_mode` and
+/// `github_mirror_template` were renamed, the latter into the `network` setting
+fn rename_legacy_keys(table: &mut Table) {
+    rename_key(table, "coordinate_source", "position_source");
+    rename_key(table, "auto_detect_color_mode", "auto_detect_color_scheme");
+    rename_key(table, "github_mirror_template", "network");
+}
+
+/// Moves the value of `from` to `to`, keeping `to` if the file already has both
+///
+/// The entry keeps its place and the comments above it; only its key changes.
+fn rename_key(table: &mut Table, from: &str, to: &str) {
+    if table.contains_key(to) {
+        table.remove(from);
+        return;
+    }
+
+    let order: Vec<String> = table
+        .iter()
+        .map(|(key, _)| if key == from { to } else { key }.to_string())
+        .collect();
+    let Some((key, item)) = table.remove_entry(from) else {
+        return;
+    };
+    table.insert_formatted(
+        &Key::new(to).with_leaf_decor(key.leaf_decor().clone()),
+        item,
+    );
+
+    let position = |key: &Key| order.iter().position(|name| name == key.get());
+    table.sort_values_by(|a, _, b, _| position(a).cmp(&position(b)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::{Config, ImageFormat, Network, PositionSource};
+
+    /// Parses and migrates a fixture, then deserializes it like the config manager
+    fn load_fixture(name: &str) -> (Option<u32>, Config) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/config")
+            .join(name);
+        let mut document: DocumentMut = std::fs::read_to_string(path).unwrap().parse().unwrap();
+        let migrated_from = migrate_config(&mut document).unwrap();
+        let config: Config = toml::from_str(&document.to_string()).unwrap();
+        config.validate().unwrap();
+        (migrated_from, config)
+    }
+
+    #[test]
+    fn migrations_form_a_chain_up_to_current_version() {
+        for (expected_from, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, expected_from as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn migrates_files_from_0_1() {
+        let (migrated_from, config) = load_fixture("0.1.toml");
+        assert_eq!(migrated_from, Some(0));
+        assert_eq!(config.version(), CONFIG_VERSION);
+        assert_eq!(
+            config.network(),
+            Some(&Network::GitHubMirrorTemplate(
+                "https://mirror.example.com/<repo>/<version>/<asset>".to_string()
+            ))
+        );
+        assert_eq!(
+            config.position_source(),
+            &PositionSource::Manual {
+                latitude: 39.9,
+                longitude: 116.4,
+                altitude: 875.0,
+            }
+        );
+        assert!(!config.auto_detect_color_scheme());
+        assert_eq!(config.image_format(), &ImageFormat::Png);
+        assert_eq!(config.interval(), 30);
+        assert_eq!(
+            config.monitor_specific_wallpapers().get("any monitor"),
+            Some(&"Catalina".to_string())
+        );
+    }
+
+    #[test]
+    fn migrates_files_from_0_2() {
+        let (migrated_from, config) = load_fixture("0.2.toml");
+        assert_eq!(migrated_from, Some(0));
+        assert_eq!(config.version(), CONFIG_VERSION);
+        assert_eq!(
+            config.network(),
+            Some(&Network::Socks5 {
+                host: "127.0.0.1".to_string(),
+                port: 1080,
+            })
+        );
+        assert!(matches!(
+            config.position_source(),
+            PositionSource::Automatic { .. }
+        ));
+        assert!(config.auto_detect_color_scheme());
+        assert!(!config.lock_screen_wallpaper_enabled());
+        assert_eq!(
+            config
+                .monitor_specific_wallpapers()
+                .get(r"\\?\DISPLAY#AAA#1"),
+            Some(&"Big Sur".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_current_files_and_rejects_newer_ones() {
+        let (migrated_from, config) = load_fixture("current.toml");
+        assert_eq!(migrated_from, None);
+        assert!(config.blend_wallpapers());
+        assert_eq!(config.image_format_fallbacks(), [ImageFormat::Webp]);
+
+        let mut document: DocumentMut =
+            format!("version = {}", CONFIG_VERSION + 1).parse().unwrap();
+        assert!(matches!(
+            migrate_config(&mut document),
+            Err(ConfigError::UnsupportedVersion { .. })
+        ));
+
+        let mut document: DocumentMut = "version = -1".parse().unwrap();
+        assert!(matches!(
+            migrate_config(&mut document),
+            Err(ConfigError::InvalidVersion(version)) if version == "-1"
+        ));
+    }
+
+    #[test]
+    fn newer_keys_win_over_legacy_ones() {
+        let mut document: DocumentMut = r#"
+            github_mirror_template = "https://old.example.com"
+            network = "https://new.example.com"
+            coordinate_source = { type = "AUTOMATIC" }
+            position_source = { type = "MANUAL", latitude = 1.0, longitude = 2.0 }
+        "#
+        .parse()
+        .unwrap();
+        migrate_config(&mut document).unwrap();
+
+        assert_eq!(
+            document.get("network").and_then(Item::as_str),
+            Some("https://new.example.com")
+        );
+        assert!(!document.contains_key("github_mirror_template"));
+        assert!(!document.contains_key("coordinate_source"));
+        assert_eq!(
+            document
+                .get("position_source")
+                .and_then(|source| source.get("type"))
+                .and_then(Item::as_str),
+            Some("MANUAL")
+        );
+    }
+
+    #[test]
+    fn renamed_keys_keep_their_place_and_comments() {
+        let mut document: DocumentMut = r#"# Mirror for downloads
+github_mirror_template = "https://mirror.example.com" # fast
+auto_detect_color_mode = false
+unknown = true
+
+# Home
+[coordinate_source]
+type = "AUTOMATIC"
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(migrate_config(&mut document).unwrap(), Some(0));
+
+        assert_eq!(
+            document.to_string(),
+            r#"# Mirror for downloads
+network = "https://mirror.example.com" # fast
+auto_detect_color_scheme = false
+unknown = true
+version = 1
+
+# Home
+[position_source]
+type = "AUTOMATIC"
+"#
+        );
+    }
+}
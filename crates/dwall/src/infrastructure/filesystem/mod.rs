@@ -1,4 +1,6 @@
+pub(crate) mod config_document;
 pub mod config_manager;
+pub(crate) mod config_migration;
 
 // Re-export commonly used types
 pub use config_manager::{read_config_file, write_config_file};
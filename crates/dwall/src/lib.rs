@@ -17,10 +17,15 @@ pub use lazy::{DWALL_CACHE_DIR, DWALL_CONFIG_DIR, DWALL_LOG_DIR};
 
 // Re-export domain types
 pub use domain::geography::Position;
+pub use domain::time::{
+    AtmosphericConditions, MoonPhase, SolarAlgorithm, SolarEvent, SolarEvents, SolarModel,
+};
 pub use domain::visual::{ThemeValidator, apply_solar_theme};
 
 // Re-export infrastructure types
-pub use infrastructure::display::{DisplayMonitor, DisplayMonitorProvider};
+pub use infrastructure::display::{
+    DisplayMonitor, DisplayMonitorProvider, Orientation, Resolution,
+};
 pub use infrastructure::filesystem::{read_config_file, write_config_file};
 pub use infrastructure::platform::windows::{RegistryError, RegistryKey};
 
@@ -1,3 +1,6 @@
 pub mod cache;
 pub mod helpers;
 pub mod string;
+
+#[cfg(test)]
+pub(crate) mod testing;
@@ -0,0 +1,36 @@
+//! Helpers shared by unit tests
+
+use std::{fs, path::PathBuf};
+
+/// Empty directory under the system temp directory, unique to `name` and this process
+///
+/// Tests pass a name starting with their module, such as `pack-zip`, and remove the
+/// directory when they pass; a failed test leaves it behind for inspection.
+pub(crate) fn scratch_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("dwall-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// A small PNG file of one `color`; distinct colors tell images apart
+pub(crate) fn png_image(color: [u8; 3]) -> Vec<u8> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(8, 8, image::Rgb(color))
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    png.into_inner()
+}
+
+/// Writes a zip archive at `path` holding `entries` as (name, content)
+pub(crate) fn write_zip(path: &std::path::Path, entries: &[(&str, &[u8])]) {
+    use std::io::Write;
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    for (name, content) in entries {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap();
+}
@@ -28,6 +28,9 @@ pub enum Error {
 
     #[error("Time difference would be negative")]
     NegativeDuration,
+
+    #[error("Invalid date '{0}', expected YYYY-MM-DD")]
+    InvalidDateFormat(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -340,6 +340,13 @@ impl UtcDateTime {
     pub const fn is_leap_year(&self) -> bool {
         is_leap_year(self.year())
     }
+
+    /// Returns the calendar date of this instant.
+    #[inline]
+    pub const fn date(&self) -> Date {
+        let (year, month, day) = self.ymd();
+        Date { year, month, day }
+    }
 }
 
 impl Default for UtcDateTime {
@@ -617,6 +624,8 @@ impl PartialOrd for OffsetDateTime {
     }
 }
 
+/// A calendar date without a time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Date {
     year: u16,
     month: Month,
@@ -624,6 +633,41 @@ pub struct Date {
 }
 
 impl Date {
+    /// Creates a new date, validating the day against the month and year.
+    pub fn new(year: u16, month: Month, day: u8) -> Result<Self> {
+        if year < 1970 {
+            return Err(Error::InvalidYear(year));
+        }
+        if day == 0 || day > month.days_in_month(year) {
+            return Err(Error::InvalidDay(day));
+        }
+
+        Ok(Self { year, month, day })
+    }
+
+    /// Returns the year.
+    #[inline]
+    pub const fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Returns the month.
+    #[inline]
+    pub const fn month(&self) -> Month {
+        self.month
+    }
+
+    /// Returns the day of the month.
+    #[inline]
+    pub const fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Returns midnight (00:00:00) of this date in UTC.
+    pub fn midnight_utc(&self) -> Result<UtcDateTime> {
+        UtcDateTime::new(self.year, self.month, self.day, 0, 0, 0)
+    }
+
     pub fn with_hms(&self, hour: u8, minute: u8, second: u8) -> Result<OffsetDateTime> {
         let offset = Offset::local_offset();
 
@@ -633,6 +677,43 @@ impl Date {
     }
 }
 
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}",
+            self.year,
+            self.month.as_u8(),
+            self.day
+        )
+    }
+}
+
+impl std::str::FromStr for Date {
+    type Err = Error;
+
+    /// Parses an ISO 8601 calendar date (`YYYY-MM-DD`).
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidDateFormat(s.to_string());
+
+        let mut parts = s.split('-');
+        let (Some(year), Some(month), Some(day), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return Err(invalid());
+        }
+
+        let year = year.parse().map_err(|_| invalid())?;
+        let month: u8 = month.parse().map_err(|_| invalid())?;
+        let day = day.parse().map_err(|_| invalid())?;
+
+        Date::new(year, Month::try_from(month)?, day)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -844,6 +925,33 @@ mod tests {
         assert_eq!(utc_back.timestamp(), 0);
     }
 
+    #[test]
+    fn test_date_new_and_midnight() {
+        let date = Date::new(2024, Month::February, 29).unwrap();
+        assert_eq!(date.year(), 2024);
+        assert_eq!(date.month(), Month::February);
+        assert_eq!(date.day(), 29);
+
+        let midnight = date.midnight_utc().unwrap();
+        assert_eq!(midnight.ymd_hms(), (2024, Month::February, 29, 0, 0, 0));
+        assert_eq!(midnight.date(), date);
+
+        assert!(Date::new(2023, Month::February, 29).is_err());
+        assert!(Date::new(1969, Month::December, 31).is_err());
+    }
+
+    #[test]
+    fn test_date_parse_and_display() {
+        let date: Date = "2024-06-21".parse().unwrap();
+        assert_eq!(date, Date::new(2024, Month::June, 21).unwrap());
+        assert_eq!(date.to_string(), "2024-06-21");
+
+        assert!("2024-6-21".parse::<Date>().is_err());
+        assert!("2024-13-01".parse::<Date>().is_err());
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("2024-06-21-01".parse::<Date>().is_err());
+    }
+
     #[test]
     fn test_offset_overflow_handling() {
         let offset = offset_plus_8();
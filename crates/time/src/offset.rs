@@ -25,6 +25,15 @@ impl Offset {
         }
     }
 
+    /// Creates an offset of `seconds` east of UTC
+    pub fn from_seconds(seconds: i32) -> Result<Self, ParseOffsetError> {
+        if !(-50400..=50400).contains(&seconds) {
+            return Err(ParseOffsetError::OutOfRange(seconds));
+        }
+
+        Ok(Self { seconds })
+    }
+
     pub const fn seconds(&self) -> i32 {
         self.seconds
     }
@@ -547,4 +556,14 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_from_seconds_checks_range() {
+        assert_eq!(Offset::from_seconds(-25200).unwrap().seconds(), -25200);
+        assert_eq!(Offset::from_seconds(50400).unwrap().seconds(), 50400);
+        assert!(matches!(
+            Offset::from_seconds(50401),
+            Err(ParseOffsetError::OutOfRange(50401))
+        ));
+    }
 }
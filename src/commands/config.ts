@@ -5,4 +5,7 @@ export const readConfigFile = async () => invoke<Config>("read_config_file");
 export const writeConfigFile = async (config: Config) =>
   invoke<Config>("write_config_file", { config });
 
+export const validateConfig = async (config: Config) =>
+  invoke<ConfigIssue[]>("validate_config_cmd", { config });
+
 export const openConfigDir = async () => invoke<void>("open_config_dir");
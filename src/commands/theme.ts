@@ -1,6 +1,13 @@
 import { invoke } from "@tauri-apps/api/core";
 import type { ThemeID } from "~/themes";
-import type { CustomizedTheme } from "~/types";
+import type {
+  CustomizedTheme,
+  GnomeExportOptions,
+  ImageCheck,
+  IntegrityReport,
+  ThemeManifest,
+  ValidationReport,
+} from "~/types";
 
 export const validateTheme = async (
   themesDirectory: string,
@@ -15,6 +22,35 @@ export const validateTheme = async (
     imageFormat,
   });
 
+export const getThemeValidationReport = async (
+  themesDirectory: string,
+  themeId: string,
+  imageFormat: CustomizedTheme["image_format"],
+  isCustomized = false,
+  imageCheck: ImageCheck = "headers",
+) =>
+  invoke<ValidationReport>("get_theme_validation_report_cmd", {
+    themesDirectory,
+    themeId,
+    isCustomized,
+    imageFormat,
+    imageCheck,
+  });
+
+export const packCustomizedTheme = async (
+  customizedThemesDirectory: string,
+  themeId: string,
+  zipPath: string,
+) =>
+  invoke<ThemeManifest>("pack_customized_theme_cmd", {
+    customizedThemesDirectory,
+    themeId,
+    zipPath,
+  });
+
+export const verifyTheme = async (themesDirectory: string, themeId: string) =>
+  invoke<IntegrityReport>("verify_theme_cmd", { themesDirectory, themeId });
+
 export const applyTheme = async (config: Config) =>
   invoke<void>("apply_theme_cmd", { config });
 
@@ -47,3 +83,82 @@ export const getCustomizedThemes = async (customizedThemesDirectory: string) =>
   invoke<CustomizedTheme[]>("get_customized_themes_cmd", {
     customizedThemesDirectory,
   });
+
+export const importDdwTheme = async (
+  ddwPath: string,
+  customizedThemesDirectory: string,
+) =>
+  invoke<string>("import_ddw_theme_cmd", {
+    ddwPath,
+    customizedThemesDirectory,
+  });
+
+export const writeHeicSolarConfig = async (
+  heicPath: string,
+  solarConfigPath: string,
+) =>
+  invoke<void>("write_heic_solar_config_cmd", { heicPath, solarConfigPath });
+
+export const importHeicTheme = async (
+  heicPath: string,
+  framesDirectory: string,
+  customizedThemesDirectory: string,
+) =>
+  invoke<string>("import_heic_theme_cmd", {
+    heicPath,
+    framesDirectory,
+    customizedThemesDirectory,
+  });
+
+export const importGnomeBackground = async (
+  xmlPath: string,
+  customizedThemesDirectory: string,
+) =>
+  invoke<string>("import_gnome_background_cmd", {
+    xmlPath,
+    customizedThemesDirectory,
+  });
+
+export const exportGnomeBackground = async (options: GnomeExportOptions) =>
+  invoke<void>("export_gnome_background_cmd", { options });
+
+export const importKdeWallpaper = async (
+  packagePath: string,
+  customizedThemesDirectory: string,
+) =>
+  invoke<string>("import_kde_wallpaper_cmd", {
+    packagePath,
+    customizedThemesDirectory,
+  });
+
+export const exportKdeWallpaper = async (
+  themesDirectory: string,
+  themeId: string,
+  isCustomized: boolean,
+  imageFormat: CustomizedTheme["image_format"],
+  packageDirectory: string,
+) =>
+  invoke<void>("export_kde_wallpaper_cmd", {
+    themesDirectory,
+    themeId,
+    isCustomized,
+    imageFormat,
+    packageDirectory,
+  });
+
+export const importPhotoTheme = async (
+  photosDirectory: string,
+  themeName: string,
+  author: string,
+  customizedThemesDirectory: string,
+  latitude?: number,
+  longitude?: number,
+) =>
+  invoke<string>("import_photo_theme_cmd", {
+    photosDirectory,
+    themeName,
+    author,
+    latitude,
+    longitude,
+    customizedThemesDirectory,
+  });
@@ -1,4 +1,66 @@
-export type ImageFormat = "jpeg" | "png";
+export type ImageFormat = "jpeg" | "png" | "webp" | "avif" | "jxl";
+
+export type ValidationSeverity = "warning" | "error";
+
+export type ImageCheck = "headers" | "decode";
+
+export type ValidationIssue =
+  | { kind: "theme_directory_not_found"; path: string }
+  | { kind: "configuration_missing" }
+  | { kind: "configuration_unreadable"; file: string; reason: string }
+  | { kind: "empty_configuration"; file: string }
+  | {
+      kind: "invalid_solar_angle";
+      index: number;
+      field: "altitude" | "azimuth";
+      value: number | null;
+    }
+  | { kind: "duplicate_index"; index: number; occurrences: number }
+  | { kind: "images_directory_missing"; path: string }
+  | { kind: "missing_image"; index: number; path: string }
+  | {
+      kind: "image_format_mismatch";
+      index: number;
+      path: string;
+      expected: string;
+    }
+  | {
+      kind: "image_content_mismatch";
+      index: number;
+      path: string;
+      actual: string;
+      expected: string;
+    }
+  | { kind: "corrupt_image"; index: number; path: string; reason: string }
+  | {
+      kind: "inconsistent_resolution";
+      index: number;
+      path: string;
+      width: number;
+      height: number;
+      expected_width: number;
+      expected_height: number;
+    }
+  | { kind: "unreferenced_image"; path: string };
+
+export type ValidationItem = ValidationIssue & {
+  severity: ValidationSeverity;
+  message: string;
+};
+
+export interface ImageDetails {
+  index: number;
+  path: string;
+  format: string;
+  width: number;
+  height: number;
+}
+
+export interface ValidationReport {
+  theme_id: string;
+  items: ValidationItem[];
+  images: ImageDetails[];
+}
 
 export interface CustomizedTheme {
   id: string;
@@ -8,4 +70,42 @@ export interface CustomizedTheme {
   author: string;
   thumbnails: string[];
   version: number;
+  license?: string;
+}
+
+export interface GnomeExportOptions {
+  themes_directory: string;
+  theme_id: string;
+  is_customized: boolean;
+  image_format: ImageFormat;
+  latitude: number;
+  longitude: number;
+  date: string;
+  xml_path: string;
+}
+
+export interface ThemeManifest {
+  name: string;
+  author: string;
+  version: number;
+  license?: string;
+  image_formats: ImageFormat[];
+  min_dwall_version: string;
+  files: Record<string, string>;
+}
+
+export type IntegrityIssue =
+  | { kind: "missing_file"; path: string }
+  | { kind: "modified_file"; path: string; expected: string; actual: string }
+  | { kind: "unlisted_file"; path: string }
+  | { kind: "unsupported_dwall_version"; required: string; current: string };
+
+export type IntegrityItem = IntegrityIssue & {
+  severity: ValidationSeverity;
+  message: string;
+};
+
+export interface IntegrityReport {
+  manifest: ThemeManifest;
+  items: IntegrityItem[];
 }
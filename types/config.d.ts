@@ -8,19 +8,44 @@ interface Socks5 {
 type Network = GithubMirrorTemplate | Socks5;
 
 interface Config {
+  /** Layout version of `config.toml`, older files are migrated when read */
+  version: number;
   network?: Network;
   selected_theme_id?: string;
   interval: number;
   image_format: string;
+  /** Formats tried, in order, for themes without images in `image_format` */
+  image_format_fallbacks: string[];
+  /** Convert theme images without a preferred format into the cache */
+  convert_image_formats: boolean;
   themes_directory: string;
   customized_themes_directory: string;
   position_source: PositionSource;
+  atmosphere?: Atmosphere;
+  /** Meters above the terrain forming the horizon, not above sea level */
+  horizon_height?: number;
+  solar_algorithm?: SolarAlgorithm;
   auto_detect_color_scheme: boolean;
   lock_screen_wallpaper_enabled: boolean;
+  blend_wallpapers?: boolean;
+  /** Degrees another image must be closer to the sun before replacing the current one, unused when blending */
+  image_switch_margin?: number;
   monitor_specific_wallpapers: string | Record<string, string>;
   title_bar_color_follows_windows_theme: boolean;
 }
 
+interface Atmosphere {
+  /** Surface air pressure in hPa, estimated from altitude when omitted */
+  pressure?: number;
+  /** Surface air temperature in °C */
+  temperature?: number;
+}
+
+type SolarAlgorithm =
+  | { type: "STANDARD" }
+  /** `delta_t` is TT − UT in seconds, estimated from the date when omitted */
+  | { type: "SPA"; delta_t?: number };
+
 interface PositionSourceAutomatic {
   type: "AUTOMATIC";
   update_on_each_calculation?: boolean;
@@ -35,3 +60,10 @@ interface PositionSourceManual {
 }
 
 type PositionSource = PositionSourceAutomatic | PositionSourceManual;
+
+interface ConfigIssue {
+  severity: "warning" | "error";
+  /** Keys leading to the setting, such as `["position_source", "latitude"]` */
+  field: string[];
+  message: string;
+}
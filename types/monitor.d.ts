@@ -1,6 +1,14 @@
+interface Resolution {
+  width: number;
+  height: number;
+}
+
 interface MonitorInfo {
   device_path: string;
   friendly_name: string;
+  /** Desktop size with rotation applied, missing when the system does not report it */
+  resolution?: Resolution;
+  orientation?: "landscape" | "portrait";
 }
 
 interface MonitorItem {